@@ -139,6 +139,12 @@ fn deserialize_features(features: &wgpu_types::Features) -> Vec<&'static str> {
     if features.contains(wgpu_types::Features::TIMESTAMP_QUERY) {
         return_features.push("timestamp-query");
     }
+    if features.contains(wgpu_types::Features::DEPTH32FLOAT_STENCIL8) {
+        return_features.push("depth32float-stencil8");
+    }
+    if features.contains(wgpu_types::Features::INDIRECT_FIRST_INSTANCE) {
+        return_features.push("indirect-first-instance");
+    }
 
     // extended from spec
     if features.contains(wgpu_types::Features::MAPPABLE_PRIMARY_BUFFERS) {
@@ -208,6 +214,24 @@ fn deserialize_features(features: &wgpu_types::Features) -> Vec<&'static str> {
     if features.contains(wgpu_types::Features::SHADER_PRIMITIVE_INDEX) {
         return_features.push("shader-primitive-index");
     }
+    if features.contains(wgpu_types::Features::PRECISE_OCCLUSION_QUERY) {
+        return_features.push("precise-occlusion-query");
+    }
+    if features.contains(wgpu_types::Features::SHADER_F16) {
+        return_features.push("shader-f16");
+    }
+    if features.contains(wgpu_types::Features::RG11B10UFLOAT_RENDERABLE) {
+        return_features.push("rg11b10ufloat-renderable");
+    }
+    if features.contains(wgpu_types::Features::RASTER_ORDER_GROUPS) {
+        return_features.push("raster-order-groups");
+    }
+    if features.contains(wgpu_types::Features::SUBGROUP) {
+        return_features.push("subgroup");
+    }
+    if features.contains(wgpu_types::Features::TEXTURE_FORMAT_16BIT_NORM) {
+        return_features.push("texture-format-16bit-norm");
+    }
 
     return_features
 }
@@ -320,6 +344,12 @@ impl From<GpuRequiredFeatures> for wgpu_types::Features {
         if required_features.0.contains("timestamp-query") {
             features.set(wgpu_types::Features::TIMESTAMP_QUERY, true);
         }
+        if required_features.0.contains("depth32float-stencil8") {
+            features.set(wgpu_types::Features::DEPTH32FLOAT_STENCIL8, true);
+        }
+        if required_features.0.contains("indirect-first-instance") {
+            features.set(wgpu_types::Features::INDIRECT_FIRST_INSTANCE, true);
+        }
 
         // extended from spec
         if required_features.0.contains("mappable-primary-buffers") {
@@ -406,6 +436,24 @@ impl From<GpuRequiredFeatures> for wgpu_types::Features {
         if required_features.0.contains("shader-primitive-index") {
             features.set(wgpu_types::Features::SHADER_PRIMITIVE_INDEX, true);
         }
+        if required_features.0.contains("precise-occlusion-query") {
+            features.set(wgpu_types::Features::PRECISE_OCCLUSION_QUERY, true);
+        }
+        if required_features.0.contains("shader-f16") {
+            features.set(wgpu_types::Features::SHADER_F16, true);
+        }
+        if required_features.0.contains("rg11b10ufloat-renderable") {
+            features.set(wgpu_types::Features::RG11B10UFLOAT_RENDERABLE, true);
+        }
+        if required_features.0.contains("raster-order-groups") {
+            features.set(wgpu_types::Features::RASTER_ORDER_GROUPS, true);
+        }
+        if required_features.0.contains("subgroup") {
+            features.set(wgpu_types::Features::SUBGROUP, true);
+        }
+        if required_features.0.contains("texture-format-16bit-norm") {
+            features.set(wgpu_types::Features::TEXTURE_FORMAT_16BIT_NORM, true);
+        }
 
         features
     }
@@ -317,6 +317,10 @@ impl RenderBundleEncoder {
                         .unwrap();
                     check_buffer_usage(buffer.usage, wgt::BufferUsages::VERTEX)
                         .map_pass_err(scope)?;
+                    if offset % wgt::VERTEX_BUFFER_OFFSET_ALIGNMENT != 0 {
+                        return Err(RenderCommandError::UnalignedVertexBufferOffset(offset))
+                            .map_pass_err(scope);
+                    }
 
                     let end = match size {
                         Some(s) => offset + s.get(),
@@ -479,7 +483,7 @@ impl RenderBundleEncoder {
 
                     buffer_memory_init_actions.extend(buffer.initialization_status.create_action(
                         buffer_id,
-                        offset..(offset + mem::size_of::<wgt::DrawIndirectArgs>() as u64),
+                        offset..(offset + mem::size_of::<wgt::DrawIndexedIndirectArgs>() as u64),
                         MemoryInitKind::NeedsInitializedMemory,
                     ));
 
@@ -113,6 +113,8 @@ pub struct ComputePassDescriptor<'a> {
 pub enum DispatchError {
     #[error("compute pipeline must be set")]
     MissingPipeline,
+    #[error("each dispatch group size dimension ({current:?}) must be less or equal to {limit}")]
+    InvalidGroupSize { current: [u32; 3], limit: u32 },
     #[error("current compute pipeline has a layout which is incompatible with a currently set bind group, first differing at entry index {index}")]
     IncompatibleBindGroup {
         index: u32,
@@ -504,6 +506,14 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     };
 
                     state.is_ready().map_pass_err(scope)?;
+                    let limit = device.limits.max_compute_workgroups_per_dimension;
+                    if groups.iter().any(|&count| count > limit) {
+                        return Err(DispatchError::InvalidGroupSize {
+                            current: groups,
+                            limit,
+                        })
+                        .map_pass_err(scope);
+                    }
                     state
                         .flush_states(
                             raw,
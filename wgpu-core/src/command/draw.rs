@@ -67,6 +67,11 @@ pub enum RenderCommandError {
     BindGroupIndexOutOfRange { index: u8, max: u32 },
     #[error("dynamic buffer offset {0} does not respect device's requested `{1}` limit {2}")]
     UnalignedBufferOffset(u64, &'static str, u32),
+    #[error(
+        "vertex buffer offset {0} is not a multiple of {}",
+        wgt::VERTEX_BUFFER_OFFSET_ALIGNMENT
+    )]
+    UnalignedVertexBufferOffset(BufferAddress),
     #[error("number of buffer offsets ({actual}) does not match the number of dynamic bindings ({expected})")]
     InvalidDynamicOffsetCount { actual: usize, expected: usize },
     #[error("render pipeline {0:?} is invalid")]
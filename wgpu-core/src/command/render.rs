@@ -412,6 +412,11 @@ pub enum RenderPassErrorInner {
     InvalidResolveSourceSampleCount,
     #[error("resolve target must have a sample count of 1")]
     InvalidResolveTargetSampleCount,
+    #[error("resolve source format {src:?} must match the resolve target format {dst:?}")]
+    MismatchedResolveTextureFormat {
+        src: wgt::TextureFormat,
+        dst: wgt::TextureFormat,
+    },
     #[error("surface texture is dropped before the render pass is finished")]
     SurfaceTextureDropped,
     #[error("not enough memory left")]
@@ -658,6 +663,12 @@ impl<'a, A: HalApi> RenderPassInfo<'a, A> {
                 if resolve_view.samples != 1 {
                     return Err(RenderPassErrorInner::InvalidResolveTargetSampleCount);
                 }
+                if color_view.desc.format != resolve_view.desc.format {
+                    return Err(RenderPassErrorInner::MismatchedResolveTextureFormat {
+                        src: color_view.desc.format,
+                        dst: resolve_view.desc.format,
+                    });
+                }
 
                 render_attachments
                     .push(resolve_view.to_render_attachment(hal::TextureUses::COLOR_TARGET));
@@ -707,6 +718,7 @@ impl<'a, A: HalApi> RenderPassInfo<'a, A> {
             sample_count,
             color_attachments: &colors,
             depth_stencil_attachment: depth_stencil,
+            occlusion_query_set: None,
         };
         unsafe {
             cmd_buf.encoder.raw.begin_render_pass(&hal_desc);
@@ -1097,6 +1109,10 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             .map_pass_err(scope)?;
                         check_buffer_usage(buffer.usage, BufferUsages::VERTEX)
                             .map_pass_err(scope)?;
+                        if offset % wgt::VERTEX_BUFFER_OFFSET_ALIGNMENT != 0 {
+                            return Err(RenderCommandError::UnalignedVertexBufferOffset(offset))
+                                .map_pass_err(scope);
+                        }
                         let buf_raw = buffer
                             .raw
                             .as_ref()
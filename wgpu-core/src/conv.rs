@@ -15,7 +15,12 @@ pub fn is_valid_copy_src_texture_format(format: wgt::TextureFormat) -> bool {
 pub fn is_valid_copy_dst_texture_format(format: wgt::TextureFormat) -> bool {
     use wgt::TextureFormat as Tf;
     match format {
-        Tf::Depth32Float | Tf::Depth24Plus | Tf::Depth24PlusStencil8 => false,
+        Tf::Stencil8
+        | Tf::Depth16Unorm
+        | Tf::Depth32Float
+        | Tf::Depth24Plus
+        | Tf::Depth24PlusStencil8
+        | Tf::Depth32FloatStencil8 => false,
         _ => true,
     }
 }
@@ -143,7 +148,12 @@ pub fn check_texture_dimension_size(
             return Err(Tde::Zero(dim));
         }
         if given > limit {
-            return Err(Tde::LimitExceeded { dim, given, limit });
+            // The depth of 1D and 2D textures is their number of layers,
+            // which has its own limit.
+            return Err(match (dimension, dim) {
+                (D1, Ted::Z) | (D2, Ted::Z) => Tde::LayerLimitExceeded { given, limit },
+                _ => Tde::LimitExceeded { dim, given, limit },
+            });
         }
     }
     if sample_size == 0 || sample_size > sample_limit || !is_power_of_two(sample_size) {
@@ -577,6 +577,17 @@ impl<A: HalApi> Device<A> {
             ));
         }
 
+        if desc.sample_count > 1
+            && desc.usage.contains(wgt::TextureUsages::STORAGE_BINDING)
+            && !format_features
+                .flags
+                .contains(wgt::TextureFormatFeatureFlags::STORAGE_MULTISAMPLE)
+        {
+            return Err(resource::CreateTextureError::MultisampledStorage(
+                desc.format,
+            ));
+        }
+
         conv::check_texture_dimension_size(
             desc.dimension,
             desc.size,
@@ -870,7 +881,11 @@ impl<A: HalApi> Device<A> {
             None
         };
 
-        //TODO: check for wgt::DownlevelFlags::COMPARISON_SAMPLERS
+        // Sampler objects carry their comparison function, so plain
+        // `COMPARISON_SAMPLERS` support isn't enough for them.
+        if desc.compare.is_some() {
+            self.require_downlevel_flags(wgt::DownlevelFlags::MUTABLE_COMPARISON_SAMPLERS)?;
+        }
 
         let hal_desc = hal::SamplerDescriptor {
             label: desc.label.borrow_option(),
@@ -948,7 +963,8 @@ impl<A: HalApi> Device<A> {
         );
         let info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), caps)
             .validate(&module)?;
-        let interface = validation::Interface::new(&module, &info, self.features);
+        let interface =
+            validation::Interface::new(&module, &info, self.features, self.limits.clone());
         let hal_shader = hal::ShaderInput::Naga(hal::NagaShader { module, info });
 
         let hal_desc = hal::ShaderModuleDescriptor {
@@ -1158,6 +1174,9 @@ impl<A: HalApi> Device<A> {
                 && entry.visibility.contains(wgt::ShaderStages::FRAGMENT)
             {
                 required_downlevel_flags |= wgt::DownlevelFlags::FRAGMENT_WRITABLE_STORAGE;
+                if entry.visibility.contains(wgt::ShaderStages::VERTEX) {
+                    required_downlevel_flags |= wgt::DownlevelFlags::CROSS_STAGE_WRITABLE_STORAGE;
+                }
             }
 
             self.require_features(required_features)
@@ -1608,6 +1627,12 @@ impl<A: HalApi> Device<A> {
                 multisampled,
             } => {
                 use wgt::TextureSampleType as Tst;
+                // Stencil views of depth-stencil formats hold unsigned integers.
+                let view_sample_type = if view.desc.aspects() == hal::FormatAspects::STENCIL {
+                    Tst::Uint
+                } else {
+                    format_info.sample_type
+                };
                 if multisampled != (view.samples != 1) {
                     return Err(Error::InvalidTextureMultisample {
                         binding,
@@ -1615,7 +1640,7 @@ impl<A: HalApi> Device<A> {
                         view_samples: view.samples,
                     });
                 }
-                match (sample_type, format_info.sample_type, view.format_features.filterable) {
+                match (sample_type, view_sample_type, view.format_features.filterable) {
                     (Tst::Uint, Tst::Uint, ..) |
                     (Tst::Sint, Tst::Sint, ..) |
                     (Tst::Depth, Tst::Depth, ..) |
@@ -2145,6 +2170,17 @@ impl<A: HalApi> Device<A> {
         }
 
         if let Some(ds) = depth_stencil_state {
+            if ds.bias.clamp != 0.0
+                && !self
+                    .downlevel
+                    .flags
+                    .contains(wgt::DownlevelFlags::DEPTH_BIAS_CLAMP)
+            {
+                log::warn!(
+                    "Depth bias clamp {} is ignored by this adapter",
+                    ds.bias.clamp
+                );
+            }
             let error = loop {
                 if !self
                     .describe_format_features(adapter, ds.format)?
@@ -2417,6 +2453,14 @@ impl<A: HalApi> Device<A> {
             .contains(wgt::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
         {
             Ok(adapter.get_texture_format_features(format))
+        } else if format == TextureFormat::Rg11b10Float
+            && self
+                .features
+                .contains(wgt::Features::RG11B10UFLOAT_RENDERABLE)
+        {
+            let mut features = format_desc.guaranteed_format_features;
+            features.allowed_usages |= wgt::TextureUsages::RENDER_ATTACHMENT;
+            Ok(features)
         } else {
             Ok(format_desc.guaranteed_format_features)
         }
@@ -4360,11 +4404,21 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 );
             }
             if !caps.present_modes.contains(&config.present_mode) {
+                // Surfaces without a display to sync to may not support FIFO.
+                let fallback = if caps.present_modes.contains(&wgt::PresentMode::Fifo) {
+                    wgt::PresentMode::Fifo
+                } else {
+                    caps.present_modes
+                        .first()
+                        .copied()
+                        .unwrap_or(wgt::PresentMode::Fifo)
+                };
                 log::warn!(
-                    "Surface does not support present mode: {:?}, falling back to FIFO",
+                    "Surface does not support present mode: {:?}, falling back to {:?}",
                     config.present_mode,
+                    fallback,
                 );
-                config.present_mode = wgt::PresentMode::Fifo;
+                config.present_mode = fallback;
             }
             if !caps.formats.contains(&config.format) {
                 return Err(E::UnsupportedFormat {
@@ -4406,9 +4460,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Err(_) => break E::InvalidSurface,
             };
 
+            let adapter = &adapter_guard[device.adapter_id.value];
             let caps = unsafe {
                 let suf = A::get_surface(surface);
-                let adapter = &adapter_guard[device.adapter_id.value];
                 match adapter.raw.adapter.surface_capabilities(&suf.raw) {
                     Some(caps) => caps,
                     None => break E::UnsupportedQueueFamily,
@@ -4434,6 +4488,19 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             if let Err(error) = validate_surface_configuraiton(&mut hal_config, &caps) {
                 break error;
             }
+            // Writing to the surface textures from shaders also needs the format to support it.
+            if config.usage.contains(wgt::TextureUsages::STORAGE_BINDING) {
+                let storage_supported = device
+                    .describe_format_features(adapter, config.format)
+                    .map_or(false, |features| {
+                        features
+                            .allowed_usages
+                            .contains(wgt::TextureUsages::STORAGE_BINDING)
+                    });
+                if !storage_supported {
+                    break E::UnsupportedStorageFormat(config.format);
+                }
+            }
 
             match unsafe {
                 A::get_surface_mut(surface)
@@ -61,9 +61,15 @@ fn check_limits(requested: &wgt::Limits, allowed: &wgt::Limits) -> Vec<FailedLim
     compare!(max_vertex_buffers, Less);
     compare!(max_vertex_attributes, Less);
     compare!(max_vertex_buffer_array_stride, Less);
+    compare!(max_inter_stage_shader_components, Less);
     compare!(max_push_constant_size, Less);
     compare!(min_uniform_buffer_offset_alignment, Greater);
     compare!(min_storage_buffer_offset_alignment, Greater);
+    compare!(max_compute_invocations_per_workgroup, Less);
+    compare!(max_compute_workgroup_size_x, Less);
+    compare!(max_compute_workgroup_size_y, Less);
+    compare!(max_compute_workgroup_size_z, Less);
+    compare!(max_compute_workgroups_per_dimension, Less);
     failed
 }
 
@@ -257,6 +263,10 @@ impl<A: HalApi> Adapter<A> {
             wgt::TextureFormatFeatureFlags::STORAGE_READ_WRITE,
             caps.contains(Tfc::STORAGE_READ_WRITE),
         );
+        flags.set(
+            wgt::TextureFormatFeatureFlags::STORAGE_MULTISAMPLE,
+            caps.contains(Tfc::STORAGE_MULTISAMPLE),
+        );
 
         // We are currently taking the filtering and blending together,
         // but we may reconsider this in the future if there are formats
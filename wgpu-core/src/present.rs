@@ -75,6 +75,8 @@ pub enum ConfigureSurfaceError {
     },
     #[error("requested usage is not supported")]
     UnsupportedUsage,
+    #[error("requested format {0:?} can't be used as a storage texture")]
+    UnsupportedStorageFormat(wgt::TextureFormat),
 }
 
 #[repr(C)]
@@ -1,5 +1,5 @@
 use crate::{
-    device::{DeviceError, HostMap, MissingFeatures},
+    device::{DeviceError, HostMap, MissingDownlevelFlags, MissingFeatures},
     hub::{Global, GlobalIdentityHandlerFactory, HalApi, Resource, Token},
     id::{DeviceId, SurfaceId, TextureId, Valid},
     init_tracker::BufferInitTracker,
@@ -227,6 +227,8 @@ pub enum TextureDimensionError {
         given: u32,
         limit: u32,
     },
+    #[error("Array layer count {given} exceeds the limit of {limit}")]
+    LayerLimitExceeded { given: u32, limit: u32 },
     #[error("sample count {0} is invalid")]
     InvalidSampleCount(u32),
 }
@@ -245,6 +247,8 @@ pub enum CreateTextureError {
     InvalidMipLevelCount(u32),
     #[error("The texture usages {0:?} are not allowed on a texture of type {1:?}")]
     InvalidUsages(wgt::TextureUsages, wgt::TextureFormat),
+    #[error("Multisampled textures of format {0:?} can't be used as storage")]
+    MultisampledStorage(wgt::TextureFormat),
     #[error("Texture format {0:?} can't be used")]
     MissingFeatures(wgt::TextureFormat, #[source] MissingFeatures),
 }
@@ -429,6 +433,9 @@ pub enum CreateSamplerError {
     /// AddressMode::ClampToBorder requires feature ADDRESS_MODE_CLAMP_TO_BORDER.
     #[error(transparent)]
     MissingFeatures(#[from] MissingFeatures),
+    /// Comparison samplers require MUTABLE_COMPARISON_SAMPLERS downlevel flag.
+    #[error(transparent)]
+    MissingDownlevelFlags(#[from] MissingDownlevelFlags),
 }
 
 impl<A: hal::Api> Resource for Sampler<A> {
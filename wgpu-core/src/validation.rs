@@ -34,6 +34,16 @@ enum NumericDimension {
     Matrix(naga::VectorSize, naga::VectorSize),
 }
 
+impl NumericDimension {
+    fn num_components(&self) -> u32 {
+        match *self {
+            Self::Scalar => 1,
+            Self::Vector(size) => size as u32,
+            Self::Matrix(columns, rows) => columns as u32 * rows as u32,
+        }
+    }
+}
+
 impl fmt::Display for NumericDimension {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -108,6 +118,7 @@ struct EntryPoint {
 #[derive(Debug)]
 pub struct Interface {
     features: wgt::Features,
+    limits: wgt::Limits,
     resources: naga::Arena<Resource>,
     entry_points: FastHashMap<(naga::ShaderStage, String), EntryPoint>,
 }
@@ -238,6 +249,8 @@ pub enum StageError {
         #[source]
         error: InputError,
     },
+    #[error("shader passes {used} components between stages, but the limit is {limit}")]
+    TooManyInterStageComponents { used: u32, limit: u32 },
 }
 
 fn map_storage_format_to_naga(format: wgt::TextureFormat) -> Option<naga::StorageFormat> {
@@ -647,14 +660,20 @@ impl NumericType {
         use wgt::TextureFormat as Tf;
 
         let (dim, kind) = match format {
-            Tf::R8Unorm | Tf::R8Snorm | Tf::R16Float | Tf::R32Float => {
-                (NumericDimension::Scalar, Sk::Float)
-            }
+            Tf::R8Unorm
+            | Tf::R8Snorm
+            | Tf::R16Float
+            | Tf::R16Unorm
+            | Tf::R16Snorm
+            | Tf::R32Float => (NumericDimension::Scalar, Sk::Float),
             Tf::R8Uint | Tf::R16Uint | Tf::R32Uint => (NumericDimension::Scalar, Sk::Uint),
             Tf::R8Sint | Tf::R16Sint | Tf::R32Sint => (NumericDimension::Scalar, Sk::Sint),
-            Tf::Rg8Unorm | Tf::Rg8Snorm | Tf::Rg16Float | Tf::Rg32Float => {
-                (NumericDimension::Vector(Vs::Bi), Sk::Float)
-            }
+            Tf::Rg8Unorm
+            | Tf::Rg8Snorm
+            | Tf::Rg16Float
+            | Tf::Rg16Unorm
+            | Tf::Rg16Snorm
+            | Tf::Rg32Float => (NumericDimension::Vector(Vs::Bi), Sk::Float),
             Tf::Rg8Uint | Tf::Rg16Uint | Tf::Rg32Uint => {
                 (NumericDimension::Vector(Vs::Bi), Sk::Uint)
             }
@@ -668,15 +687,22 @@ impl NumericType {
             | Tf::Bgra8UnormSrgb
             | Tf::Rgb10a2Unorm
             | Tf::Rgba16Float
+            | Tf::Rgba16Unorm
+            | Tf::Rgba16Snorm
             | Tf::Rgba32Float => (NumericDimension::Vector(Vs::Quad), Sk::Float),
-            Tf::Rgba8Uint | Tf::Rgba16Uint | Tf::Rgba32Uint => {
+            Tf::Rgba8Uint | Tf::Rgb10a2Uint | Tf::Rgba16Uint | Tf::Rgba32Uint => {
                 (NumericDimension::Vector(Vs::Quad), Sk::Uint)
             }
             Tf::Rgba8Sint | Tf::Rgba16Sint | Tf::Rgba32Sint => {
                 (NumericDimension::Vector(Vs::Quad), Sk::Sint)
             }
             Tf::Rg11b10Float => (NumericDimension::Vector(Vs::Tri), Sk::Float),
-            Tf::Depth32Float | Tf::Depth24Plus | Tf::Depth24PlusStencil8 => {
+            Tf::Stencil8
+            | Tf::Depth16Unorm
+            | Tf::Depth32Float
+            | Tf::Depth24Plus
+            | Tf::Depth24PlusStencil8
+            | Tf::Depth32FloatStencil8 => {
                 panic!("Unexpected depth format")
             }
             Tf::Rgb9e5Ufloat => (NumericDimension::Vector(Vs::Tri), Sk::Float),
@@ -850,6 +876,7 @@ impl Interface {
         module: &naga::Module,
         info: &naga::valid::ModuleInfo,
         features: wgt::Features,
+        limits: wgt::Limits,
     ) -> Self {
         let mut resources = naga::Arena::new();
         let mut resource_mapping = FastHashMap::default();
@@ -925,6 +952,7 @@ impl Interface {
 
         Self {
             features,
+            limits,
             resources,
             entry_points,
         }
@@ -1086,6 +1114,26 @@ impl Interface {
             }
         }
 
+        // check the components passed between the vertex and fragment stages
+        let varyings = match shader_stage {
+            naga::ShaderStage::Vertex => Some(&entry_point.outputs),
+            naga::ShaderStage::Fragment => Some(&entry_point.inputs),
+            naga::ShaderStage::Compute => None,
+        };
+        if let Some(varyings) = varyings {
+            let used = varyings
+                .iter()
+                .map(|varying| match *varying {
+                    Varying::Local { ref iv, .. } => iv.ty.dim.num_components(),
+                    Varying::BuiltIn(_) => 0,
+                })
+                .sum::<u32>();
+            let limit = self.limits.max_inter_stage_shader_components;
+            if used > limit {
+                return Err(StageError::TooManyInterStageComponents { used, limit });
+            }
+        }
+
         let outputs = entry_point
             .outputs
             .iter()
@@ -10,6 +10,33 @@ pub mod db {
     pub mod nvidia {
         pub const VENDOR: u32 = 0x10DE;
     }
+    pub mod apple {
+        pub const VENDOR: u32 = 0x106B;
+    }
+    pub mod arm {
+        pub const VENDOR: u32 = 0x13B5;
+    }
+    pub mod broadcom {
+        pub const VENDOR: u32 = 0x14E4;
+    }
+    pub mod imgtec {
+        pub const VENDOR: u32 = 0x1010;
+    }
+    pub mod qualcomm {
+        pub const VENDOR: u32 = 0x5143;
+    }
+
+    /// Returns `true` if all the GPUs of `vendor` are tile-based.
+    pub fn is_tile_based(vendor: u32) -> bool {
+        [
+            apple::VENDOR,
+            arm::VENDOR,
+            broadcom::VENDOR,
+            imgtec::VENDOR,
+            qualcomm::VENDOR,
+        ]
+        .contains(&vendor)
+    }
 }
 
 pub fn map_naga_stage(stage: naga::ShaderStage) -> wgt::ShaderStages {
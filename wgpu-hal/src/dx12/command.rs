@@ -682,7 +682,10 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
 
         self.pass.resolves.clear();
         for (rtv, cat) in color_views.iter().zip(desc.color_attachments.iter()) {
-            if !cat.ops.contains(crate::AttachmentOps::LOAD) {
+            if !cat
+                .ops
+                .intersects(crate::AttachmentOps::LOAD | crate::AttachmentOps::DONT_CARE)
+            {
                 let value = [
                     cat.clear_value.r as f32,
                     cat.clear_value.g as f32,
@@ -701,10 +704,16 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
         }
         if let Some(ref ds) = desc.depth_stencil_attachment {
             let mut flags = native::ClearFlags::empty();
-            if !ds.depth_ops.contains(crate::AttachmentOps::LOAD) {
+            if !ds
+                .depth_ops
+                .intersects(crate::AttachmentOps::LOAD | crate::AttachmentOps::DONT_CARE)
+            {
                 flags |= native::ClearFlags::DEPTH;
             }
-            if !ds.stencil_ops.contains(crate::AttachmentOps::LOAD) {
+            if !ds
+                .stencil_ops
+                .intersects(crate::AttachmentOps::LOAD | crate::AttachmentOps::DONT_CARE)
+            {
                 flags |= native::ClearFlags::STENCIL;
             }
 
@@ -16,6 +16,8 @@ pub(super) fn map_texture_format(format: wgt::TextureFormat) -> dxgiformat::DXGI
         Tf::R16Uint => DXGI_FORMAT_R16_UINT,
         Tf::R16Sint => DXGI_FORMAT_R16_SINT,
         Tf::R16Float => DXGI_FORMAT_R16_FLOAT,
+        Tf::R16Unorm => DXGI_FORMAT_R16_UNORM,
+        Tf::R16Snorm => DXGI_FORMAT_R16_SNORM,
         Tf::Rg8Unorm => DXGI_FORMAT_R8G8_UNORM,
         Tf::Rg8Snorm => DXGI_FORMAT_R8G8_SNORM,
         Tf::Rg8Uint => DXGI_FORMAT_R8G8_UINT,
@@ -26,6 +28,8 @@ pub(super) fn map_texture_format(format: wgt::TextureFormat) -> dxgiformat::DXGI
         Tf::Rg16Uint => DXGI_FORMAT_R16G16_UINT,
         Tf::Rg16Sint => DXGI_FORMAT_R16G16_SINT,
         Tf::Rg16Float => DXGI_FORMAT_R16G16_FLOAT,
+        Tf::Rg16Unorm => DXGI_FORMAT_R16G16_UNORM,
+        Tf::Rg16Snorm => DXGI_FORMAT_R16G16_SNORM,
         Tf::Rgba8Unorm => DXGI_FORMAT_R8G8B8A8_UNORM,
         Tf::Rgba8UnormSrgb => DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
         Tf::Bgra8UnormSrgb => DXGI_FORMAT_B8G8R8A8_UNORM_SRGB,
@@ -34,6 +38,7 @@ pub(super) fn map_texture_format(format: wgt::TextureFormat) -> dxgiformat::DXGI
         Tf::Rgba8Uint => DXGI_FORMAT_R8G8B8A8_UINT,
         Tf::Rgba8Sint => DXGI_FORMAT_R8G8B8A8_SINT,
         Tf::Rgb10a2Unorm => DXGI_FORMAT_R10G10B10A2_UNORM,
+        Tf::Rgb10a2Uint => DXGI_FORMAT_R10G10B10A2_UINT,
         Tf::Rg11b10Float => DXGI_FORMAT_R11G11B10_FLOAT,
         Tf::Rg32Uint => DXGI_FORMAT_R32G32_UINT,
         Tf::Rg32Sint => DXGI_FORMAT_R32G32_SINT,
@@ -41,12 +46,18 @@ pub(super) fn map_texture_format(format: wgt::TextureFormat) -> dxgiformat::DXGI
         Tf::Rgba16Uint => DXGI_FORMAT_R16G16B16A16_UINT,
         Tf::Rgba16Sint => DXGI_FORMAT_R16G16B16A16_SINT,
         Tf::Rgba16Float => DXGI_FORMAT_R16G16B16A16_FLOAT,
+        Tf::Rgba16Unorm => DXGI_FORMAT_R16G16B16A16_UNORM,
+        Tf::Rgba16Snorm => DXGI_FORMAT_R16G16B16A16_SNORM,
         Tf::Rgba32Uint => DXGI_FORMAT_R32G32B32A32_UINT,
         Tf::Rgba32Sint => DXGI_FORMAT_R32G32B32A32_SINT,
         Tf::Rgba32Float => DXGI_FORMAT_R32G32B32A32_FLOAT,
+        // There is no stencil-only format, the depth is unused.
+        Tf::Stencil8 => DXGI_FORMAT_D24_UNORM_S8_UINT,
+        Tf::Depth16Unorm => DXGI_FORMAT_D16_UNORM,
         Tf::Depth32Float => DXGI_FORMAT_D32_FLOAT,
         Tf::Depth24Plus => DXGI_FORMAT_D24_UNORM_S8_UINT,
         Tf::Depth24PlusStencil8 => DXGI_FORMAT_D24_UNORM_S8_UINT,
+        Tf::Depth32FloatStencil8 => DXGI_FORMAT_D32_FLOAT_S8X24_UINT,
         Tf::Rgb9e5Ufloat => DXGI_FORMAT_R9G9B9E5_SHAREDEXP,
         Tf::Bc1RgbaUnorm => DXGI_FORMAT_BC1_UNORM,
         Tf::Bc1RgbaUnormSrgb => DXGI_FORMAT_BC1_UNORM_SRGB,
@@ -115,10 +126,15 @@ pub fn map_texture_format_nosrgb(format: wgt::TextureFormat) -> dxgiformat::DXGI
 //TODO: stencil views?
 pub fn map_texture_format_nodepth(format: wgt::TextureFormat) -> dxgiformat::DXGI_FORMAT {
     match format {
+        wgt::TextureFormat::Stencil8 => dxgiformat::DXGI_FORMAT_X24_TYPELESS_G8_UINT,
+        wgt::TextureFormat::Depth16Unorm => dxgiformat::DXGI_FORMAT_R16_UNORM,
         wgt::TextureFormat::Depth32Float => dxgiformat::DXGI_FORMAT_R32_FLOAT,
         wgt::TextureFormat::Depth24Plus | wgt::TextureFormat::Depth24PlusStencil8 => {
             dxgiformat::DXGI_FORMAT_R24_UNORM_X8_TYPELESS
         }
+        wgt::TextureFormat::Depth32FloatStencil8 => {
+            dxgiformat::DXGI_FORMAT_R32_FLOAT_X8X24_TYPELESS
+        }
         _ => {
             assert_eq!(
                 crate::FormatAspects::from(format),
@@ -131,10 +147,13 @@ pub fn map_texture_format_nodepth(format: wgt::TextureFormat) -> dxgiformat::DXG
 
 pub fn map_texture_format_depth_typeless(format: wgt::TextureFormat) -> dxgiformat::DXGI_FORMAT {
     match format {
+        wgt::TextureFormat::Stencil8 => dxgiformat::DXGI_FORMAT_R24G8_TYPELESS,
+        wgt::TextureFormat::Depth16Unorm => dxgiformat::DXGI_FORMAT_R16_TYPELESS,
         wgt::TextureFormat::Depth32Float => dxgiformat::DXGI_FORMAT_R32_TYPELESS,
         wgt::TextureFormat::Depth24Plus | wgt::TextureFormat::Depth24PlusStencil8 => {
             dxgiformat::DXGI_FORMAT_R24G8_TYPELESS
         }
+        wgt::TextureFormat::Depth32FloatStencil8 => dxgiformat::DXGI_FORMAT_R32G8X24_TYPELESS,
         _ => unreachable!(),
     }
 }
@@ -185,6 +185,7 @@ impl super::Adapter {
         log::info!("Vendor: {}", vendor);
         log::info!("Renderer: {}", renderer);
         log::info!("Version: {}", version);
+        let info = Self::make_info(vendor, renderer);
 
         log::debug!("Extensions: {:#?}", extensions);
 
@@ -234,7 +235,8 @@ impl super::Adapter {
             | wgt::DownlevelFlags::DEVICE_LOCAL_IMAGE_COPIES
             | wgt::DownlevelFlags::NON_POWER_OF_TWO_MIPMAPPED_TEXTURES
             | wgt::DownlevelFlags::CUBE_ARRAY_TEXTURES
-            | wgt::DownlevelFlags::COMPARISON_SAMPLERS;
+            | wgt::DownlevelFlags::COMPARISON_SAMPLERS
+            | wgt::DownlevelFlags::MUTABLE_COMPARISON_SAMPLERS;
         downlevel_flags.set(wgt::DownlevelFlags::COMPUTE_SHADERS, ver >= (3, 1));
         downlevel_flags.set(
             wgt::DownlevelFlags::FRAGMENT_WRITABLE_STORAGE,
@@ -255,9 +257,16 @@ impl super::Adapter {
                 && (vertex_shader_storage_blocks != 0 || vertex_ssbo_false_zero),
         );
 
+        // Tile-based GPUs run the vertex stage of a pass ahead of its fragments.
+        downlevel_flags.set(
+            wgt::DownlevelFlags::CROSS_STAGE_WRITABLE_STORAGE,
+            !crate::auxil::db::is_tile_based(info.vendor as u32),
+        );
+
         let mut features = wgt::Features::empty()
             | wgt::Features::TEXTURE_COMPRESSION_ETC2
             | wgt::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+            | wgt::Features::DEPTH32FLOAT_STENCIL8
             | wgt::Features::CLEAR_COMMANDS;
         features.set(
             wgt::Features::DEPTH_CLAMPING,
@@ -334,13 +343,46 @@ impl super::Adapter {
             } else {
                 !0
             },
+            max_inter_stage_shader_components: gl.get_parameter_i32(glow::MAX_VARYING_COMPONENTS)
+                as u32,
             max_push_constant_size: 0,
             min_uniform_buffer_offset_alignment,
             min_storage_buffer_offset_alignment,
+            max_compute_invocations_per_workgroup: if ver >= (3, 1) {
+                gl.get_parameter_i32(glow::MAX_COMPUTE_WORK_GROUP_INVOCATIONS) as u32
+            } else {
+                wgt::Limits::default().max_compute_invocations_per_workgroup
+            },
+            max_compute_workgroup_size_x: if ver >= (3, 1) {
+                gl.get_parameter_indexed_i32(glow::MAX_COMPUTE_WORK_GROUP_SIZE, 0) as u32
+            } else {
+                wgt::Limits::default().max_compute_workgroup_size_x
+            },
+            max_compute_workgroup_size_y: if ver >= (3, 1) {
+                gl.get_parameter_indexed_i32(glow::MAX_COMPUTE_WORK_GROUP_SIZE, 1) as u32
+            } else {
+                wgt::Limits::default().max_compute_workgroup_size_y
+            },
+            max_compute_workgroup_size_z: if ver >= (3, 1) {
+                gl.get_parameter_indexed_i32(glow::MAX_COMPUTE_WORK_GROUP_SIZE, 2) as u32
+            } else {
+                wgt::Limits::default().max_compute_workgroup_size_z
+            },
+            max_compute_workgroups_per_dimension: if ver >= (3, 1) {
+                (0..3)
+                    .map(|i| {
+                        gl.get_parameter_indexed_i32(glow::MAX_COMPUTE_WORK_GROUP_COUNT, i) as u32
+                    })
+                    .min()
+                    .unwrap()
+            } else {
+                // no compute shaders, so the limit is never exercised
+                wgt::Limits::default().max_compute_workgroups_per_dimension
+            },
         };
 
         let mut workarounds = super::Workarounds::empty();
-        let r = renderer.to_lowercase();
+        let r = info.name.to_lowercase();
         // Check for Mesa sRGB clear bug. See
         // [`super::PrivateCapabilities::MESA_I915_SRGB_SHADER_CLEAR`].
         if r.contains("mesa")
@@ -369,7 +411,7 @@ impl super::Adapter {
                     shading_language_version,
                 }),
             },
-            info: Self::make_info(vendor, renderer),
+            info,
             features,
             capabilities: crate::Capabilities {
                 limits,
@@ -488,15 +530,26 @@ impl crate::Adapter<super::Api> for super::Adapter {
             Tf::Bgra8UnormSrgb | Tf::Rgba8Snorm | Tf::Bgra8Unorm => filtered_color,
             Tf::Rgba8Uint | Tf::Rgba8Sint => unfiltered_color | Tfc::STORAGE,
             Tf::Rgb10a2Unorm | Tf::Rg11b10Float => filtered_color,
+            Tf::Rgb10a2Uint => unfiltered_color,
             Tf::Rg32Uint | Tf::Rg32Sint => unfiltered_color,
             Tf::Rg32Float => unfiltered_color | Tfc::STORAGE,
             Tf::Rgba16Uint | Tf::Rgba16Sint => unfiltered_color | Tfc::STORAGE,
             Tf::Rgba16Float => filtered_color | Tfc::STORAGE,
+            // Needs `EXT_texture_norm16`, which isn't checked for.
+            Tf::R16Unorm
+            | Tf::R16Snorm
+            | Tf::Rg16Unorm
+            | Tf::Rg16Snorm
+            | Tf::Rgba16Unorm
+            | Tf::Rgba16Snorm => Tfc::empty(),
             Tf::Rgba32Uint | Tf::Rgba32Sint => unfiltered_color | Tfc::STORAGE,
             Tf::Rgba32Float => unfiltered_color | Tfc::STORAGE,
+            Tf::Stencil8 => Tfc::SAMPLED | Tfc::DEPTH_STENCIL_ATTACHMENT,
+            Tf::Depth16Unorm => Tfc::SAMPLED | Tfc::DEPTH_STENCIL_ATTACHMENT,
             Tf::Depth32Float => Tfc::SAMPLED | Tfc::DEPTH_STENCIL_ATTACHMENT,
             Tf::Depth24Plus => Tfc::SAMPLED | Tfc::DEPTH_STENCIL_ATTACHMENT,
             Tf::Depth24PlusStencil8 => Tfc::SAMPLED | Tfc::DEPTH_STENCIL_ATTACHMENT,
+            Tf::Depth32FloatStencil8 => Tfc::SAMPLED | Tfc::DEPTH_STENCIL_ATTACHMENT,
             Tf::Rgb9e5Ufloat
             | Tf::Bc1RgbaUnorm
             | Tf::Bc1RgbaUnormSrgb
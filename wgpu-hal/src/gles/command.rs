@@ -457,7 +457,10 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
 
         // issue the clears
         for (i, cat) in desc.color_attachments.iter().enumerate() {
-            if !cat.ops.contains(crate::AttachmentOps::LOAD) {
+            if !cat
+                .ops
+                .intersects(crate::AttachmentOps::LOAD | crate::AttachmentOps::DONT_CARE)
+            {
                 let c = &cat.clear_value;
                 self.cmd_buffer
                     .commands
@@ -480,12 +483,18 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
             }
         }
         if let Some(ref dsat) = desc.depth_stencil_attachment {
-            if !dsat.depth_ops.contains(crate::AttachmentOps::LOAD) {
+            if !dsat
+                .depth_ops
+                .intersects(crate::AttachmentOps::LOAD | crate::AttachmentOps::DONT_CARE)
+            {
                 self.cmd_buffer
                     .commands
                     .push(C::ClearDepth(dsat.clear_value.0));
             }
-            if !dsat.stencil_ops.contains(crate::AttachmentOps::LOAD) {
+            if !dsat
+                .stencil_ops
+                .intersects(crate::AttachmentOps::LOAD | crate::AttachmentOps::DONT_CARE)
+            {
                 self.cmd_buffer
                     .commands
                     .push(C::ClearStencil(dsat.clear_value.1));
@@ -199,6 +199,9 @@ pub trait Adapter<A: Api>: Send + Sync {
     /// Returns the capabilities of working with a specified surface.
     ///
     /// `None` means presentation is not supported for it.
+    ///
+    /// The capabilities reflect the display the surface is currently on, so they
+    /// should be queried again when it moves to another display.
     unsafe fn surface_capabilities(&self, surface: &A::Surface) -> Option<SurfaceCapabilities>;
 }
 
@@ -361,6 +364,8 @@ pub trait CommandEncoder<A: Api>: Send + Sync {
     /// Works with a single array layer.
     /// Note: `dst` current usage has to be `TextureUses::COPY_DST`.
     /// Note: the copy extent is in physical size (rounded to the block size)
+    /// Note: copies never scale, both regions have the same `size`. Backends like
+    /// Metal don't have scaling blits, so a render pass has to be used instead.
     unsafe fn copy_texture_to_texture<T>(
         &mut self,
         src: &A::Texture,
@@ -432,6 +437,7 @@ pub trait CommandEncoder<A: Api>: Send + Sync {
     // render passes
 
     // Begins a render pass, clears all active bindings.
+    // Only one pass can be active at a time, the previous one has to be ended first.
     unsafe fn begin_render_pass(&mut self, desc: &RenderPassDescriptor<A>);
     unsafe fn end_render_pass(&mut self);
 
@@ -495,6 +501,7 @@ pub trait CommandEncoder<A: Api>: Send + Sync {
     // compute passes
 
     // Begins a compute pass, clears all active bindings.
+    // Only one pass can be active at a time, the previous one has to be ended first.
     unsafe fn begin_compute_pass(&mut self, desc: &ComputePassDescriptor);
     unsafe fn end_compute_pass(&mut self);
 
@@ -540,6 +547,8 @@ bitflags!(
         const STORAGE_READ_WRITE = 1 << 4;
         /// Format can be used as storage with atomics.
         const STORAGE_ATOMIC = 1 << 5;
+        /// Format can be used as storage in a multisampled texture.
+        const STORAGE_MULTISAMPLE = 1 << 11;
 
         /// Format can be used as color and input attachment.
         const COLOR_ATTACHMENT = 1 << 6;
@@ -547,6 +556,8 @@ bitflags!(
         const COLOR_ATTACHMENT_BLEND = 1 << 7;
         /// Format can be used as depth-stencil and input attachment.
         const DEPTH_STENCIL_ATTACHMENT = 1 << 8;
+        /// Format can be the resolve target of a multisampled color attachment.
+        const MULTISAMPLE_RESOLVE = 1 << 12;
 
         /// Format can be copied from.
         const COPY_SRC = 1 << 9;
@@ -577,8 +588,13 @@ impl From<wgt::TextureAspect> for FormatAspects {
 impl From<wgt::TextureFormat> for FormatAspects {
     fn from(format: wgt::TextureFormat) -> Self {
         match format {
-            wgt::TextureFormat::Depth32Float | wgt::TextureFormat::Depth24Plus => Self::DEPTH,
-            wgt::TextureFormat::Depth24PlusStencil8 => Self::DEPTH | Self::STENCIL,
+            wgt::TextureFormat::Depth16Unorm
+            | wgt::TextureFormat::Depth32Float
+            | wgt::TextureFormat::Depth24Plus => Self::DEPTH,
+            wgt::TextureFormat::Depth24PlusStencil8 | wgt::TextureFormat::Depth32FloatStencil8 => {
+                Self::DEPTH | Self::STENCIL
+            }
+            wgt::TextureFormat::Stencil8 => Self::STENCIL,
             _ => Self::COLOR,
         }
     }
@@ -588,6 +604,10 @@ bitflags!(
     pub struct MemoryFlags: u32 {
         const TRANSIENT = 1 << 0;
         const PREFER_COHERENT = 1 << 1;
+        /// Hazards on the resource aren't tracked by the driver, so every
+        /// dependency between its uses has to be expressed with a barrier,
+        /// including ones between dispatches of a compute pass.
+        const UNTRACKED = 1 << 2;
     }
 );
 
@@ -597,6 +617,10 @@ bitflags!(
     pub struct AttachmentOps: u8 {
         const LOAD = 1 << 0;
         const STORE = 1 << 1;
+        /// Without `LOAD`, leaves the initial contents undefined instead of
+        /// clearing them. Only valid if the pass overwrites every texel,
+        /// but lets tile-based GPUs skip initializing the tile memory.
+        const DONT_CARE = 1 << 2;
     }
 );
 
@@ -698,6 +722,9 @@ pub struct SurfaceCapabilities {
     pub swap_chain_sizes: RangeInclusive<u32>,
 
     /// Current extent of the surface, if known.
+    ///
+    /// Backends that can only query it on some threads may report the last known
+    /// extent instead, which can lag behind a resize in flight.
     pub current_extent: Option<wgt::Extent3d>,
 
     /// Range of supported extents.
@@ -1114,6 +1141,8 @@ pub struct RenderPassDescriptor<'a, A: Api> {
     pub sample_count: u32,
     pub color_attachments: &'a [ColorAttachment<'a, A>],
     pub depth_stencil_attachment: Option<DepthStencilAttachment<'a, A>>,
+    /// Query set receiving the results of the occlusion queries issued within the pass.
+    pub occlusion_query_set: Option<&'a A::QuerySet>,
 }
 
 #[derive(Clone, Debug)]
@@ -2,7 +2,35 @@ use mtl::{MTLFeatureSet, MTLGPUFamily, MTLLanguageVersion};
 use objc::{class, msg_send, sel, sel_impl};
 use parking_lot::Mutex;
 
-use std::{sync::Arc, thread};
+use std::sync::Arc;
+
+/// PCI vendor IDs of the GPU makers, which Metal doesn't report.
+const VENDOR_ID_APPLE: usize = 0x106B;
+const VENDOR_ID_AMD: usize = 0x1002;
+const VENDOR_ID_INTEL: usize = 0x8086;
+const VENDOR_ID_NVIDIA: usize = 0x10DE;
+
+/// Maximum number of queues `Adapter::open_with_queues` opens. Metal has no
+/// limit of its own, each queue being a separate `MTLCommandQueue`, but Apple
+/// advises creating few of them. Four covers one queue for graphics, one for
+/// async compute and two for transfers.
+const MAX_EXPOSED_QUEUES: usize = 4;
+
+/// Guesses the PCI vendor ID from the device name, or returns 0.
+fn vendor_id_from_name(name: &str) -> usize {
+    let prefixes = [
+        ("Apple", VENDOR_ID_APPLE),
+        ("AMD", VENDOR_ID_AMD),
+        ("Radeon", VENDOR_ID_AMD),
+        ("Intel", VENDOR_ID_INTEL),
+        ("NVIDIA", VENDOR_ID_NVIDIA),
+        ("GeForce", VENDOR_ID_NVIDIA),
+    ];
+    prefixes
+        .iter()
+        .find(|&&(prefix, _)| name.starts_with(prefix))
+        .map_or(0, |&(_, vendor)| vendor)
+}
 
 unsafe impl Send for super::Adapter {}
 unsafe impl Sync for super::Adapter {}
@@ -11,6 +39,235 @@ impl super::Adapter {
     pub(super) fn new(shared: Arc<super::AdapterShared>) -> Self {
         Self { shared }
     }
+
+    /// Returns the underlying Metal device, for interoperation with native code.
+    ///
+    /// The device is reference counted, so the returned handle keeps it alive.
+    /// Objects created from it are not tracked by wgpu, and work submitted to
+    /// queues of its own has to be synchronized with wgpu's queue by the user.
+    pub fn as_raw_device(&self) -> mtl::Device {
+        self.shared.device.lock().clone()
+    }
+
+    /// Returns the depth format best suited for this adapter.
+    ///
+    /// Without stencil, this is `Depth16Unorm` for up to 16 bits where it's
+    /// supported, and `Depth32Float` otherwise. With stencil, it's
+    /// `Depth24PlusStencil8` for up to 24 bits where the packed format exists,
+    /// and `Depth32FloatStencil8` otherwise, which requires
+    /// `Features::DEPTH32FLOAT_STENCIL8`.
+    pub fn best_depth_format(&self, needs_stencil: bool, min_bits: u32) -> wgt::TextureFormat {
+        self.shared
+            .private_caps
+            .best_depth_format(needs_stencil, min_bits)
+    }
+
+    /// Returns the known issues of the device, which are already worked around
+    /// by the backend, for applications that need their own mitigations.
+    pub fn disabilities(&self) -> super::Disabilities {
+        self.shared.disabilities.flags()
+    }
+
+    /// Returns the shader stages that can write to storage buffers.
+    ///
+    /// Tile-based GPUs don't allow a read-write buffer to be visible to both
+    /// the vertex and the fragment stages.
+    pub fn read_write_buffer_stages(&self) -> wgt::ShaderStages {
+        self.shared.private_caps.read_write_buffer_stages
+    }
+
+    /// Returns whether shared events can be created, exported and imported
+    /// on this adapter, to synchronize with other processes.
+    pub fn supports_shared_events(&self) -> bool {
+        self.shared.private_caps.supports_shared_event
+    }
+
+    /// Returns whether buffers can be viewed as 1D textures with
+    /// `Device::create_buffer_texture_view`.
+    pub fn supports_texture_buffers(&self) -> bool {
+        self.shared.private_caps.supports_texture_buffer
+    }
+
+    /// Returns how many viewports `CommandEncoder::set_viewports` can set at once,
+    /// which is 1 on the families without multiple viewport support.
+    pub fn max_viewports(&self) -> u32 {
+        self.shared.private_caps.max_viewports
+    }
+
+    /// Returns `true` if samplers can be encoded into argument buffers, in which
+    /// case all of them are created with `supportArgumentBuffers`.
+    pub fn supports_argument_buffer_samplers(&self) -> bool {
+        self.shared.private_caps.argument_buffer_samplers
+    }
+
+    /// Returns `true` if fragment shaders can use raster order groups, which is
+    /// also advertised as `Features::RASTER_ORDER_GROUPS`.
+    ///
+    /// The MSL needs to be compiled with version 2.0 or later, and declares the
+    /// groups with the `[[raster_order_group(n)]]` attribute on the device memory
+    /// and texture arguments of the fragment function. Accesses to the resources
+    /// of a group are ordered between the overlapping fragments, in the order the
+    /// primitives were submitted.
+    pub fn supports_raster_order_groups(&self) -> bool {
+        self.shared.private_caps.supports_raster_order_groups
+    }
+
+    /// Returns the number of threads in a SIMD-group, which is the subgroup size
+    /// of `Features::SUBGROUP`, or `None` if it can't be determined.
+    ///
+    /// Metal only reports it for compiled pipelines, so it's taken from an empty
+    /// kernel compiled when the adapter is created. Pipelines may still get a
+    /// smaller width, which they report with `ComputePipeline::thread_execution_width`.
+    pub fn simd_width(&self) -> Option<u32> {
+        self.shared.private_caps.simd_width
+    }
+
+    /// Returns the highest anisotropy clamp samplers use. Higher clamps are
+    /// lowered to it.
+    pub fn max_sampler_anisotropy(&self) -> u8 {
+        self.shared.private_caps.max_sampler_anisotropy
+    }
+
+    /// Returns `true` if debug groups, signposts and encoder labels show up in
+    /// GPU captures. Otherwise, the command encoders skip them.
+    pub fn supports_debug_markers(&self) -> bool {
+        self.shared.private_caps.supports_debug_markers
+    }
+
+    /// Describes the adapter.
+    ///
+    /// The vendor is guessed from the device name, and the device ID is the
+    /// registry ID of the GPU where the OS reports it, or 0 otherwise. Apple GPUs
+    /// and the low-power GPUs of Macs are integrated, the others discrete.
+    pub fn info(&self) -> wgt::AdapterInfo {
+        let name = self.shared.device.lock().name().to_string();
+        let vendor = vendor_id_from_name(&name);
+        let caps = &self.shared.private_caps;
+        wgt::AdapterInfo {
+            name,
+            vendor,
+            device: self.registry_id().map_or(0, |id| id as usize),
+            device_type: if caps.low_power || vendor == VENDOR_ID_APPLE {
+                wgt::DeviceType::IntegratedGpu
+            } else {
+                wgt::DeviceType::DiscreteGpu
+            },
+            backend: wgt::Backend::Metal,
+        }
+    }
+
+    /// Returns `true` for the low-power GPU of a Mac with automatic graphics
+    /// switching, and on iOS.
+    ///
+    /// Preferring the other GPU gives more performance at the cost of battery life.
+    pub fn is_low_power(&self) -> bool {
+        self.shared.private_caps.low_power
+    }
+
+    /// Returns `true` if the GPU has no displays attached, like external GPUs
+    /// used for compute or a second GPU of a Mac Pro.
+    pub fn is_headless(&self) -> bool {
+        self.shared.private_caps.headless
+    }
+
+    /// Returns the registry ID of the GPU, which is the same in all processes.
+    ///
+    /// Returns `None` if the OS is too old to report it.
+    pub fn registry_id(&self) -> Option<u64> {
+        if self.shared.private_caps.supports_registry_id {
+            Some(self.shared.device.lock().registry_id())
+        } else {
+            None
+        }
+    }
+
+    /// Checks if IOSurface-backed resources can be shared with another process
+    /// using the GPU with `registry_id`, which requires both to be the same device.
+    pub fn can_share_resources_with(&self, registry_id: u64) -> bool {
+        self.registry_id() == Some(registry_id)
+    }
+
+    /// Opens a device that compiles shaders with at most the given MSL version.
+    ///
+    /// The version is clamped to the highest one supported by the adapter.
+    /// This is useful for checking that shaders still work on older systems.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`crate::Adapter::open`].
+    pub unsafe fn open_with_msl_version(
+        &self,
+        features: wgt::Features,
+        msl_version: MTLLanguageVersion,
+    ) -> Result<crate::OpenDevice<super::Api>, crate::DeviceError> {
+        Ok(crate::OpenDevice {
+            device: self.create_device(features, msl_version),
+            queue: self.create_queue(),
+        })
+    }
+
+    /// Returns how many queues `open_with_queues` can open at most.
+    pub fn exposed_queues(&self) -> usize {
+        MAX_EXPOSED_QUEUES
+    }
+
+    /// Opens a device with `count` queues, each submitting to its own Metal
+    /// command queue, such as one for graphics and one for async compute.
+    ///
+    /// Work submitted to different queues isn't ordered, so resources shared
+    /// between them have to be synchronized with fences. Fails if `count` is 0
+    /// or more than `exposed_queues`.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`crate::Adapter::open`].
+    pub unsafe fn open_with_queues(
+        &self,
+        features: wgt::Features,
+        count: usize,
+    ) -> Result<(super::Device, Vec<super::Queue>), super::QueueCountError> {
+        if count == 0 {
+            return Err(super::QueueCountError::Zero);
+        }
+        if count > MAX_EXPOSED_QUEUES {
+            return Err(super::QueueCountError::TooMany {
+                requested: count,
+                exposed: MAX_EXPOSED_QUEUES,
+            });
+        }
+        let device = self.create_device(features, self.shared.private_caps.msl_version);
+        let queues = (0..count).map(|_| self.create_queue()).collect();
+        Ok((device, queues))
+    }
+
+    fn create_device(
+        &self,
+        features: wgt::Features,
+        msl_version: MTLLanguageVersion,
+    ) -> super::Device {
+        super::Device {
+            shared: Arc::clone(&self.shared),
+            features,
+            msl_version: msl_version.min(self.shared.private_caps.msl_version),
+            memory_pressure: None,
+            pipeline_cache: None,
+        }
+    }
+
+    fn create_queue(&self) -> super::Queue {
+        let device = self.shared.device.lock();
+        let timestamp_origin = if self.shared.private_caps.supports_counter_sampling {
+            Some(super::sample_timestamps(&device))
+        } else {
+            None
+        };
+        super::Queue {
+            raw: Arc::new(Mutex::new(device.new_command_queue())),
+            timestamp_origin,
+            frames_in_flight: Arc::default(),
+            max_frames_in_flight: None,
+        }
+    }
 }
 
 impl crate::Adapter<super::Api> for super::Adapter {
@@ -18,16 +275,7 @@ impl crate::Adapter<super::Api> for super::Adapter {
         &self,
         features: wgt::Features,
     ) -> Result<crate::OpenDevice<super::Api>, crate::DeviceError> {
-        let queue = self.shared.device.lock().new_command_queue();
-        Ok(crate::OpenDevice {
-            device: super::Device {
-                shared: Arc::clone(&self.shared),
-                features,
-            },
-            queue: super::Queue {
-                raw: Arc::new(Mutex::new(queue)),
-            },
-        })
+        self.open_with_msl_version(features, self.shared.private_caps.msl_version)
     }
 
     unsafe fn texture_format_capabilities(
@@ -51,11 +299,12 @@ impl crate::Adapter<super::Api> for super::Adapter {
 
         let extra = match format {
             Tf::R8Unorm => {
-                read_write_tier2_if
-                    | Tfc::SAMPLED_LINEAR
-                    | Tfc::STORAGE
-                    | Tfc::COLOR_ATTACHMENT
-                    | Tfc::COLOR_ATTACHMENT_BLEND
+                let mut flags =
+                    Tfc::SAMPLED_LINEAR | Tfc::COLOR_ATTACHMENT | Tfc::COLOR_ATTACHMENT_BLEND;
+                if pc.format_r8unorm_write {
+                    flags |= read_write_tier2_if | Tfc::STORAGE;
+                }
+                flags
             }
             Tf::R8Snorm => {
                 Tfc::SAMPLED_LINEAR
@@ -81,7 +330,14 @@ impl crate::Adapter<super::Api> for super::Adapter {
             Tf::Rg8Uint | Tf::Rg8Sint => Tfc::COLOR_ATTACHMENT,
             Tf::R32Uint | Tf::R32Sint => {
                 if pc.format_r32_all {
-                    read_write_tier1_if | Tfc::STORAGE | Tfc::COLOR_ATTACHMENT
+                    // Atomic image operations are limited to the same formats
+                    // as tier 2 read-write textures.
+                    let atomic_if = if read_write_tier2_if.is_empty() {
+                        Tfc::empty()
+                    } else {
+                        Tfc::STORAGE_ATOMIC
+                    };
+                    read_write_tier1_if | atomic_if | Tfc::STORAGE | Tfc::COLOR_ATTACHMENT
                 } else {
                     Tfc::COLOR_ATTACHMENT
                 }
@@ -98,6 +354,15 @@ impl crate::Adapter<super::Api> for super::Adapter {
             Tf::Rg16Uint | Tf::Rg16Sint => {
                 read_write_tier2_if | Tfc::STORAGE | Tfc::COLOR_ATTACHMENT
             }
+            // Writable on macOS, but naga has no storage formats for them.
+            Tf::R16Unorm
+            | Tf::R16Snorm
+            | Tf::Rg16Unorm
+            | Tf::Rg16Snorm
+            | Tf::Rgba16Unorm
+            | Tf::Rgba16Snorm => {
+                Tfc::SAMPLED_LINEAR | Tfc::COLOR_ATTACHMENT | Tfc::COLOR_ATTACHMENT_BLEND
+            }
             Tf::Rg16Float => {
                 read_write_tier2_if
                     | Tfc::SAMPLED_LINEAR
@@ -133,6 +398,11 @@ impl crate::Adapter<super::Api> for super::Adapter {
                 flags.set(Tfc::STORAGE, pc.format_rgb10a2_unorm_all);
                 flags
             }
+            Tf::Rgb10a2Uint => {
+                let mut flags = Tfc::COLOR_ATTACHMENT;
+                flags.set(Tfc::STORAGE, pc.format_rgb10a2_uint_color_write);
+                flags
+            }
             Tf::Rg11b10Float => {
                 let mut flags =
                     Tfc::SAMPLED_LINEAR | Tfc::COLOR_ATTACHMENT | Tfc::COLOR_ATTACHMENT_BLEND;
@@ -179,6 +449,14 @@ impl crate::Adapter<super::Api> for super::Adapter {
                     Tfc::COLOR_ATTACHMENT
                 }
             }
+            Tf::Stencil8 => Tfc::DEPTH_STENCIL_ATTACHMENT,
+            Tf::Depth16Unorm => {
+                if pc.format_depth16unorm {
+                    Tfc::DEPTH_STENCIL_ATTACHMENT | Tfc::SAMPLED_LINEAR
+                } else {
+                    Tfc::empty()
+                }
+            }
             Tf::Depth32Float => {
                 if pc.format_depth32float_filter {
                     Tfc::DEPTH_STENCIL_ATTACHMENT | Tfc::SAMPLED_LINEAR
@@ -187,7 +465,19 @@ impl crate::Adapter<super::Api> for super::Adapter {
                 }
             }
             Tf::Depth24Plus | Tf::Depth24PlusStencil8 => {
-                Tfc::DEPTH_STENCIL_ATTACHMENT | Tfc::SAMPLED_LINEAR
+                // Falls back to `Depth32Float` without `Depth24Unorm_Stencil8`.
+                if pc.format_depth24_stencil8 || pc.format_depth32float_filter {
+                    Tfc::DEPTH_STENCIL_ATTACHMENT | Tfc::SAMPLED_LINEAR
+                } else {
+                    Tfc::DEPTH_STENCIL_ATTACHMENT
+                }
+            }
+            Tf::Depth32FloatStencil8 => {
+                if pc.format_depth32float_filter {
+                    Tfc::DEPTH_STENCIL_ATTACHMENT | Tfc::SAMPLED_LINEAR
+                } else {
+                    Tfc::DEPTH_STENCIL_ATTACHMENT
+                }
             }
             Tf::Rgb9e5Ufloat => Tfc::SAMPLED_LINEAR,
             Tf::Bc1RgbaUnorm
@@ -260,27 +550,58 @@ impl crate::Adapter<super::Api> for super::Adapter {
             }
         };
 
-        Tfc::COPY_SRC | Tfc::COPY_DST | Tfc::SAMPLED | extra
+        // Integer formats can't be resolved, which leaves the blendable ones,
+        // except for the 8-bit snorm formats on the families lacking support
+        // and the 16-bit normalized ones outside of macOS.
+        let resolve = match format {
+            Tf::R8Snorm | Tf::Rg8Snorm | Tf::Rgba8Snorm => pc.format_any8_snorm_all,
+            // iOS doesn't resolve the 16-bit normalized formats.
+            Tf::R16Unorm
+            | Tf::R16Snorm
+            | Tf::Rg16Unorm
+            | Tf::Rg16Snorm
+            | Tf::Rgba16Unorm
+            | Tf::Rgba16Snorm => pc.format_r16_norm_all,
+            _ => extra.contains(Tfc::COLOR_ATTACHMENT_BLEND),
+        };
+        let resolve_if = if resolve {
+            Tfc::MULTISAMPLE_RESOLVE
+        } else {
+            Tfc::empty()
+        };
+
+        // Multisampled textures are read-only in MSL, so `STORAGE_MULTISAMPLE` is never exposed.
+        Tfc::COPY_SRC | Tfc::COPY_DST | Tfc::SAMPLED | resolve_if | extra
     }
 
     unsafe fn surface_capabilities(
         &self,
         surface: &super::Surface,
     ) -> Option<crate::SurfaceCapabilities> {
-        let current_extent = if surface.main_thread_id == thread::current().id() {
-            Some(surface.dimensions())
-        } else {
-            log::warn!("Unable to get the current view dimensions on a non-main thread");
+        let pc = &self.shared.private_caps;
+        // Without a display, there is no view size to follow, and no vblank to
+        // sync the presentation to, so only `Immediate` can make progress.
+        let current_extent = if pc.headless {
             None
+        } else {
+            let current_extent = surface.current_extent();
+            if current_extent.is_none() {
+                log::warn!("Unable to get the current view dimensions on a non-main thread");
+            }
+            current_extent
         };
 
-        let pc = &self.shared.private_caps;
+        let mut formats = vec![
+            wgt::TextureFormat::Bgra8Unorm,
+            wgt::TextureFormat::Bgra8UnormSrgb,
+        ];
+        // Half-float drawables are only worth it for values above 1.0,
+        // which need a display with extended dynamic range.
+        if pc.supports_edr && surface.supports_edr() {
+            formats.push(wgt::TextureFormat::Rgba16Float);
+        }
+
         Some(crate::SurfaceCapabilities {
-            formats: vec![
-                wgt::TextureFormat::Bgra8Unorm,
-                wgt::TextureFormat::Bgra8UnormSrgb,
-                wgt::TextureFormat::Rgba16Float,
-            ],
             //Note: this is hardcoded in `CAMetalLayer` documentation
             swap_chain_sizes: if pc.can_set_maximum_drawables_count {
                 2..=3
@@ -289,7 +610,22 @@ impl crate::Adapter<super::Api> for super::Adapter {
                 // iOS 10.3 was tested to use 3 on iphone5s
                 3..=3
             },
-            present_modes: if pc.can_set_display_sync {
+            // `displaySyncEnabled` is a property of the layer, and it's honored
+            // the same way by every display the layer can be moved to.
+            //
+            // Mailbox is emulated by syncing to vblank with three drawables, so
+            // that `nextDrawable` doesn't block on the one being displayed. On iOS
+            // the drawable count and the display sync are both fixed, so it's not
+            // advertised there, and `Fifo` already behaves mostly like it.
+            present_modes: if pc.headless {
+                vec![wgt::PresentMode::Immediate]
+            } else if pc.can_set_display_sync && pc.can_set_maximum_drawables_count {
+                vec![
+                    wgt::PresentMode::Fifo,
+                    wgt::PresentMode::Immediate,
+                    wgt::PresentMode::Mailbox,
+                ]
+            } else if pc.can_set_display_sync {
                 vec![wgt::PresentMode::Fifo, wgt::PresentMode::Immediate]
             } else {
                 vec![wgt::PresentMode::Fifo]
@@ -310,7 +646,13 @@ impl crate::Adapter<super::Api> for super::Adapter {
                 height: 4096,
                 depth_or_array_layers: 1,
             },
-            usage: crate::TextureUses::COLOR_TARGET, //TODO: expose more
+            // Anything beyond `COLOR_TARGET` opts out of the framebuffer-only fast path.
+            usage: formats
+                .iter()
+                .fold(crate::TextureUses::empty(), |usage, &format| {
+                    usage | pc.surface_usage(format)
+                }),
+            formats,
         })
     }
 }
@@ -327,6 +669,33 @@ const RESOURCE_HEAP_SUPPORT: &[MTLFeatureSet] = &[
     MTLFeatureSet::macOS_GPUFamily2_v1,
 ];
 
+const RENDER_TARGET_HEAP_SUPPORT: &[MTLFeatureSet] = &[
+    MTLFeatureSet::iOS_GPUFamily3_v2,
+    MTLFeatureSet::iOS_GPUFamily4_v1,
+    MTLFeatureSet::iOS_GPUFamily5_v1,
+    MTLFeatureSet::tvOS_GPUFamily2_v1,
+    MTLFeatureSet::macOS_GPUFamily1_v3,
+    MTLFeatureSet::macOS_GPUFamily2_v1,
+];
+
+const MULTIPLE_VIEWPORT_SUPPORT: &[MTLFeatureSet] = &[
+    MTLFeatureSet::iOS_GPUFamily5_v1,
+    MTLFeatureSet::macOS_GPUFamily1_v3,
+    MTLFeatureSet::macOS_GPUFamily2_v1,
+];
+
+const DEBUG_MARKER_SUPPORT: &[MTLFeatureSet] = &[
+    MTLFeatureSet::macOS_GPUFamily1_v2,
+    MTLFeatureSet::macOS_GPUFamily2_v1,
+    MTLFeatureSet::iOS_GPUFamily1_v3,
+    MTLFeatureSet::iOS_GPUFamily2_v3,
+    MTLFeatureSet::iOS_GPUFamily3_v2,
+    MTLFeatureSet::iOS_GPUFamily4_v1,
+    MTLFeatureSet::iOS_GPUFamily5_v1,
+    MTLFeatureSet::tvOS_GPUFamily1_v2,
+    MTLFeatureSet::tvOS_GPUFamily2_v1,
+];
+
 const ARGUMENT_BUFFER_SUPPORT: &[MTLFeatureSet] = &[
     MTLFeatureSet::iOS_GPUFamily1_v4,
     MTLFeatureSet::iOS_GPUFamily2_v4,
@@ -380,6 +749,18 @@ const ANY8_SNORM_RESOLVE: &[MTLFeatureSet] = &[
     MTLFeatureSet::macOS_GPUFamily2_v1,
 ];
 
+const R8UNORM_WRITE_SUPPORT: &[MTLFeatureSet] = &[
+    MTLFeatureSet::iOS_GPUFamily1_v2,
+    MTLFeatureSet::iOS_GPUFamily2_v2,
+    MTLFeatureSet::iOS_GPUFamily3_v1,
+    MTLFeatureSet::iOS_GPUFamily4_v1,
+    MTLFeatureSet::iOS_GPUFamily5_v1,
+    MTLFeatureSet::tvOS_GPUFamily1_v1,
+    MTLFeatureSet::tvOS_GPUFamily2_v1,
+    MTLFeatureSet::macOS_GPUFamily1_v1,
+    MTLFeatureSet::macOS_GPUFamily2_v1,
+];
+
 const RGBA8_SRGB: &[MTLFeatureSet] = &[
     MTLFeatureSet::iOS_GPUFamily2_v3,
     MTLFeatureSet::iOS_GPUFamily3_v1,
@@ -499,11 +880,41 @@ const DEPTH_CLIP_MODE: &[MTLFeatureSet] = &[
     MTLFeatureSet::macOS_GPUFamily2_v1,
 ];
 
+const DEPTH_BIAS_CLAMP_SUPPORT: &[MTLFeatureSet] = &[
+    MTLFeatureSet::iOS_GPUFamily3_v1,
+    MTLFeatureSet::iOS_GPUFamily4_v1,
+    MTLFeatureSet::iOS_GPUFamily5_v1,
+    MTLFeatureSet::tvOS_GPUFamily2_v1,
+    MTLFeatureSet::macOS_GPUFamily1_v1,
+    MTLFeatureSet::macOS_GPUFamily2_v1,
+];
+
+const COUNTING_OCCLUSION_QUERY_SUPPORT: &[MTLFeatureSet] = &[
+    MTLFeatureSet::iOS_GPUFamily3_v1,
+    MTLFeatureSet::iOS_GPUFamily4_v1,
+    MTLFeatureSet::iOS_GPUFamily5_v1,
+    MTLFeatureSet::tvOS_GPUFamily2_v1,
+    MTLFeatureSet::macOS_GPUFamily1_v1,
+    MTLFeatureSet::macOS_GPUFamily2_v1,
+];
+
 impl super::PrivateCapabilities {
     fn version_at_least(major: u32, minor: u32, needed_major: u32, needed_minor: u32) -> bool {
         major > needed_major || (major == needed_major && minor >= needed_minor)
     }
 
+    /// Compiles an empty kernel to find out its thread execution width.
+    fn query_simd_width(device: &mtl::DeviceRef) -> Option<u32> {
+        let library = device
+            .new_library_with_source("kernel void simd_width() {}", &mtl::CompileOptions::new())
+            .ok()?;
+        let function = library.get_function("simd_width", None).ok()?;
+        let pipeline = device
+            .new_compute_pipeline_state_with_function(&function)
+            .ok()?;
+        Some(pipeline.thread_execution_width() as u32)
+    }
+
     fn supports_any(raw: &mtl::DeviceRef, features_sets: &[MTLFeatureSet]) -> bool {
         features_sets
             .iter()
@@ -544,36 +955,113 @@ impl super::PrivateCapabilities {
             sample_count_mask |= 8;
         }
 
-        Self {
-            family_check,
-            msl_version: if os_is_mac {
-                if Self::version_at_least(major, minor, 10, 15) {
-                    MTLLanguageVersion::V2_2
-                } else if Self::version_at_least(major, minor, 10, 14) {
-                    MTLLanguageVersion::V2_1
-                } else if Self::version_at_least(major, minor, 10, 13) {
-                    MTLLanguageVersion::V2_0
-                } else if Self::version_at_least(major, minor, 10, 12) {
-                    MTLLanguageVersion::V1_2
-                } else if Self::version_at_least(major, minor, 10, 11) {
-                    MTLLanguageVersion::V1_1
-                } else {
-                    MTLLanguageVersion::V1_0
-                }
-            } else if Self::version_at_least(major, minor, 13, 0) {
+        let supports_counter_sampling = if os_is_mac {
+            Self::version_at_least(major, minor, 10, 15)
+        } else {
+            Self::version_at_least(major, minor, 14, 0)
+        };
+
+        let apple_gpu =
+            !os_is_mac || (family_check && device.supports_family(MTLGPUFamily::Apple1));
+
+        // The timestamps are written between the commands of render, compute and
+        // blit encoders. Apple GPUs, Apple silicon Macs included, only sample
+        // counters at stage boundaries. The OS versions that can't be asked about
+        // it predate the Macs with Apple GPUs.
+        let supports_timestamp_query = supports_counter_sampling
+            && super::timestamp_counter_set(device).is_some()
+            && if (os_is_mac && Self::version_at_least(major, minor, 11, 0))
+                || (!os_is_mac && Self::version_at_least(major, minor, 14, 0))
+            {
+                [
+                    mtl::MTLCounterSamplingPoint::AtDrawBoundary,
+                    mtl::MTLCounterSamplingPoint::AtDispatchBoundary,
+                    mtl::MTLCounterSamplingPoint::AtBlitBoundary,
+                ]
+                .iter()
+                .all(|&point| device.supports_counter_sampling(point))
+            } else {
+                !apple_gpu
+            };
+
+        // Metal has no geometry or tessellation-style amplification stages. The only
+        // amplification it offers is the per-vertex one (`[[amplification_id]]`), so
+        // anything else has to be emulated with compute passes.
+        //
+        // The query itself only appeared in a later update of these versions, and
+        // sending it to a device that doesn't know it raises an exception.
+        let supports_vertex_amplification = if os_is_mac {
+            Self::version_at_least(major, minor, 10, 15)
+        } else {
+            Self::version_at_least(major, minor, 13, 0)
+        } && {
+            let responds: objc::runtime::BOOL = unsafe {
+                msg_send![
+                    device.as_ref(),
+                    respondsToSelector: sel!(supportsVertexAmplificationCount:)
+                ]
+            };
+            responds == objc::runtime::YES
+        };
+        let max_vertex_amplification_count = if supports_vertex_amplification {
+            [8u64, 4, 2]
+                .iter()
+                .copied()
+                .find(|&count| {
+                    let supported: objc::runtime::BOOL = unsafe {
+                        msg_send![device.as_ref(), supportsVertexAmplificationCount: count]
+                    };
+                    supported == objc::runtime::YES
+                })
+                .unwrap_or(1) as u32
+        } else {
+            1
+        };
+
+        // Setting the property on an OS that doesn't know about it raises an
+        // Objective-C exception, so it's checked for in addition to the families.
+        let argument_buffers = Self::supports_any(device, ARGUMENT_BUFFER_SUPPORT);
+        let argument_buffer_samplers = argument_buffers && {
+            let responds: objc::runtime::BOOL = unsafe {
+                msg_send![
+                    class!(MTLSamplerDescriptor),
+                    instancesRespondToSelector: sel!(setSupportArgumentBuffers:)
+                ]
+            };
+            responds == objc::runtime::YES
+        };
+
+        let msl_version = if os_is_mac {
+            if Self::version_at_least(major, minor, 10, 15) {
                 MTLLanguageVersion::V2_2
-            } else if Self::version_at_least(major, minor, 12, 0) {
+            } else if Self::version_at_least(major, minor, 10, 14) {
                 MTLLanguageVersion::V2_1
-            } else if Self::version_at_least(major, minor, 11, 0) {
+            } else if Self::version_at_least(major, minor, 10, 13) {
                 MTLLanguageVersion::V2_0
-            } else if Self::version_at_least(major, minor, 10, 0) {
+            } else if Self::version_at_least(major, minor, 10, 12) {
                 MTLLanguageVersion::V1_2
-            } else if Self::version_at_least(major, minor, 9, 0) {
+            } else if Self::version_at_least(major, minor, 10, 11) {
                 MTLLanguageVersion::V1_1
             } else {
                 MTLLanguageVersion::V1_0
-            },
-            exposed_queues: 1,
+            }
+        } else if Self::version_at_least(major, minor, 13, 0) {
+            MTLLanguageVersion::V2_2
+        } else if Self::version_at_least(major, minor, 12, 0) {
+            MTLLanguageVersion::V2_1
+        } else if Self::version_at_least(major, minor, 11, 0) {
+            MTLLanguageVersion::V2_0
+        } else if Self::version_at_least(major, minor, 10, 0) {
+            MTLLanguageVersion::V1_2
+        } else if Self::version_at_least(major, minor, 9, 0) {
+            MTLLanguageVersion::V1_1
+        } else {
+            MTLLanguageVersion::V1_0
+        };
+
+        Self {
+            family_check,
+            msl_version,
             read_write_texture_tier: if os_is_mac {
                 if Self::version_at_least(major, minor, 10, 13) {
                     device.read_write_texture_support()
@@ -586,7 +1074,9 @@ impl super::PrivateCapabilities {
                 mtl::MTLReadWriteTextureTier::TierNone
             },
             resource_heaps: Self::supports_any(device, RESOURCE_HEAP_SUPPORT),
-            argument_buffers: Self::supports_any(device, ARGUMENT_BUFFER_SUPPORT),
+            render_target_heaps: Self::supports_any(device, RENDER_TARGET_HEAP_SUPPORT),
+            argument_buffers,
+            argument_buffer_samplers,
             shared_textures: !os_is_mac,
             mutable_comparison_samplers: Self::supports_any(
                 device,
@@ -606,8 +1096,15 @@ impl super::PrivateCapabilities {
             layered_rendering: Self::supports_any(device, LAYERED_RENDERING_SUPPORT),
             function_specialization: Self::supports_any(device, FUNCTION_SPECIALIZATION_SUPPORT),
             depth_clip_mode: Self::supports_any(device, DEPTH_CLIP_MODE),
+            depth_bias_clamp: Self::supports_any(device, DEPTH_BIAS_CLAMP_SUPPORT),
+            counting_occlusion_query: Self::supports_any(device, COUNTING_OCCLUSION_QUERY_SUPPORT),
             texture_cube_array: Self::supports_any(device, TEXTURE_CUBE_ARRAY_SUPPORT),
             format_depth24_stencil8: os_is_mac && device.d24_s8_supported(),
+            format_stencil8: if os_is_mac {
+                Self::version_at_least(major, minor, 10, 12)
+            } else {
+                Self::version_at_least(major, minor, 10, 0)
+            },
             format_depth32_stencil8_filter: os_is_mac,
             format_depth32_stencil8_none: !os_is_mac,
             format_min_srgb_channels: if os_is_mac { 4 } else { 1 },
@@ -619,6 +1116,7 @@ impl super::PrivateCapabilities {
             format_any8_unorm_srgb_no_write: !Self::supports_any(device, ANY8_UNORM_SRGB_ALL)
                 && !os_is_mac,
             format_any8_snorm_all: Self::supports_any(device, ANY8_SNORM_RESOLVE),
+            format_r8unorm_write: Self::supports_any(device, R8UNORM_WRITE_SUPPORT),
             format_r16_norm_all: os_is_mac,
             format_r32_all: !Self::supports_any(
                 device,
@@ -736,6 +1234,12 @@ impl super::PrivateCapabilities {
             } else {
                 1 << 28 // 256MB otherwise
             },
+            max_visibility_queries: if os_is_mac {
+                // The visibility result offset is limited to 256KB on macOS and 64KB otherwise.
+                (256 << 10) / crate::QUERY_SIZE as u32
+            } else {
+                (64 << 10) / crate::QUERY_SIZE as u32
+            },
             max_texture_size: if Self::supports_any(
                 device,
                 &[
@@ -757,6 +1261,27 @@ impl super::PrivateCapabilities {
             } else {
                 4096
             },
+            max_texture_1d_size: if Self::supports_any(
+                device,
+                &[
+                    MTLFeatureSet::iOS_GPUFamily3_v1,
+                    MTLFeatureSet::tvOS_GPUFamily2_v1,
+                    MTLFeatureSet::macOS_GPUFamily1_v1,
+                ],
+            ) {
+                16384
+            } else if Self::supports_any(
+                device,
+                &[
+                    MTLFeatureSet::iOS_GPUFamily1_v2,
+                    MTLFeatureSet::iOS_GPUFamily2_v2,
+                    MTLFeatureSet::tvOS_GPUFamily1_v1,
+                ],
+            ) {
+                8192
+            } else {
+                4096
+            },
             max_texture_3d_size: 2048,
             max_texture_layers: 2048,
             max_fragment_input_components: if os_is_mac { 128 } else { 60 },
@@ -777,7 +1302,36 @@ impl super::PrivateCapabilities {
             } else {
                 4
             },
-            max_total_threadgroup_memory: if Self::supports_any(
+            // Not a device query: Metal accepts up to 2^32 - 1 threadgroups along
+            // each dimension of a grid on every family. This is the WebGPU default,
+            // which keeps dispatches portable to the other backends.
+            max_threadgroups_per_dimension: 0xFFFF,
+            max_threads_per_threadgroup: {
+                let size = device.max_threads_per_threadgroup();
+                [size.width as u32, size.height as u32, size.depth as u32]
+            },
+            max_total_threads_per_threadgroup: if Self::supports_any(
+                device,
+                &[
+                    MTLFeatureSet::iOS_GPUFamily4_v1,
+                    MTLFeatureSet::iOS_GPUFamily5_v1,
+                    MTLFeatureSet::macOS_GPUFamily1_v1,
+                ],
+            ) {
+                1024
+            } else {
+                512
+            },
+            // The exact value is only known since macOS 10.13 and iOS 11,
+            // before that it's derived from the feature sets.
+            max_total_threadgroup_memory: if Self::version_at_least(
+                major,
+                minor,
+                if os_is_mac { 10 } else { 11 },
+                if os_is_mac { 13 } else { 0 },
+            ) {
+                device.max_threadgroup_memory_length() as u32
+            } else if Self::supports_any(
                 device,
                 &[
                     MTLFeatureSet::iOS_GPUFamily4_v2,
@@ -798,31 +1352,52 @@ impl super::PrivateCapabilities {
                 16 << 10
             },
             sample_count_mask,
-            supports_debug_markers: Self::supports_any(
-                device,
-                &[
-                    MTLFeatureSet::macOS_GPUFamily1_v2,
-                    MTLFeatureSet::macOS_GPUFamily2_v1,
-                    MTLFeatureSet::iOS_GPUFamily1_v3,
-                    MTLFeatureSet::iOS_GPUFamily2_v3,
-                    MTLFeatureSet::iOS_GPUFamily3_v2,
-                    MTLFeatureSet::iOS_GPUFamily4_v1,
-                    MTLFeatureSet::iOS_GPUFamily5_v1,
-                    MTLFeatureSet::tvOS_GPUFamily1_v2,
-                    MTLFeatureSet::tvOS_GPUFamily2_v1,
-                ],
-            ),
-            supports_binary_archives: family_check
+            supports_debug_markers: Self::supports_any(device, DEBUG_MARKER_SUPPORT),
+            // Binary archives came after the GPU families.
+            supports_binary_archives: if os_is_mac {
+                Self::version_at_least(major, minor, 11, 0)
+            } else {
+                Self::version_at_least(major, minor, 14, 0)
+            } && (device.supports_family(MTLGPUFamily::Apple3)
+                || device.supports_family(MTLGPUFamily::Mac1)),
+            supports_indirect_command_buffers: family_check
                 && (device.supports_family(MTLGPUFamily::Apple3)
-                    || device.supports_family(MTLGPUFamily::Mac1)),
+                    || device.supports_family(MTLGPUFamily::Mac2)),
             supports_capture_manager: if os_is_mac {
                 Self::version_at_least(major, minor, 10, 13)
             } else {
                 Self::version_at_least(major, minor, 11, 0)
             },
+            supports_capture_to_file: if os_is_mac {
+                Self::version_at_least(major, minor, 10, 15)
+            } else {
+                Self::version_at_least(major, minor, 13, 0)
+            },
+            supports_working_set_size: if os_is_mac {
+                Self::version_at_least(major, minor, 10, 13)
+            } else {
+                Self::version_at_least(major, minor, 16, 0)
+            },
             can_set_maximum_drawables_count: os_is_mac
                 || Self::version_at_least(major, minor, 11, 2),
             can_set_display_sync: os_is_mac && Self::version_at_least(major, minor, 10, 13),
+            supports_edr: os_is_mac && Self::version_at_least(major, minor, 10, 15),
+            supports_primitive_index: os_is_mac && Self::version_at_least(major, minor, 10, 15),
+            // `half` is usable everywhere since MSL 2.0, while older versions
+            // don't accept it in every shader interface.
+            supports_shader_f16: msl_version >= MTLLanguageVersion::V2_0,
+            supports_raster_order_groups: msl_version >= MTLLanguageVersion::V2_0
+                && if os_is_mac {
+                    Self::version_at_least(major, minor, 10, 13)
+                } else {
+                    Self::version_at_least(major, minor, 11, 0)
+                }
+                && device.raster_order_groups_supported(),
+            supports_simd_group_functions: family_check
+                && msl_version >= MTLLanguageVersion::V2_1
+                && (device.supports_family(MTLGPUFamily::Apple6)
+                    || device.supports_family(MTLGPUFamily::Mac2)),
+            simd_width: Self::query_simd_width(device),
             can_set_next_drawable_timeout: if os_is_mac {
                 Self::version_at_least(major, minor, 10, 13)
             } else {
@@ -850,6 +1425,49 @@ impl super::PrivateCapabilities {
             } else {
                 Self::version_at_least(major, minor, 11, 0)
             },
+            supports_registry_id: if os_is_mac {
+                Self::version_at_least(major, minor, 10, 13)
+            } else {
+                Self::version_at_least(major, minor, 11, 0)
+            },
+            supports_memory_barriers: if os_is_mac {
+                Self::version_at_least(major, minor, 10, 14)
+            } else {
+                Self::version_at_least(major, minor, 12, 0)
+            },
+            supports_counter_sampling,
+            supports_timestamp_query,
+            supports_command_buffer_error_options: if os_is_mac {
+                Self::version_at_least(major, minor, 11, 0)
+            } else {
+                Self::version_at_least(major, minor, 14, 0)
+            },
+            supports_shared_event: if os_is_mac {
+                Self::version_at_least(major, minor, 10, 14)
+            } else {
+                Self::version_at_least(major, minor, 12, 0)
+            },
+            supports_texture_buffer: if os_is_mac {
+                Self::version_at_least(major, minor, 10, 14)
+            } else {
+                Self::version_at_least(major, minor, 12, 0)
+            },
+            // Apple GPUs shade whole quads and keep the helper lanes alive
+            // through divergent branches, so derivatives stay defined.
+            non_uniform_derivatives: apple_gpu,
+            tile_based_rendering: apple_gpu,
+            // Unlike textures, buffers are writable from every function type
+            // regardless of the read-write texture tier.
+            read_write_buffer_stages: wgt::ShaderStages::VERTEX_FRAGMENT
+                | wgt::ShaderStages::COMPUTE,
+            max_vertex_amplification_count,
+            max_viewports: if Self::supports_any(device, MULTIPLE_VIEWPORT_SUPPORT) {
+                16
+            } else {
+                1
+            },
+            // The same on every family so far.
+            max_sampler_anisotropy: 16,
         }
     }
 
@@ -857,7 +1475,6 @@ impl super::PrivateCapabilities {
         use wgt::Features as F;
 
         let mut features = F::empty()
-            | F::DEPTH_CLAMPING
             | F::TEXTURE_COMPRESSION_BC
             | F::MAPPABLE_PRIMARY_BUFFERS
             | F::VERTEX_WRITABLE_STORAGE
@@ -865,6 +1482,8 @@ impl super::PrivateCapabilities {
             | F::POLYGON_MODE_LINE
             | F::CLEAR_COMMANDS;
 
+        // Clamping is done by switching the depth clip mode from `Clip` to `Clamp`.
+        features.set(F::DEPTH_CLAMPING, self.depth_clip_mode);
         features.set(
             F::TEXTURE_BINDING_ARRAY
                 | F::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING
@@ -884,6 +1503,30 @@ impl super::PrivateCapabilities {
             F::ADDRESS_MODE_CLAMP_TO_BORDER,
             self.sampler_clamp_to_border,
         );
+        features.set(F::PRECISE_OCCLUSION_QUERY, self.counting_occlusion_query);
+        // `Depth32Float_Stencil8` is supported by all Metal devices.
+        features.insert(F::DEPTH32FLOAT_STENCIL8);
+        // So are the 16-bit normalized formats, but with fewer capabilities on iOS.
+        features.insert(F::TEXTURE_FORMAT_16BIT_NORM);
+        // The `baseInstance` of indirect arguments is only honored by the
+        // families that support base instances in direct draws.
+        features.set(F::INDIRECT_FIRST_INSTANCE, self.base_instance);
+        features.set(F::SHADER_PRIMITIVE_INDEX, self.supports_primitive_index);
+        features.set(F::SHADER_F16, self.supports_shader_f16);
+        features.set(F::RASTER_ORDER_GROUPS, self.supports_raster_order_groups);
+        features.set(F::SUBGROUP, self.supports_simd_group_functions);
+        features.insert(F::RG11B10UFLOAT_RENDERABLE);
+        features.set(F::TIMESTAMP_QUERY, self.supports_timestamp_query);
+        // The draws are issued one by one for now, but only exposed on the families
+        // that can execute them from an indirect command buffer. The count variant
+        // would need them to be encoded on the GPU, which isn't implemented.
+        features.set(
+            F::MULTI_DRAW_INDIRECT,
+            self.supports_indirect_command_buffers,
+        );
+        // `CONSERVATIVE_RASTERIZATION` is left out on purpose: no family has a
+        // conservative rasterization state in the pipeline descriptor, so
+        // `PrimitiveState::conservative` would be silently ignored.
 
         features
     }
@@ -894,23 +1537,41 @@ impl super::PrivateCapabilities {
             wgt::DownlevelFlags::CUBE_ARRAY_TEXTURES,
             self.texture_cube_array,
         );
-        //TODO: separate the mutable comparisons from immutable ones
+        // Samplers declared in MSL can always compare, but only some families
+        // accept a comparison function on `MTLSamplerDescriptor`.
+        downlevel
+            .flags
+            .set(wgt::DownlevelFlags::COMPARISON_SAMPLERS, true);
         downlevel.flags.set(
-            wgt::DownlevelFlags::COMPARISON_SAMPLERS,
+            wgt::DownlevelFlags::MUTABLE_COMPARISON_SAMPLERS,
             self.mutable_comparison_samplers,
         );
         downlevel
             .flags
             .set(wgt::DownlevelFlags::ANISOTROPIC_FILTERING, true);
+        downlevel.flags.set(
+            wgt::DownlevelFlags::NON_UNIFORM_DERIVATIVES,
+            self.non_uniform_derivatives,
+        );
+        downlevel.flags.set(
+            wgt::DownlevelFlags::CROSS_STAGE_WRITABLE_STORAGE,
+            !self.tile_based_rendering,
+        );
+        // The first Apple GPU families ignore the clamp of `setDepthBias`.
+        downlevel
+            .flags
+            .set(wgt::DownlevelFlags::DEPTH_BIAS_CLAMP, self.depth_bias_clamp);
 
         let base = wgt::Limits::default();
         crate::Capabilities {
             limits: wgt::Limits {
-                max_texture_dimension_1d: self.max_texture_size as u32,
+                max_texture_dimension_1d: self.max_texture_1d_size as u32,
                 max_texture_dimension_2d: self.max_texture_size as u32,
                 max_texture_dimension_3d: self.max_texture_3d_size as u32,
                 max_texture_array_layers: self.max_texture_layers as u32,
-                max_bind_groups: 8,
+                // Bind groups are flattened into the per-stage resource tables, so
+                // their number is only bounded by the per-stage limits below.
+                max_bind_groups: crate::MAX_BIND_GROUPS as u32,
                 max_dynamic_uniform_buffers_per_pipeline_layout: base
                     .max_dynamic_uniform_buffers_per_pipeline_layout,
                 max_dynamic_storage_buffers_per_pipeline_layout: base
@@ -925,9 +1586,19 @@ impl super::PrivateCapabilities {
                 max_vertex_buffers: base.max_vertex_buffers,
                 max_vertex_attributes: base.max_vertex_attributes,
                 max_vertex_buffer_array_stride: base.max_vertex_buffer_array_stride,
+                max_inter_stage_shader_components: self.max_fragment_input_components as u32,
                 max_push_constant_size: 0x1000,
                 min_uniform_buffer_offset_alignment: self.buffer_alignment as u32,
                 min_storage_buffer_offset_alignment: self.buffer_alignment as u32,
+                // The total is capped separately from the dimensions, and
+                // can't exceed what the dimensions allow together either.
+                max_compute_invocations_per_workgroup: self
+                    .max_total_threads_per_threadgroup
+                    .min(self.max_threads_per_threadgroup.iter().product()),
+                max_compute_workgroup_size_x: self.max_threads_per_threadgroup[0],
+                max_compute_workgroup_size_y: self.max_threads_per_threadgroup[1],
+                max_compute_workgroup_size_z: self.max_threads_per_threadgroup[2],
+                max_compute_workgroups_per_dimension: self.max_threadgroups_per_dimension,
             },
             alignments: crate::Alignments {
                 buffer_copy_offset: wgt::BufferSize::new(self.buffer_alignment).unwrap(),
@@ -937,6 +1608,89 @@ impl super::PrivateCapabilities {
         }
     }
 
+    /// Picks the depth format best suited for the device, given whether stencil
+    /// is needed and the minimum number of depth bits.
+    ///
+    /// Without stencil, `Depth16Unorm` is chosen for up to 16 bits where it's
+    /// supported, since it's half the size. Otherwise it's `Depth32Float`: it
+    /// takes the same space as the packed 24-bit format and gives more precision.
+    ///
+    /// With stencil, `Depth24PlusStencil8` is chosen for up to 24 bits where it
+    /// maps to the packed `Depth24Unorm_Stencil8`, which is half the size of
+    /// `Depth32Float_Stencil8`. Otherwise it would fall back to the latter, so
+    /// `Depth32FloatStencil8` is chosen directly.
+    pub fn best_depth_format(&self, needs_stencil: bool, min_bits: u32) -> wgt::TextureFormat {
+        if needs_stencil {
+            if min_bits <= 24 && self.format_depth24_stencil8 {
+                wgt::TextureFormat::Depth24PlusStencil8
+            } else {
+                wgt::TextureFormat::Depth32FloatStencil8
+            }
+        } else if min_bits <= 16 && self.format_depth16unorm {
+            wgt::TextureFormat::Depth16Unorm
+        } else {
+            wgt::TextureFormat::Depth32Float
+        }
+    }
+
+    /// Returns the usages of drawables of `format` once `framebufferOnly` is
+    /// turned off, which allows copies, and shader access where the format
+    /// supports it. Surfaces configured with `COLOR_TARGET` only keep it on.
+    pub fn surface_usage(&self, format: wgt::TextureFormat) -> crate::TextureUses {
+        let mut usage = crate::TextureUses::COLOR_TARGET
+            | crate::TextureUses::COPY_SRC
+            | crate::TextureUses::COPY_DST;
+        let shader_access = match format {
+            wgt::TextureFormat::Bgra8UnormSrgb => self.format_rgba8_srgb_all,
+            _ => true,
+        };
+        usage.set(
+            crate::TextureUses::STORAGE_READ | crate::TextureUses::STORAGE_WRITE,
+            shader_access,
+        );
+        usage
+    }
+
+    /// Returns whether a read-write storage buffer can be visible to `stages`.
+    ///
+    /// On top of the stages themselves, tile-based GPUs process the whole geometry
+    /// before shading the fragments, so the writes of one stage aren't visible
+    /// to the other within a render pass.
+    pub fn supports_read_write_buffer(&self, stages: wgt::ShaderStages) -> bool {
+        self.read_write_buffer_stages.contains(stages)
+            && !(self.tile_based_rendering && stages.contains(wgt::ShaderStages::VERTEX_FRAGMENT))
+    }
+
+    /// Returns whether textures of `format` can be sampled with a filtering sampler
+    /// when they aren't compared, which `Depth32Float` doesn't support on Apple GPUs.
+    ///
+    /// Non-comparison sampling without filtering works for every depth format.
+    pub fn supports_depth_filtering(&self, format: mtl::MTLPixelFormat) -> bool {
+        match format {
+            mtl::MTLPixelFormat::Depth32Float | mtl::MTLPixelFormat::Depth32Float_Stencil8 => {
+                self.format_depth32float_filter
+            }
+            _ => true,
+        }
+    }
+
+    /// Sample counts supported by the attachments of `format`, as a mask of the counts.
+    ///
+    /// Depth and stencil formats support every count of the device, and so do the
+    /// color formats except for the 128-bit ones, which only multisample on macOS.
+    pub fn format_sample_count_mask(&self, format: wgt::TextureFormat) -> u8 {
+        use wgt::TextureFormat as Tf;
+
+        if format.describe().block_dimensions != (1, 1) || format == Tf::Rgb9e5Ufloat {
+            // Not renderable at all.
+            return 1;
+        }
+        match format {
+            Tf::Rgba32Uint | Tf::Rgba32Sint | Tf::Rgba32Float if !self.format_rgba32float_all => 1,
+            _ => self.sample_count_mask,
+        }
+    }
+
     pub fn map_format(&self, format: wgt::TextureFormat) -> mtl::MTLPixelFormat {
         use mtl::MTLPixelFormat::*;
         use wgt::TextureFormat as Tf;
@@ -949,6 +1703,8 @@ impl super::PrivateCapabilities {
             Tf::R16Uint => R16Uint,
             Tf::R16Sint => R16Sint,
             Tf::R16Float => R16Float,
+            Tf::R16Unorm => R16Unorm,
+            Tf::R16Snorm => R16Snorm,
             Tf::Rg8Unorm => RG8Unorm,
             Tf::Rg8Snorm => RG8Snorm,
             Tf::Rg8Uint => RG8Uint,
@@ -959,6 +1715,8 @@ impl super::PrivateCapabilities {
             Tf::Rg16Uint => RG16Uint,
             Tf::Rg16Sint => RG16Sint,
             Tf::Rg16Float => RG16Float,
+            Tf::Rg16Unorm => RG16Unorm,
+            Tf::Rg16Snorm => RG16Snorm,
             Tf::Rgba8Unorm => RGBA8Unorm,
             Tf::Rgba8UnormSrgb => RGBA8Unorm_sRGB,
             Tf::Bgra8UnormSrgb => BGRA8Unorm_sRGB,
@@ -967,6 +1725,7 @@ impl super::PrivateCapabilities {
             Tf::Rgba8Uint => RGBA8Uint,
             Tf::Rgba8Sint => RGBA8Sint,
             Tf::Rgb10a2Unorm => RGB10A2Unorm,
+            Tf::Rgb10a2Uint => RGB10A2Uint,
             Tf::Rg11b10Float => RG11B10Float,
             Tf::Rg32Uint => RG32Uint,
             Tf::Rg32Sint => RG32Sint,
@@ -974,9 +1733,19 @@ impl super::PrivateCapabilities {
             Tf::Rgba16Uint => RGBA16Uint,
             Tf::Rgba16Sint => RGBA16Sint,
             Tf::Rgba16Float => RGBA16Float,
+            Tf::Rgba16Unorm => RGBA16Unorm,
+            Tf::Rgba16Snorm => RGBA16Snorm,
             Tf::Rgba32Uint => RGBA32Uint,
             Tf::Rgba32Sint => RGBA32Sint,
             Tf::Rgba32Float => RGBA32Float,
+            Tf::Stencil8 => {
+                if self.format_stencil8 {
+                    Stencil8
+                } else {
+                    Depth32Float_Stencil8
+                }
+            }
+            Tf::Depth16Unorm => Depth16Unorm,
             Tf::Depth32Float => Depth32Float,
             Tf::Depth24Plus => {
                 if self.format_depth24_stencil8 {
@@ -992,6 +1761,7 @@ impl super::PrivateCapabilities {
                     Depth32Float_Stencil8
                 }
             }
+            Tf::Depth32FloatStencil8 => Depth32Float_Stencil8,
             Tf::Rgb9e5Ufloat => RGB9E5Float,
             Tf::Bc1RgbaUnorm => BC1_RGBA,
             Tf::Bc1RgbaUnormSrgb => BC1_RGBA_sRGB,
@@ -1029,12 +1799,12 @@ impl super::PrivateCapabilities {
             Tf::Astc8x5RgbaUnormSrgb => ASTC_8x5_sRGB,
             Tf::Astc8x6RgbaUnorm => ASTC_8x6_LDR,
             Tf::Astc8x6RgbaUnormSrgb => ASTC_8x6_sRGB,
-            Tf::Astc10x5RgbaUnorm => ASTC_8x8_LDR,
-            Tf::Astc10x5RgbaUnormSrgb => ASTC_8x8_sRGB,
-            Tf::Astc10x6RgbaUnorm => ASTC_10x5_LDR,
-            Tf::Astc10x6RgbaUnormSrgb => ASTC_10x5_sRGB,
-            Tf::Astc8x8RgbaUnorm => ASTC_10x6_LDR,
-            Tf::Astc8x8RgbaUnormSrgb => ASTC_10x6_sRGB,
+            Tf::Astc10x5RgbaUnorm => ASTC_10x5_LDR,
+            Tf::Astc10x5RgbaUnormSrgb => ASTC_10x5_sRGB,
+            Tf::Astc10x6RgbaUnorm => ASTC_10x6_LDR,
+            Tf::Astc10x6RgbaUnormSrgb => ASTC_10x6_sRGB,
+            Tf::Astc8x8RgbaUnorm => ASTC_8x8_LDR,
+            Tf::Astc8x8RgbaUnormSrgb => ASTC_8x8_sRGB,
             Tf::Astc10x8RgbaUnorm => ASTC_10x8_LDR,
             Tf::Astc10x8RgbaUnormSrgb => ASTC_10x8_sRGB,
             Tf::Astc10x10RgbaUnorm => ASTC_10x10_LDR,
@@ -1056,4 +1826,772 @@ impl super::PrivateDisabilities {
             broken_layered_clear_image: is_intel,
         }
     }
+
+    fn flags(&self) -> super::Disabilities {
+        let mut flags = super::Disabilities::empty();
+        flags.set(
+            super::Disabilities::BROKEN_VIEWPORT_NEAR_DEPTH,
+            self.broken_viewport_near_depth,
+        );
+        flags.set(
+            super::Disabilities::BROKEN_LAYERED_CLEAR_IMAGE,
+            self.broken_layered_clear_image,
+        );
+        flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Adapter, AdapterShared};
+    use std::sync::Arc;
+    use wgt::TextureFormat as Tf;
+
+    #[test]
+    fn astc_block_dimensions() {
+        let caps = AdapterShared::for_tests().private_caps;
+        let formats = [
+            Tf::Astc4x4RgbaUnorm,
+            Tf::Astc4x4RgbaUnormSrgb,
+            Tf::Astc5x4RgbaUnorm,
+            Tf::Astc5x4RgbaUnormSrgb,
+            Tf::Astc5x5RgbaUnorm,
+            Tf::Astc5x5RgbaUnormSrgb,
+            Tf::Astc6x5RgbaUnorm,
+            Tf::Astc6x5RgbaUnormSrgb,
+            Tf::Astc6x6RgbaUnorm,
+            Tf::Astc6x6RgbaUnormSrgb,
+            Tf::Astc8x5RgbaUnorm,
+            Tf::Astc8x5RgbaUnormSrgb,
+            Tf::Astc8x6RgbaUnorm,
+            Tf::Astc8x6RgbaUnormSrgb,
+            Tf::Astc10x5RgbaUnorm,
+            Tf::Astc10x5RgbaUnormSrgb,
+            Tf::Astc10x6RgbaUnorm,
+            Tf::Astc10x6RgbaUnormSrgb,
+            Tf::Astc8x8RgbaUnorm,
+            Tf::Astc8x8RgbaUnormSrgb,
+            Tf::Astc10x8RgbaUnorm,
+            Tf::Astc10x8RgbaUnormSrgb,
+            Tf::Astc10x10RgbaUnorm,
+            Tf::Astc10x10RgbaUnormSrgb,
+            Tf::Astc12x10RgbaUnorm,
+            Tf::Astc12x10RgbaUnormSrgb,
+            Tf::Astc12x12RgbaUnorm,
+            Tf::Astc12x12RgbaUnormSrgb,
+        ];
+        for &format in formats.iter() {
+            let info = format.describe();
+            let (width, height) = info.block_dimensions;
+            let expected = format!(
+                "ASTC_{}x{}_{}",
+                width,
+                height,
+                if info.srgb { "sRGB" } else { "LDR" }
+            );
+            assert_eq!(format!("{:?}", caps.map_format(format)), expected);
+        }
+    }
+
+    #[test]
+    fn best_depth_format() {
+        for &small_formats in [false, true].iter() {
+            let adapter = Adapter::for_tests(|shared| {
+                shared.private_caps.format_depth16unorm = small_formats;
+                shared.private_caps.format_depth24_stencil8 = small_formats;
+            });
+            let (depth, depth_stencil) = if small_formats {
+                (Tf::Depth16Unorm, Tf::Depth24PlusStencil8)
+            } else {
+                (Tf::Depth32Float, Tf::Depth32FloatStencil8)
+            };
+            assert_eq!(adapter.best_depth_format(false, 16), depth);
+            assert_eq!(adapter.best_depth_format(false, 24), Tf::Depth32Float);
+            assert_eq!(adapter.best_depth_format(true, 8), depth_stencil);
+            assert_eq!(adapter.best_depth_format(true, 24), depth_stencil);
+            assert_eq!(
+                adapter.best_depth_format(true, 32),
+                Tf::Depth32FloatStencil8
+            );
+        }
+    }
+
+    #[test]
+    fn depth16unorm_without_support() {
+        use crate::Adapter as _;
+
+        let adapter = Adapter::for_tests(|shared| shared.private_caps.format_depth16unorm = false);
+        let caps = unsafe { adapter.texture_format_capabilities(Tf::Depth16Unorm) };
+        assert!(!caps.contains(crate::TextureFormatCapabilities::DEPTH_STENCIL_ATTACHMENT));
+    }
+
+    #[test]
+    fn norm16_formats_on_ios() {
+        use crate::Adapter as _;
+
+        let adapter = Adapter::for_tests(|shared| shared.private_caps.format_r16_norm_all = false);
+        for &format in [Tf::R16Unorm, Tf::Rg16Snorm, Tf::Rgba16Unorm].iter() {
+            let caps = unsafe { adapter.texture_format_capabilities(format) };
+            assert!(caps.contains(
+                crate::TextureFormatCapabilities::SAMPLED_LINEAR
+                    | crate::TextureFormatCapabilities::COLOR_ATTACHMENT_BLEND
+            ));
+            assert!(!caps.contains(crate::TextureFormatCapabilities::MULTISAMPLE_RESOLVE));
+        }
+    }
+
+    #[test]
+    fn rgb10a2uint_never_blends() {
+        use crate::{Adapter as _, TextureFormatCapabilities as Tfc};
+
+        for &color_write in [false, true].iter() {
+            let adapter = Adapter::for_tests(|shared| {
+                shared.private_caps.format_rgb10a2_uint_color_write = color_write
+            });
+            let caps = unsafe { adapter.texture_format_capabilities(Tf::Rgb10a2Uint) };
+            assert!(caps.contains(Tfc::COLOR_ATTACHMENT));
+            assert!(!caps.intersects(Tfc::COLOR_ATTACHMENT_BLEND | Tfc::MULTISAMPLE_RESOLVE));
+            assert_eq!(caps.contains(Tfc::STORAGE), color_write);
+        }
+    }
+
+    #[test]
+    fn rg11b10_renderable_feature() {
+        let source = "
+            #include <metal_stdlib>
+            using namespace metal;
+
+            vertex float4 vs_main() {
+                return float4(0.0);
+            }
+
+            fragment float4 fs_main() {
+                return float4(1.0);
+            }
+        ";
+        let adapter = Adapter::for_tests(|_| {});
+        let device = adapter.shared.device.lock();
+        let library = device
+            .new_library_with_source(source, &mtl::CompileOptions::new())
+            .unwrap();
+
+        let descriptor = mtl::RenderPipelineDescriptor::new();
+        let vs = library.get_function("vs_main", None).unwrap();
+        let fs = library.get_function("fs_main", None).unwrap();
+        descriptor.set_vertex_function(Some(&vs));
+        descriptor.set_fragment_function(Some(&fs));
+        let attachment = descriptor.color_attachments().object_at(0).unwrap();
+        attachment.set_pixel_format(mtl::MTLPixelFormat::RG11B10Float);
+        attachment.set_blending_enabled(true);
+        attachment.set_source_rgb_blend_factor(mtl::MTLBlendFactor::SourceAlpha);
+        attachment.set_destination_rgb_blend_factor(mtl::MTLBlendFactor::OneMinusSourceAlpha);
+        assert!(device.new_render_pipeline_state(&descriptor).is_ok());
+    }
+
+    #[test]
+    fn r32_storage_atomics() {
+        use crate::{Adapter as _, TextureFormatCapabilities as Tfc};
+        use mtl::MTLReadWriteTextureTier as Tier;
+
+        for &(tier, atomic) in [
+            (Tier::TierNone, false),
+            (Tier::Tier1, false),
+            (Tier::Tier2, true),
+        ]
+        .iter()
+        {
+            let adapter = Adapter::for_tests(|shared| {
+                shared.private_caps.format_r32_all = true;
+                shared.private_caps.read_write_texture_tier = tier;
+            });
+            for &format in [Tf::R32Uint, Tf::R32Sint].iter() {
+                let caps = unsafe { adapter.texture_format_capabilities(format) };
+                assert_eq!(caps.contains(Tfc::STORAGE_ATOMIC), atomic);
+            }
+            let caps = unsafe { adapter.texture_format_capabilities(Tf::R32Float) };
+            assert!(!caps.contains(Tfc::STORAGE_ATOMIC));
+        }
+    }
+
+    #[test]
+    fn integer_formats_never_resolve() {
+        use crate::Adapter as _;
+
+        let adapter = Adapter::for_tests(|_| {});
+        let formats = [
+            Tf::R8Uint,
+            Tf::R8Sint,
+            Tf::R16Uint,
+            Tf::R16Sint,
+            Tf::Rg8Uint,
+            Tf::Rg8Sint,
+            Tf::R32Uint,
+            Tf::R32Sint,
+            Tf::Rg16Uint,
+            Tf::Rg16Sint,
+            Tf::Rgba8Uint,
+            Tf::Rgba8Sint,
+            Tf::Rg32Uint,
+            Tf::Rg32Sint,
+            Tf::Rgba16Uint,
+            Tf::Rgba16Sint,
+            Tf::Rgba32Uint,
+            Tf::Rgba32Sint,
+        ];
+        for &format in formats.iter() {
+            let caps = unsafe { adapter.texture_format_capabilities(format) };
+            assert!(
+                !caps.contains(crate::TextureFormatCapabilities::MULTISAMPLE_RESOLVE),
+                "{:?}",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn queried_threadgroup_memory() {
+        let shared = AdapterShared::for_tests();
+        assert_eq!(
+            shared.private_caps.max_total_threadgroup_memory as u64,
+            shared.device.lock().max_threadgroup_memory_length()
+        );
+    }
+
+    #[test]
+    fn compute_workgroup_limits() {
+        let mut caps = AdapterShared::for_tests().private_caps;
+        caps.max_threads_per_threadgroup = [1024, 512, 64];
+        caps.max_total_threads_per_threadgroup = 1024;
+        let limits = caps.capabilities().limits;
+        assert_eq!(limits.max_compute_workgroup_size_x, 1024);
+        assert_eq!(limits.max_compute_workgroup_size_y, 512);
+        assert_eq!(limits.max_compute_workgroup_size_z, 64);
+        assert_eq!(limits.max_compute_invocations_per_workgroup, 1024);
+
+        caps.max_threads_per_threadgroup = [16, 8, 4];
+        let limits = caps.capabilities().limits;
+        assert_eq!(limits.max_compute_invocations_per_workgroup, 512);
+    }
+
+    #[test]
+    fn format_sample_counts() {
+        let mut caps = AdapterShared::for_tests().private_caps;
+        caps.sample_count_mask = 1 | 2 | 4 | 8;
+        caps.format_rgba32float_all = false;
+        assert_eq!(caps.format_sample_count_mask(Tf::Rgba8Unorm), 15);
+        assert_eq!(caps.format_sample_count_mask(Tf::Depth32Float), 15);
+        assert_eq!(caps.format_sample_count_mask(Tf::Rgba32Float), 1);
+        assert_eq!(caps.format_sample_count_mask(Tf::Bc1RgbaUnorm), 1);
+
+        caps.format_rgba32float_all = true;
+        assert_eq!(caps.format_sample_count_mask(Tf::Rgba32Float), 15);
+    }
+
+    #[test]
+    fn read_write_buffer_stages() {
+        use wgt::ShaderStages as Ss;
+
+        let mut caps = AdapterShared::for_tests().private_caps;
+        caps.read_write_buffer_stages = Ss::FRAGMENT | Ss::COMPUTE;
+        caps.tile_based_rendering = false;
+        assert!(caps.supports_read_write_buffer(Ss::COMPUTE));
+        assert!(caps.supports_read_write_buffer(Ss::FRAGMENT));
+        assert!(!caps.supports_read_write_buffer(Ss::VERTEX));
+
+        caps.read_write_buffer_stages = Ss::VERTEX_FRAGMENT | Ss::COMPUTE;
+        assert!(caps.supports_read_write_buffer(Ss::VERTEX_FRAGMENT));
+        caps.tile_based_rendering = true;
+        assert!(caps.supports_read_write_buffer(Ss::VERTEX));
+        assert!(!caps.supports_read_write_buffer(Ss::VERTEX_FRAGMENT));
+    }
+
+    #[test]
+    fn depth_filtering() {
+        use mtl::MTLPixelFormat as Pf;
+
+        let mut caps = AdapterShared::for_tests().private_caps;
+        caps.format_depth32float_filter = false;
+        assert!(!caps.supports_depth_filtering(Pf::Depth32Float));
+        assert!(!caps.supports_depth_filtering(Pf::Depth32Float_Stencil8));
+        assert!(caps.supports_depth_filtering(Pf::Depth16Unorm));
+        caps.format_depth32float_filter = true;
+        assert!(caps.supports_depth_filtering(Pf::Depth32Float));
+    }
+
+    #[test]
+    fn write_texture_mips() {
+        use crate::{Adapter as _, Device as _};
+
+        let adapter = Adapter::for_tests(|_| {});
+        let open = unsafe { adapter.open(wgt::Features::empty()) }.unwrap();
+
+        // 8x8, 4x4, 2x2 and 1x1 levels, each filled with its index
+        const SIZE: u32 = 8;
+        const MIP_LEVELS: u32 = 4;
+        let texture = unsafe {
+            open.device.create_texture(&crate::TextureDescriptor {
+                label: None,
+                size: wgt::Extent3d {
+                    width: SIZE,
+                    height: SIZE,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: MIP_LEVELS,
+                sample_count: 1,
+                dimension: wgt::TextureDimension::D2,
+                format: Tf::R8Uint,
+                usage: crate::TextureUses::COPY_SRC | crate::TextureUses::COPY_DST,
+                memory_flags: crate::MemoryFlags::empty(),
+            })
+        }
+        .unwrap();
+        let data = (0..MIP_LEVELS)
+            .flat_map(|level| {
+                let size = (SIZE >> level) as usize;
+                std::iter::repeat(level as u8).take(size * size)
+            })
+            .collect::<Vec<u8>>();
+        unsafe { open.device.write_texture_mips(&open.queue, &texture, &data) }.unwrap();
+
+        let readback = open.device.shared.device.lock().new_buffer(
+            data.len() as u64,
+            mtl::MTLResourceOptions::StorageModeShared,
+        );
+        let queue = open.queue.raw.lock();
+        let cmd_buf = queue.new_command_buffer();
+        let encoder = cmd_buf.new_blit_command_encoder();
+        let mut offset = 0;
+        for level in 0..MIP_LEVELS {
+            let size = (SIZE >> level) as u64;
+            encoder.copy_from_texture_to_buffer(
+                &texture.raw,
+                0,
+                level as u64,
+                mtl::MTLOrigin { x: 0, y: 0, z: 0 },
+                mtl::MTLSize::new(size, size, 1),
+                &readback,
+                offset,
+                size,
+                size * size,
+                mtl::MTLBlitOption::empty(),
+            );
+            offset += size * size;
+        }
+        encoder.end_encoding();
+        cmd_buf.commit();
+        cmd_buf.wait_until_completed();
+
+        let texels =
+            unsafe { std::slice::from_raw_parts(readback.contents() as *const u8, data.len()) };
+        assert_eq!(texels, &data[..]);
+    }
+
+    #[test]
+    fn buffer_texture_views() {
+        use crate::{Adapter as _, Device as _};
+
+        let adapter = Adapter::for_tests(|_| {});
+        let supported = adapter.supports_texture_buffers();
+        let open = unsafe { adapter.open(wgt::Features::empty()) }.unwrap();
+        let buffer = unsafe {
+            open.device.create_buffer(&crate::BufferDescriptor {
+                label: None,
+                size: 1024,
+                usage: crate::BufferUses::STORAGE_READ,
+                memory_flags: crate::MemoryFlags::empty(),
+            })
+        }
+        .unwrap();
+
+        let create = |format, offset, texel_count| unsafe {
+            open.device
+                .create_buffer_texture_view(&buffer, format, offset, texel_count)
+        };
+        assert_eq!(create(Tf::Rgba8Unorm, 0, 256).is_ok(), supported);
+        assert!(create(Tf::Rgba8Unorm, 0, 257).is_err());
+        assert!(create(Tf::Rgba8Unorm, 1, 16).is_err());
+        assert!(create(Tf::Bc1RgbaUnorm, 0, 16).is_err());
+    }
+
+    #[test]
+    fn external_device_adapter() {
+        use crate::{Adapter as _, Instance as _};
+
+        let device = AdapterShared::for_tests().device.into_inner();
+        let instance = unsafe {
+            super::super::Instance::init(&crate::InstanceDescriptor {
+                name: "test",
+                flags: crate::InstanceFlags::empty(),
+            })
+        }
+        .unwrap();
+        let exposed = instance.expose_adapter(device.clone());
+        assert_eq!(exposed.info.name, device.name());
+        assert_eq!(exposed.info.device as u64, device.registry_id());
+        assert_eq!(exposed.info.backend, wgt::Backend::Metal);
+        assert_eq!(
+            exposed.adapter.as_raw_device().registry_id(),
+            device.registry_id()
+        );
+        let open = unsafe { exposed.adapter.open(wgt::Features::empty()) }.unwrap();
+        assert_eq!(
+            open.device.shared.device.lock().registry_id(),
+            device.registry_id()
+        );
+    }
+
+    #[test]
+    fn capture_to_file_unsupported() {
+        use crate::Adapter as _;
+
+        let adapter =
+            Adapter::for_tests(|shared| shared.private_caps.supports_capture_to_file = false);
+        let open = unsafe { adapter.open(wgt::Features::empty()) }.unwrap();
+        let result = unsafe {
+            open.device
+                .start_capture_to_file(&open.queue, std::path::Path::new("frame.gputrace"))
+        };
+        assert_eq!(result, Err(super::super::CaptureError::Unsupported));
+    }
+
+    #[test]
+    fn heap_aliasing() {
+        use crate::{Adapter as _, Device as _};
+
+        let adapter = Adapter::for_tests(|_| {});
+        let caps = &adapter.shared.private_caps;
+        let supported = caps.resource_heaps && caps.supports_memory_barriers;
+        let open = unsafe { adapter.open(wgt::Features::empty()) }.unwrap();
+        let heap = match unsafe { open.device.create_heap(1 << 20, None) } {
+            Ok(heap) => heap,
+            Err(_) => {
+                assert!(!supported);
+                return;
+            }
+        };
+
+        let desc = crate::BufferDescriptor {
+            label: None,
+            size: 1024,
+            usage: crate::BufferUses::STORAGE_READ,
+            memory_flags: crate::MemoryFlags::empty(),
+        };
+        let placed = unsafe { open.device.create_buffer_in_heap(&heap, &desc) }.unwrap();
+        let dedicated = unsafe { open.device.create_buffer(&desc) }.unwrap();
+        assert!(unsafe { open.device.make_buffer_aliasable(&dedicated) }.is_err());
+        assert!(unsafe { open.device.make_buffer_aliasable(&placed) }.is_ok());
+        assert!(placed.raw.is_aliasable());
+
+        let mappable = crate::BufferDescriptor {
+            usage: crate::BufferUses::MAP_READ,
+            ..desc
+        };
+        assert!(unsafe { open.device.create_buffer_in_heap(&heap, &mappable) }.is_err());
+    }
+
+    #[test]
+    fn frames_in_flight_limit() {
+        use std::sync::{
+            atomic::{AtomicBool, Ordering},
+            Barrier,
+        };
+
+        let frames = Arc::new(super::super::FramesInFlight::default());
+        frames.begin(2);
+        frames.begin(2);
+
+        let barrier = Arc::new(Barrier::new(2));
+        let begun = Arc::new(AtomicBool::new(false));
+        let waiter = {
+            let frames = Arc::clone(&frames);
+            let barrier = Arc::clone(&barrier);
+            let begun = Arc::clone(&begun);
+            std::thread::spawn(move || {
+                barrier.wait();
+                // Blocks until one of the frames is completed.
+                frames.begin(2);
+                begun.store(true, Ordering::SeqCst);
+            })
+        };
+        barrier.wait();
+        assert!(!begun.load(Ordering::SeqCst));
+        frames.end();
+        waiter.join().unwrap();
+        assert!(begun.load(Ordering::SeqCst));
+        assert_eq!(*frames.count.lock(), 2);
+
+        frames.end();
+        frames.end();
+        assert_eq!(*frames.count.lock(), 0);
+    }
+
+    #[test]
+    fn debug_markers_skipped() {
+        use crate::{Adapter as _, CommandEncoder as _, Device as _};
+        use mtl::MTLFeatureSet as Fs;
+
+        // The first versions of every family predate debug groups.
+        for feature_set in &[
+            Fs::iOS_GPUFamily1_v1,
+            Fs::iOS_GPUFamily1_v2,
+            Fs::iOS_GPUFamily2_v1,
+            Fs::iOS_GPUFamily3_v1,
+            Fs::macOS_GPUFamily1_v1,
+            Fs::tvOS_GPUFamily1_v1,
+        ] {
+            assert!(!super::DEBUG_MARKER_SUPPORT.contains(feature_set));
+        }
+
+        let adapter =
+            Adapter::for_tests(|shared| shared.private_caps.supports_debug_markers = false);
+        assert!(!adapter.supports_debug_markers());
+        let open = unsafe { adapter.open(wgt::Features::empty()) }.unwrap();
+        let mut encoder = unsafe {
+            open.device
+                .create_command_encoder(&crate::CommandEncoderDescriptor {
+                    label: None,
+                    queue: &open.queue,
+                })
+        }
+        .unwrap();
+        unsafe {
+            encoder.begin_encoding(Some("encoder")).unwrap();
+            encoder.begin_debug_marker("group");
+            encoder.insert_debug_marker("marker");
+            encoder.end_debug_marker();
+        }
+        // No blit encoder is started just to hold the markers.
+        assert!(encoder.state.blit.is_none());
+        unsafe { encoder.discard_encoding() };
+    }
+
+    #[test]
+    fn memory_pressure_callback() {
+        use crate::{Adapter as _, Device as _};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let adapter = Adapter::for_tests(|_| {});
+        let mut open = unsafe { adapter.open(wgt::Features::empty()) }.unwrap();
+        if open.device.recommended_max_working_set_size().is_none() {
+            return;
+        }
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        // Any allocation is past a threshold of 0.
+        open.device.set_memory_pressure_callback(
+            0.0,
+            Some(Box::new(move |allocated, budget| {
+                assert!(allocated > 0 && budget > 0);
+                counter.fetch_add(1, Ordering::Relaxed);
+            })),
+        );
+        let desc = crate::BufferDescriptor {
+            label: None,
+            size: 1 << 16,
+            usage: crate::BufferUses::STORAGE_READ,
+            memory_flags: crate::MemoryFlags::empty(),
+        };
+        let _first = unsafe { open.device.create_buffer(&desc) }.unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        // Staying past the threshold doesn't call it again.
+        let _second = unsafe { open.device.create_buffer(&desc) }.unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn pipeline_cache_round_trip() {
+        use crate::Adapter as _;
+
+        let adapter = Adapter::for_tests(|_| {});
+        let supported = adapter.shared.private_caps.supports_binary_archives;
+
+        let mut open = unsafe { adapter.open(wgt::Features::empty()) }.unwrap();
+        assert!(open.device.pipeline_cache_data().is_none());
+        assert_eq!(open.device.enable_pipeline_cache(None), supported);
+        if !supported {
+            assert!(open.device.pipeline_cache_data().is_none());
+            return;
+        }
+        let data = open.device.pipeline_cache_data().unwrap();
+
+        let mut reopened = unsafe { adapter.open(wgt::Features::empty()) }.unwrap();
+        assert!(reopened.device.enable_pipeline_cache(Some(&data)));
+        // Garbage falls back to an empty cache.
+        assert!(reopened.device.enable_pipeline_cache(Some(&[1, 2, 3])));
+        assert!(reopened.device.pipeline_cache_data().is_some());
+    }
+
+    #[test]
+    fn multiple_queues() {
+        let adapter = Adapter::for_tests(|_| {});
+        let count = adapter.exposed_queues();
+        assert!(count > 1);
+
+        let (_device, queues) =
+            unsafe { adapter.open_with_queues(wgt::Features::empty(), count) }.unwrap();
+        assert_eq!(queues.len(), count);
+        assert!(!std::ptr::eq::<mtl::CommandQueueRef>(
+            &**queues[0].raw.lock(),
+            &**queues[1].raw.lock()
+        ));
+
+        assert_eq!(
+            unsafe { adapter.open_with_queues(wgt::Features::empty(), 0) }.err(),
+            Some(super::super::QueueCountError::Zero)
+        );
+        assert_eq!(
+            unsafe { adapter.open_with_queues(wgt::Features::empty(), count + 1) }.err(),
+            Some(super::super::QueueCountError::TooMany {
+                requested: count + 1,
+                exposed: count,
+            })
+        );
+    }
+
+    #[test]
+    fn raster_order_groups_feature() {
+        let adapter = Adapter::for_tests(|_| {});
+        if !adapter.supports_raster_order_groups() {
+            return;
+        }
+
+        let source = "
+            #include <metal_stdlib>
+            using namespace metal;
+
+            vertex float4 vs_main() {
+                return float4(0.0);
+            }
+
+            fragment void fs_main(device atomic_uint *count [[buffer(0), raster_order_group(0)]]) {
+                atomic_fetch_add_explicit(count, 1, memory_order_relaxed);
+            }
+        ";
+        let options = mtl::CompileOptions::new();
+        options.set_language_version(mtl::MTLLanguageVersion::V2_0);
+        let device = adapter.shared.device.lock();
+        let library = device.new_library_with_source(source, &options).unwrap();
+
+        let descriptor = mtl::RenderPipelineDescriptor::new();
+        let vs = library.get_function("vs_main", None).unwrap();
+        let fs = library.get_function("fs_main", None).unwrap();
+        descriptor.set_vertex_function(Some(&vs));
+        descriptor.set_fragment_function(Some(&fs));
+        descriptor
+            .color_attachments()
+            .object_at(0)
+            .unwrap()
+            .set_pixel_format(mtl::MTLPixelFormat::RGBA8Unorm);
+        assert!(device.new_render_pipeline_state(&descriptor).is_ok());
+    }
+
+    #[test]
+    fn adapter_vendors() {
+        let names = [
+            ("Apple M1 Pro", super::VENDOR_ID_APPLE),
+            ("AMD Radeon Pro 5500M", super::VENDOR_ID_AMD),
+            ("Intel(R) UHD Graphics 630", super::VENDOR_ID_INTEL),
+            ("NVIDIA GeForce GT 750M", super::VENDOR_ID_NVIDIA),
+            ("Unknown GPU", 0),
+        ];
+        for &(name, vendor) in names.iter() {
+            assert_eq!(super::vendor_id_from_name(name), vendor, "{}", name);
+        }
+
+        let adapter = Adapter::for_tests(|_| {});
+        let info = adapter.info();
+        assert_eq!(info.name, adapter.as_raw_device().name());
+        if info.vendor == super::VENDOR_ID_APPLE || adapter.is_low_power() {
+            assert_eq!(info.device_type, wgt::DeviceType::IntegratedGpu);
+        } else {
+            assert_eq!(info.device_type, wgt::DeviceType::DiscreteGpu);
+        }
+        assert!(!adapter.is_headless() || cfg!(target_os = "macos"));
+    }
+
+    #[test]
+    fn surface_storage_usage() {
+        use crate::{Adapter as _, Instance as _, Surface as _};
+
+        let instance = unsafe {
+            super::super::Instance::init(&crate::InstanceDescriptor {
+                name: "test",
+                flags: crate::InstanceFlags::empty(),
+            })
+        }
+        .unwrap();
+        let mut surface = instance.create_surface_from_layer(&mtl::MetalLayer::new());
+
+        let adapter =
+            Adapter::for_tests(|shared| shared.private_caps.format_rgba8_srgb_all = false);
+        let open = unsafe { adapter.open(wgt::Features::empty()) }.unwrap();
+        let config = |format| crate::SurfaceConfiguration {
+            swap_chain_size: 3,
+            present_mode: wgt::PresentMode::Fifo,
+            composite_alpha_mode: crate::CompositeAlphaMode::Opaque,
+            format,
+            extent: wgt::Extent3d {
+                width: 64,
+                height: 64,
+                depth_or_array_layers: 1,
+            },
+            usage: crate::TextureUses::COLOR_TARGET | crate::TextureUses::STORAGE_WRITE,
+        };
+        // sRGB drawables can't be written by shaders without `format_rgba8_srgb_all`.
+        assert!(unsafe { surface.configure(&open.device, &config(Tf::Bgra8Unorm)) }.is_ok());
+        assert!(unsafe { surface.configure(&open.device, &config(Tf::Bgra8UnormSrgb)) }.is_err());
+    }
+
+    #[test]
+    fn headless_surface_capabilities() {
+        use crate::{Adapter as _, Instance as _};
+
+        let instance = unsafe {
+            super::super::Instance::init(&crate::InstanceDescriptor {
+                name: "test",
+                flags: crate::InstanceFlags::empty(),
+            })
+        }
+        .unwrap();
+        let surface = instance.create_surface_from_layer(&mtl::MetalLayer::new());
+
+        let adapter = Adapter::for_tests(|shared| shared.private_caps.headless = true);
+        let caps = unsafe { adapter.surface_capabilities(&surface) }.unwrap();
+        assert_eq!(caps.present_modes, [wgt::PresentMode::Immediate]);
+        assert_eq!(caps.current_extent, None);
+    }
+
+    #[test]
+    fn simd_group_functions() {
+        let adapter = Adapter::for_tests(|_| {});
+        let width = adapter.simd_width().unwrap();
+        assert!(width.is_power_of_two() && width >= 4);
+        if !adapter.shared.private_caps.supports_simd_group_functions {
+            return;
+        }
+
+        let source = "
+            #include <metal_stdlib>
+            using namespace metal;
+
+            kernel void cs_main(
+                device uint *sums [[buffer(0)]],
+                uint index [[thread_position_in_grid]],
+                uint lane [[thread_index_in_simdgroup]]
+            ) {
+                sums[index] = simd_sum(lane);
+            }
+        ";
+        let options = mtl::CompileOptions::new();
+        options.set_language_version(mtl::MTLLanguageVersion::V2_1);
+        let device = adapter.shared.device.lock();
+        let library = device.new_library_with_source(source, &options).unwrap();
+        let function = library.get_function("cs_main", None).unwrap();
+        let pipeline = device
+            .new_compute_pipeline_state_with_function(&function)
+            .unwrap();
+        // Pipelines never get a wider SIMD-group than the reported one.
+        assert!(pipeline.thread_execution_width() as u32 <= width);
+    }
 }
@@ -1,8 +1,121 @@
 use super::{conv, AsNative};
-use std::{mem, ops::Range};
+use objc::{class, msg_send, sel, sel_impl};
+use std::{mem, ops::Range, ptr, sync::atomic};
 
 const WORD_SIZE: usize = 4;
 
+/// Offset of an occlusion query result within the visibility result buffer.
+///
+/// Every query of a set gets its own 8-byte slot, so that multiple queries
+/// issued within the same render pass don't overwrite each other.
+fn visibility_result_offset(index: u32) -> wgt::BufferAddress {
+    index as wgt::BufferAddress * crate::QUERY_SIZE
+}
+
+/// Location of an occlusion query result within the bytes `range` of a
+/// user-provided visibility result buffer, if it fits there.
+fn visibility_result_location(
+    range: &Range<wgt::BufferAddress>,
+    index: u32,
+) -> Option<wgt::BufferAddress> {
+    let location = range.start + visibility_result_offset(index);
+    if location + crate::QUERY_SIZE <= range.end {
+        Some(location)
+    } else {
+        None
+    }
+}
+
+/// Blit regions (source offset, destination offset, size) resolving
+/// the results of the query `range` into a buffer at `offset` with `stride`.
+fn query_result_copies(
+    range: Range<u32>,
+    offset: wgt::BufferAddress,
+    stride: wgt::BufferSize,
+) -> impl Iterator<Item = (wgt::BufferAddress, wgt::BufferAddress, wgt::BufferAddress)> {
+    let contiguous = stride.get() == crate::QUERY_SIZE;
+    let (regions, size) = if contiguous {
+        (
+            range.start..range.start + 1,
+            (range.end - range.start) as u64 * crate::QUERY_SIZE,
+        )
+    } else {
+        (range.clone(), crate::QUERY_SIZE)
+    };
+    regions.map(move |index| {
+        let dst = offset + (index - range.start) as wgt::BufferAddress * stride.get();
+        (visibility_result_offset(index), dst, size)
+    })
+}
+
+/// Indirect arguments are fetched by the GPU, so they have to live in shared or
+/// private storage: CPU writes to managed buffers aren't flushed without
+/// `didModifyRange`, and memoryless ones have no backing at all.
+fn check_indirect_buffer(buffer: &super::Buffer) {
+    debug_assert!(
+        match buffer.raw.storage_mode() {
+            mtl::MTLStorageMode::Shared | mtl::MTLStorageMode::Private => true,
+            _ => false,
+        },
+        "Indirect buffer has unsupported storage mode {:?}",
+        buffer.raw.storage_mode()
+    );
+}
+
+/// Describes why `target` can't be resolved into `resolve`, if it can't.
+///
+/// Metal requires a multisampled target resolved into a single-sampled texture
+/// of the same format and size, and fails the whole pass otherwise.
+fn resolve_mismatch(target: &mtl::TextureRef, resolve: &mtl::TextureRef) -> Option<String> {
+    if target.sample_count() == 1 {
+        Some("the target is not multisampled".to_string())
+    } else if resolve.sample_count() != 1 {
+        Some(format!(
+            "the resolve target has {} samples",
+            resolve.sample_count()
+        ))
+    } else if target.pixel_format() != resolve.pixel_format() {
+        Some(format!(
+            "the resolve target format {:?} differs from {:?}",
+            resolve.pixel_format(),
+            target.pixel_format()
+        ))
+    } else if (target.width(), target.height()) != (resolve.width(), resolve.height()) {
+        // Views are as large as the first level they cover.
+        Some(format!(
+            "the resolve target size {}x{} differs from {}x{}",
+            resolve.width(),
+            resolve.height(),
+            target.width(),
+            target.height()
+        ))
+    } else {
+        None
+    }
+}
+
+/// Layers of a render target cleared by each clear pass.
+///
+/// A single layered pass clears all of them where layered rendering works.
+/// Intel GPUs don't clear all the layers of such a pass properly, so the
+/// clear is decomposed into a pass per layer there.
+fn clear_pass_layers(layers: Range<u32>, shared: &super::AdapterShared) -> Vec<Range<u32>> {
+    if layers.is_empty() {
+        Vec::new()
+    } else if shared.private_caps.layered_rendering
+        && !shared.disabilities.broken_layered_clear_image
+    {
+        vec![layers]
+    } else {
+        layers.map(|layer| layer..layer + 1).collect()
+    }
+}
+
+// `MTLDrawPrimitivesIndirectArguments` and `MTLDrawIndexedPrimitivesIndirectArguments`
+// always include `baseInstance`, so the argument structs have to match them exactly.
+const _: [(); 16] = [(); mem::size_of::<wgt::DrawIndirectArgs>()];
+const _: [(); 20] = [(); mem::size_of::<wgt::DrawIndexedIndirectArgs>()];
+
 impl Default for super::CommandState {
     fn default() -> Self {
         Self {
@@ -10,11 +123,16 @@ impl Default for super::CommandState {
             render: None,
             compute: None,
             raw_primitive_type: mtl::MTLPrimitiveType::Point,
+            sample_count: 1,
             index: None,
             raw_wg_size: mtl::MTLSize::new(0, 0, 0),
             stage_infos: Default::default(),
             storage_buffer_length_map: Default::default(),
             work_group_memory_sizes: Vec::new(),
+            pending_untracked_barrier: false,
+            heap_fences: Vec::new(),
+            pending_visibility_result: None,
+            visibility_result_range: None,
         }
     }
 }
@@ -24,17 +142,50 @@ impl super::CommandEncoder {
         if self.state.blit.is_none() {
             debug_assert!(self.state.render.is_none() && self.state.compute.is_none());
             let cmd_buf = self.raw_cmd_buf.as_ref().unwrap();
-            self.state.blit = Some(cmd_buf.new_blit_command_encoder().to_owned());
+            let encoder = cmd_buf.new_blit_command_encoder().to_owned();
+            if let Some(fence) = self.take_untracked_wait() {
+                encoder.wait_for_fence(fence);
+            }
+            self.state.blit = Some(encoder);
         }
         self.state.blit.as_ref().unwrap()
     }
 
     pub(super) fn leave_blit(&mut self) {
         if let Some(encoder) = self.state.blit.take() {
+            if let Some(fence) = self.untracked_fence() {
+                encoder.update_fence(fence);
+            }
+            for fence in self.state.heap_fences.drain(..) {
+                encoder.update_fence(&fence);
+            }
             encoder.end_encoding();
         }
     }
 
+    /// Returns the fence to update at the end of an encoder, if any untracked
+    /// buffers exist. Encoders ended before that can't have used them.
+    fn untracked_fence(&mut self) -> Option<&mtl::FenceRef> {
+        if self.untracked_fence.is_none()
+            && self
+                .shared
+                .untracked_buffers
+                .load(atomic::Ordering::Relaxed)
+        {
+            self.untracked_fence = Some(self.shared.device.lock().new_fence());
+        }
+        self.untracked_fence.as_deref()
+    }
+
+    /// Returns the fence a new encoder has to wait for, if a barrier on
+    /// untracked buffers was recorded since the last one.
+    fn take_untracked_wait(&mut self) -> Option<&mtl::FenceRef> {
+        if !mem::replace(&mut self.state.pending_untracked_barrier, false) {
+            return None;
+        }
+        self.untracked_fence.as_deref()
+    }
+
     fn enter_any(&mut self) -> &mtl::CommandEncoderRef {
         if let Some(ref encoder) = self.state.render {
             encoder
@@ -45,7 +196,262 @@ impl super::CommandEncoder {
         }
     }
 
+    /// Makes the current encoder access the resources placed in `heap` after
+    /// all the previous encoders using `heap` are done, and the following ones
+    /// wait for it in turn.
+    ///
+    /// Outside of passes, this applies to the copies encoded until the next pass.
+    /// Within passes, the heap is also made resident, so its resources can be
+    /// accessed without being bound.
+    ///
+    /// # Safety
+    ///
+    /// - Has to be called before the resources placed in `heap` are accessed
+    ///   by the current pass, or by the copies outside of passes.
+    /// - `heap` has to be kept alive until the command buffer is done executing.
+    pub unsafe fn use_heap(&mut self, heap: &super::Heap) {
+        if self
+            .state
+            .heap_fences
+            .iter()
+            .any(|fence| ptr::eq::<mtl::FenceRef>(&**fence, &*heap.fence))
+        {
+            return;
+        }
+        if let Some(ref encoder) = self.state.render {
+            encoder.use_heap(&heap.raw);
+            encoder.wait_for_fence(&heap.fence, mtl::MTLRenderStages::Vertex);
+        } else if let Some(ref encoder) = self.state.compute {
+            encoder.use_heap(&heap.raw);
+            encoder.wait_for_fence(&heap.fence);
+        } else {
+            self.enter_blit().wait_for_fence(&heap.fence);
+        }
+        self.state.heap_fences.push(heap.fence.clone());
+    }
+
+    /// Clears the `layers` of the `levels` of a color render target with
+    /// render passes, which is cheaper than copying from the zero buffer.
+    fn clear_render_target(
+        &mut self,
+        texture: &super::Texture,
+        levels: Range<u32>,
+        layers: Range<u32>,
+    ) {
+        // The clear passes access the texture like the copies they replace,
+        // so they go after the heap fences waited for by the current copies.
+        let heap_fences = self.state.heap_fences.clone();
+        self.leave_blit();
+
+        let is_3d = texture.raw_type == mtl::MTLTextureType::D3;
+        for level in levels {
+            let slices = if is_3d {
+                0..texture.copy_size.at_mip_level(level).depth
+            } else {
+                layers.clone()
+            };
+            for pass_slices in clear_pass_layers(slices, &self.shared) {
+                let descriptor = mtl::RenderPassDescriptor::new();
+                let at_descriptor = descriptor.color_attachments().object_at(0).unwrap();
+                let count = pass_slices.end - pass_slices.start;
+                if is_3d || count == 1 {
+                    at_descriptor.set_texture(Some(&texture.raw));
+                    at_descriptor.set_level(level as u64);
+                    if is_3d {
+                        at_descriptor.set_depth_plane(pass_slices.start as u64);
+                    } else {
+                        at_descriptor.set_slice(pass_slices.start as u64);
+                    }
+                } else {
+                    let view = texture.raw.new_texture_view_from_slice(
+                        texture.raw_format,
+                        mtl::MTLTextureType::D2Array,
+                        mtl::NSRange {
+                            location: level as _,
+                            length: 1,
+                        },
+                        mtl::NSRange {
+                            location: pass_slices.start as _,
+                            length: count as _,
+                        },
+                    );
+                    at_descriptor.set_texture(Some(&view));
+                }
+                if count > 1 {
+                    descriptor.set_render_target_array_length(count as u64);
+                }
+                at_descriptor.set_clear_color(conv::map_clear_color(&wgt::Color::TRANSPARENT));
+                at_descriptor.set_load_action(mtl::MTLLoadAction::Clear);
+                at_descriptor.set_store_action(mtl::MTLStoreAction::Store);
+
+                let raw = self.raw_cmd_buf.as_ref().unwrap();
+                let encoder = raw.new_render_command_encoder(descriptor).to_owned();
+                if let Some(fence) = self.take_untracked_wait() {
+                    encoder.wait_for_fence(fence, mtl::MTLRenderStages::Vertex);
+                }
+                for fence in heap_fences.iter() {
+                    encoder.wait_for_fence(fence, mtl::MTLRenderStages::Vertex);
+                    encoder.update_fence(fence, mtl::MTLRenderStages::Fragment);
+                }
+                if let Some(fence) = self.untracked_fence() {
+                    encoder.update_fence(fence, mtl::MTLRenderStages::Fragment);
+                }
+                encoder.end_encoding();
+            }
+        }
+
+        // Copies encoded after the clear still have to wait for the heaps.
+        if !heap_fences.is_empty() {
+            let encoder = self.enter_blit();
+            for fence in heap_fences.iter() {
+                encoder.wait_for_fence(fence);
+            }
+            self.state.heap_fences = heap_fences;
+        }
+    }
+
+    /// Reserves the place of the command buffer being encoded in the queue.
+    ///
+    /// Command buffers are recorded independently, with the queue only locked
+    /// while they are created, so encoders can record on separate threads.
+    /// They execute in the order they are submitted in, unless enqueued: an
+    /// enqueued command buffer executes after all the ones enqueued or submitted
+    /// before this call, and before all the ones after, whenever it's submitted.
+    ///
+    /// # Safety
+    ///
+    /// - Has to be called between `begin_encoding` and `end_encoding`.
+    /// - The command buffer has to be submitted, or the ones enqueued after it
+    ///   never execute.
+    /// - If the submission signals a fence, its last command buffer has to be
+    ///   the last one to execute.
+    pub unsafe fn enqueue(&mut self) {
+        self.raw_cmd_buf.as_ref().unwrap().enqueue();
+    }
+
+    fn map_viewport(&self, rect: &crate::Rect<f32>, depth_range: Range<f32>) -> mtl::MTLViewport {
+        let zfar = if self.shared.disabilities.broken_viewport_near_depth {
+            depth_range.end - depth_range.start
+        } else {
+            depth_range.end
+        };
+        mtl::MTLViewport {
+            originX: rect.x as _,
+            originY: rect.y as _,
+            width: rect.w as _,
+            height: rect.h as _,
+            znear: depth_range.start as _,
+            zfar: zfar as _,
+        }
+    }
+
+    /// Sets the viewports of the current render pass, indexed by the viewport
+    /// index of the primitives.
+    ///
+    /// Setting more than `Adapter::max_viewports` of them is an error, and
+    /// leaves the viewports unchanged.
+    ///
+    /// # Safety
+    ///
+    /// - Has to be called within a render pass.
+    pub unsafe fn set_viewports(&mut self, viewports: &[(crate::Rect<f32>, Range<f32>)]) {
+        let max_viewports = self.shared.private_caps.max_viewports;
+        if viewports.len() > max_viewports as usize {
+            log::error!(
+                "{} viewports were set, but only {} are supported",
+                viewports.len(),
+                max_viewports
+            );
+            return;
+        }
+        let raw_viewports = viewports
+            .iter()
+            .map(|(rect, depth_range)| self.map_viewport(rect, depth_range.clone()))
+            .collect::<Vec<_>>();
+        let encoder = self.state.render.as_ref().unwrap();
+        let () = msg_send![
+            encoder.as_ref(),
+            setViewports: raw_viewports.as_ptr()
+            count: raw_viewports.len() as mtl::NSUInteger
+        ];
+    }
+
+    /// Makes the work encoded after this point wait until `event` reaches `value`.
+    ///
+    /// # Safety
+    ///
+    /// - Has to be called outside of passes.
+    /// - `event` has to be created or imported by the same device.
+    pub unsafe fn wait_for_shared_event(&mut self, event: &mtl::SharedEventRef, value: u64) {
+        debug_assert!(self.state.render.is_none() && self.state.compute.is_none());
+        self.leave_blit();
+        self.raw_cmd_buf
+            .as_ref()
+            .unwrap()
+            .encode_wait_for_event(event, value);
+    }
+
+    /// Sets `event` to `value` once the work encoded before this point is done.
+    ///
+    /// # Safety
+    ///
+    /// - Has to be called outside of passes.
+    /// - `event` has to be created or imported by the same device.
+    pub unsafe fn signal_shared_event(&mut self, event: &mtl::SharedEventRef, value: u64) {
+        debug_assert!(self.state.render.is_none() && self.state.compute.is_none());
+        self.leave_blit();
+        self.raw_cmd_buf
+            .as_ref()
+            .unwrap()
+            .encode_signal_event(event, value);
+    }
+
+    /// Makes the occlusion queries of the next render pass write their results
+    /// into `buffer`, starting at `offset`, instead of the buffer of the pass'
+    /// `occlusion_query_set`.
+    ///
+    /// This lets multiple passes accumulate their results into a single buffer.
+    /// Query `i` is written at `offset + i * QUERY_SIZE`, and queries that
+    /// don't fit in the buffer are skipped. Resetting and copying the results
+    /// is up to the user, as the query set isn't involved.
+    ///
+    /// # Safety
+    ///
+    /// - Has to be called outside of passes.
+    /// - `buffer` has to be kept alive until the command buffer is done executing.
+    pub unsafe fn set_visibility_result_buffer(
+        &mut self,
+        buffer: &super::Buffer,
+        offset: wgt::BufferAddress,
+    ) {
+        debug_assert!(self.state.render.is_none() && self.state.compute.is_none());
+        if offset % crate::QUERY_SIZE != 0 || offset >= buffer.size {
+            log::error!(
+                "Visibility result offset {} is unaligned or out of bounds for a buffer of {} bytes",
+                offset,
+                buffer.size
+            );
+            return;
+        }
+        self.state.pending_visibility_result = Some((buffer.raw.clone(), offset));
+    }
+
     fn begin_pass(&mut self) {
+        // Metal only allows a single encoder to be active on a command buffer,
+        // and aborts if a new one is created before the previous one ends.
+        // Release builds end the leftover encoder to keep the command buffer usable.
+        debug_assert!(
+            self.state.render.is_none() && self.state.compute.is_none(),
+            "Previous pass was not ended before beginning a new pass"
+        );
+        if let Some(encoder) = self.state.render.take() {
+            log::error!("Render pass was not ended before beginning a new pass");
+            encoder.end_encoding();
+        }
+        if let Some(encoder) = self.state.compute.take() {
+            log::error!("Compute pass was not ended before beginning a new pass");
+            encoder.end_encoding();
+        }
         self.state.reset();
         self.leave_blit();
     }
@@ -81,8 +487,21 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
     unsafe fn begin_encoding(&mut self, label: crate::Label) -> Result<(), crate::DeviceError> {
         let queue = &self.raw_queue.lock();
         let retain_references = self.shared.settings.retain_command_buffer_references;
+        let gpu_validation = self.shared.settings.gpu_validation;
         let raw = objc::rc::autoreleasepool(move || {
-            let cmd_buf_ref = if retain_references {
+            let cmd_buf_ref = if gpu_validation {
+                // MTLCommandBufferErrorOptionEncoderExecutionStatus
+                const ENCODER_EXECUTION_STATUS: mtl::NSUInteger = 1;
+                let queue_ref: &mtl::CommandQueueRef = queue;
+                let descriptor: *mut objc::runtime::Object =
+                    msg_send![class!(MTLCommandBufferDescriptor), new];
+                let () = msg_send![descriptor, setRetainedReferences: retain_references];
+                let () = msg_send![descriptor, setErrorOptions: ENCODER_EXECUTION_STATUS];
+                let cmd_buf_ref: &mtl::CommandBufferRef =
+                    msg_send![queue_ref, commandBufferWithDescriptor: descriptor];
+                let () = msg_send![descriptor, release];
+                cmd_buf_ref
+            } else if retain_references {
                 queue.new_command_buffer()
             } else {
                 queue.new_command_buffer_with_unretained_references()
@@ -90,7 +509,7 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
             cmd_buf_ref.to_owned()
         });
 
-        if let Some(label) = label {
+        if let Some(label) = label.filter(|_| self.shared.private_caps.supports_debug_markers) {
             raw.set_label(label);
         }
         self.raw_cmd_buf = Some(raw);
@@ -114,16 +533,43 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
         //do nothing
     }
 
-    unsafe fn transition_buffers<'a, T>(&mut self, _barriers: T)
+    unsafe fn transition_buffers<'a, T>(&mut self, mut barriers: T)
     where
         T: Iterator<Item = crate::BufferBarrier<'a, super::Api>>,
     {
+        // Metal tracks hazards of all other buffers by itself.
+        if !barriers.any(|bar| {
+            bar.buffer
+                .options
+                .contains(mtl::MTLResourceOptions::HazardTrackingModeUntracked)
+        }) {
+            return;
+        }
+        if let Some(ref encoder) = self.state.compute {
+            const MTL_BARRIER_SCOPE_BUFFERS: u64 = 1 << 0;
+            let () = msg_send![encoder.as_ref(), memoryBarrierWithScope: MTL_BARRIER_SCOPE_BUFFERS];
+        } else {
+            self.leave_blit();
+            self.state.pending_untracked_barrier = true;
+        }
     }
 
-    unsafe fn transition_textures<'a, T>(&mut self, _barriers: T)
+    unsafe fn transition_textures<'a, T>(&mut self, mut barriers: T)
     where
         T: Iterator<Item = crate::TextureBarrier<'a, super::Api>>,
     {
+        // Metal tracks hazards of our resources across encoders and command buffers
+        // of a queue, so only storage writes within the current compute encoder
+        // (e.g. between dispatches) need an explicit barrier.
+        let encoder = match self.state.compute {
+            Some(ref encoder) if self.shared.private_caps.supports_memory_barriers => encoder,
+            _ => return,
+        };
+        if barriers.any(|bar| bar.usage.start.contains(crate::TextureUses::STORAGE_WRITE)) {
+            const MTL_BARRIER_SCOPE_TEXTURES: u64 = 1 << 1;
+            let () =
+                msg_send![encoder.as_ref(), memoryBarrierWithScope: MTL_BARRIER_SCOPE_TEXTURES];
+        }
     }
 
     unsafe fn clear_buffer(&mut self, buffer: &super::Buffer, range: crate::MemoryRange) {
@@ -136,11 +582,6 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
         texture: &super::Texture,
         subresource_range: &wgt::ImageSubresourceRange,
     ) {
-        let shared = self.shared.clone();
-        let encoder = self.enter_blit();
-
-        let format_desc = texture.format.describe();
-
         let mip_range = subresource_range.base_mip_level..match subresource_range.mip_level_count {
             Some(c) => subresource_range.base_mip_level + c.get(),
             None => texture.mip_levels,
@@ -151,6 +592,21 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
                 None => texture.array_layers,
             };
 
+        if texture
+            .raw
+            .usage()
+            .contains(mtl::MTLTextureUsage::RenderTarget)
+            && crate::FormatAspects::from(texture.format) == crate::FormatAspects::COLOR
+        {
+            self.clear_render_target(texture, mip_range, array_range);
+            return;
+        }
+
+        let shared = self.shared.clone();
+        let encoder = self.enter_blit();
+
+        let format_desc = texture.format.describe();
+
         for mip_level in mip_range {
             // Note that Metal requires this only to be a multiple of the pixel size, not some other constant like in other APIs.
             let mip_size = texture.copy_size.at_mip_level(mip_level);
@@ -233,6 +689,7 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
             let src_origin = conv::map_origin(&copy.src_base.origin);
             let dst_origin = conv::map_origin(&copy.dst_base.origin);
             // no clamping is done: Metal expects physical sizes here
+            // no scaling either: the same extent is used on both sides
             let extent = conv::map_copy_extent(&copy.size);
             encoder.copy_from_texture(
                 &src.raw,
@@ -330,14 +787,24 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
     unsafe fn begin_query(&mut self, set: &super::QuerySet, index: u32) {
         match set.ty {
             wgt::QueryType::Occlusion => {
+                let offset = match self.state.visibility_result_range {
+                    Some(ref range) => match visibility_result_location(range, index) {
+                        Some(location) => location,
+                        None => {
+                            log::error!(
+                                "Occlusion query {} doesn't fit in the visibility result buffer",
+                                index
+                            );
+                            return;
+                        }
+                    },
+                    None => visibility_result_offset(index),
+                };
                 self.state
                     .render
                     .as_ref()
                     .unwrap()
-                    .set_visibility_result_mode(
-                        mtl::MTLVisibilityResultMode::Boolean,
-                        index as u64 * crate::QUERY_SIZE,
-                    );
+                    .set_visibility_result_mode(set.visibility_mode, offset);
             }
             _ => {}
         }
@@ -354,11 +821,21 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
             _ => {}
         }
     }
-    unsafe fn write_timestamp(&mut self, _set: &super::QuerySet, _index: u32) {}
+    unsafe fn write_timestamp(&mut self, set: &super::QuerySet, index: u32) {
+        if let Some(ref counter_sample_buffer) = set.counter_sample_buffer {
+            let encoder = self.enter_any();
+            let () = msg_send![
+                encoder,
+                sampleCountersInBuffer: counter_sample_buffer.as_ptr()
+                atSampleIndex: index as mtl::NSUInteger
+                withBarrier: objc::runtime::YES
+            ];
+        }
+    }
     unsafe fn reset_queries(&mut self, set: &super::QuerySet, range: Range<u32>) {
         let encoder = self.enter_blit();
         let raw_range = mtl::NSRange {
-            location: range.start as u64 * crate::QUERY_SIZE,
+            location: visibility_result_offset(range.start),
             length: (range.end - range.start) as u64 * crate::QUERY_SIZE,
         };
         encoder.fill_buffer(&set.raw_buffer, raw_range, 0);
@@ -369,17 +846,34 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
         range: Range<u32>,
         buffer: &super::Buffer,
         offset: wgt::BufferAddress,
-        _: wgt::BufferSize, // Metal doesn't support queries that are bigger than a single element are not supported
+        stride: wgt::BufferSize, // Metal doesn't support queries that are bigger than a single element are not supported
     ) {
+        if let Some(ref counter_sample_buffer) = set.counter_sample_buffer {
+            let encoder = self.enter_blit();
+            let raw_range = mtl::NSRange {
+                location: range.start as u64,
+                length: (range.end - range.start) as u64,
+            };
+            let () = msg_send![
+                encoder,
+                resolveCounters: counter_sample_buffer.as_ptr()
+                inRange: raw_range
+                destinationBuffer: set.raw_buffer.as_ref()
+                destinationOffset: visibility_result_offset(range.start)
+            ];
+            // Make sure the resolved counters are written before copying them.
+            self.leave_blit();
+        }
         let encoder = self.enter_blit();
-        let size = (range.end - range.start) as u64 * crate::QUERY_SIZE;
-        encoder.copy_from_buffer(
-            &set.raw_buffer,
-            range.start as u64 * crate::QUERY_SIZE,
-            &buffer.raw,
-            offset,
-            size,
-        );
+        for (src_offset, dst_offset, size) in query_result_copies(range, offset, stride) {
+            encoder.copy_from_buffer(&set.raw_buffer, src_offset, &buffer.raw, dst_offset, size);
+        }
+        if buffer
+            .options
+            .contains(mtl::MTLResourceOptions::StorageModeManaged)
+        {
+            encoder.synchronize_resource(&buffer.raw);
+        }
     }
 
     // render
@@ -387,26 +881,42 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
     unsafe fn begin_render_pass(&mut self, desc: &crate::RenderPassDescriptor<super::Api>) {
         self.begin_pass();
         self.state.index = None;
+        self.state.sample_count = desc.sample_count;
 
+        // Custom sample positions are never set, so the pass and all of its
+        // pipelines rasterize with the default positions for the sample count.
         let descriptor = mtl::RenderPassDescriptor::new();
-        //TODO: set visibility results buffer
+        if let Some((buffer, offset)) = self.state.pending_visibility_result.take() {
+            descriptor.set_visibility_result_buffer(Some(&buffer));
+            self.state.visibility_result_range = Some(offset..buffer.length());
+        } else if let Some(set) = desc.occlusion_query_set {
+            descriptor.set_visibility_result_buffer(Some(&set.raw_buffer));
+        }
 
         for (i, at) in desc.color_attachments.iter().enumerate() {
             let at_descriptor = descriptor.color_attachments().object_at(i as u64).unwrap();
             at_descriptor.set_texture(Some(&at.target.view.raw));
-            if let Some(ref resolve) = at.resolve_target {
+            let resolve_target =
+                at.resolve_target.as_ref().filter(|resolve| {
+                    match resolve_mismatch(&at.target.view.raw, &resolve.view.raw) {
+                        Some(mismatch) => {
+                            log::error!("Color attachment {} can't be resolved: {}", i, mismatch);
+                            false
+                        }
+                        None => true,
+                    }
+                });
+            if let Some(resolve) = resolve_target {
                 //Note: the selection of levels and slices is already handled by `TextureView`
                 at_descriptor.set_resolve_texture(Some(&resolve.view.raw));
             }
-            let load_action = if at.ops.contains(crate::AttachmentOps::LOAD) {
-                mtl::MTLLoadAction::Load
-            } else {
+            let load_action = conv::map_load_action(at.ops);
+            if let mtl::MTLLoadAction::Clear = load_action {
                 at_descriptor.set_clear_color(conv::map_clear_color(&at.clear_value));
-                mtl::MTLLoadAction::Clear
-            };
+            }
             let store_action = conv::map_store_action(
                 at.ops.contains(crate::AttachmentOps::STORE),
-                at.resolve_target.is_some(),
+                resolve_target.is_some(),
             );
             at_descriptor.set_load_action(load_action);
             at_descriptor.set_store_action(store_action);
@@ -417,12 +927,10 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
                 let at_descriptor = descriptor.depth_attachment().unwrap();
                 at_descriptor.set_texture(Some(&at.target.view.raw));
 
-                let load_action = if at.depth_ops.contains(crate::AttachmentOps::LOAD) {
-                    mtl::MTLLoadAction::Load
-                } else {
+                let load_action = conv::map_load_action(at.depth_ops);
+                if let mtl::MTLLoadAction::Clear = load_action {
                     at_descriptor.set_clear_depth(at.clear_value.0 as f64);
-                    mtl::MTLLoadAction::Clear
-                };
+                }
                 let store_action = if at.depth_ops.contains(crate::AttachmentOps::STORE) {
                     mtl::MTLStoreAction::Store
                 } else {
@@ -430,6 +938,15 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
                 };
                 at_descriptor.set_load_action(load_action);
                 at_descriptor.set_store_action(store_action);
+            } else if at.target.view.raw.pixel_format()
+                == mtl::MTLPixelFormat::Depth32Float_Stencil8
+            {
+                // Stencil-only textures backed by a combined format need their
+                // unused depth attached too.
+                let at_descriptor = descriptor.depth_attachment().unwrap();
+                at_descriptor.set_texture(Some(&at.target.view.raw));
+                at_descriptor.set_load_action(mtl::MTLLoadAction::DontCare);
+                at_descriptor.set_store_action(mtl::MTLStoreAction::DontCare);
             }
             if at
                 .target
@@ -440,13 +957,11 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
                 let at_descriptor = descriptor.stencil_attachment().unwrap();
                 at_descriptor.set_texture(Some(&at.target.view.raw));
 
-                let load_action = if at.depth_ops.contains(crate::AttachmentOps::LOAD) {
-                    mtl::MTLLoadAction::Load
-                } else {
+                let load_action = conv::map_load_action(at.stencil_ops);
+                if let mtl::MTLLoadAction::Clear = load_action {
                     at_descriptor.set_clear_stencil(at.clear_value.1);
-                    mtl::MTLLoadAction::Clear
-                };
-                let store_action = if at.depth_ops.contains(crate::AttachmentOps::STORE) {
+                }
+                let store_action = if at.stencil_ops.contains(crate::AttachmentOps::STORE) {
                     mtl::MTLStoreAction::Store
                 } else {
                     mtl::MTLStoreAction::DontCare
@@ -456,16 +971,43 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
             }
         }
 
+        if desc.color_attachments.is_empty() && desc.depth_stencil_attachment.is_none() {
+            // There is nothing to infer the render target size from.
+            if desc.extent.width == 0 || desc.extent.height == 0 {
+                log::error!(
+                    "Render pass without attachments has an empty extent {:?}",
+                    desc.extent
+                );
+            }
+            descriptor.set_render_target_width(desc.extent.width as u64);
+            descriptor.set_render_target_height(desc.extent.height as u64);
+            descriptor.set_default_raster_sample_count(desc.sample_count as u64);
+        }
+
         let raw = self.raw_cmd_buf.as_ref().unwrap();
-        let encoder = raw.new_render_command_encoder(descriptor);
-        if let Some(label) = desc.label {
+        let encoder = raw.new_render_command_encoder(descriptor).to_owned();
+        if let Some(label) = desc
+            .label
+            .filter(|_| self.shared.private_caps.supports_debug_markers)
+        {
             encoder.set_label(label);
         }
-        self.state.render = Some(encoder.to_owned());
+        if let Some(fence) = self.take_untracked_wait() {
+            encoder.wait_for_fence(fence, mtl::MTLRenderStages::Vertex);
+        }
+        self.state.render = Some(encoder);
     }
 
     unsafe fn end_render_pass(&mut self) {
-        self.state.render.take().unwrap().end_encoding();
+        let encoder = self.state.render.take().unwrap();
+        self.state.visibility_result_range = None;
+        if let Some(fence) = self.untracked_fence() {
+            encoder.update_fence(fence, mtl::MTLRenderStages::Fragment);
+        }
+        for fence in self.state.heap_fences.drain(..) {
+            encoder.update_fence(&fence, mtl::MTLRenderStages::Fragment);
+        }
+        encoder.end_encoding();
     }
 
     unsafe fn set_bind_group(
@@ -646,16 +1188,29 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
     }
 
     unsafe fn insert_debug_marker(&mut self, label: &str) {
-        self.enter_any().insert_debug_signpost(label);
+        if self.shared.private_caps.supports_debug_markers {
+            self.enter_any().insert_debug_signpost(label);
+        }
     }
     unsafe fn begin_debug_marker(&mut self, group_label: &str) {
-        self.enter_any().push_debug_group(group_label);
+        if self.shared.private_caps.supports_debug_markers {
+            self.enter_any().push_debug_group(group_label);
+        }
     }
     unsafe fn end_debug_marker(&mut self) {
-        self.enter_any().pop_debug_group();
+        if self.shared.private_caps.supports_debug_markers {
+            self.enter_any().pop_debug_group();
+        }
     }
 
     unsafe fn set_render_pipeline(&mut self, pipeline: &super::RenderPipeline) {
+        if pipeline.sample_count != self.state.sample_count {
+            log::error!(
+                "Render pipeline with {} samples is set in a pass with {} samples",
+                pipeline.sample_count,
+                self.state.sample_count
+            );
+        }
         self.state.raw_primitive_type = pipeline.raw_primitive_type;
         self.state.stage_infos.vs.assign_from(&pipeline.vs_info);
         self.state.stage_infos.fs.assign_from(&pipeline.fs_info);
@@ -704,10 +1259,7 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
         binding: crate::BufferBinding<'a, super::Api>,
         format: wgt::IndexFormat,
     ) {
-        let (stride, raw_type) = match format {
-            wgt::IndexFormat::Uint16 => (2, mtl::MTLIndexType::UInt16),
-            wgt::IndexFormat::Uint32 => (4, mtl::MTLIndexType::UInt32),
-        };
+        let (stride, raw_type) = conv::map_index_format(format);
         self.state.index = Some(super::IndexState {
             buffer_ptr: AsNative::from(binding.buffer.raw.as_ref()),
             offset: binding.offset,
@@ -721,26 +1273,16 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
         index: u32,
         binding: crate::BufferBinding<'a, super::Api>,
     ) {
+        debug_assert_eq!(binding.offset % wgt::VERTEX_BUFFER_OFFSET_ALIGNMENT, 0);
         let buffer_index = self.shared.private_caps.max_buffers_per_stage as u64 - 1 - index as u64;
         let encoder = self.state.render.as_ref().unwrap();
         encoder.set_vertex_buffer(buffer_index, Some(&binding.buffer.raw), binding.offset);
     }
 
     unsafe fn set_viewport(&mut self, rect: &crate::Rect<f32>, depth_range: Range<f32>) {
-        let zfar = if self.shared.disabilities.broken_viewport_near_depth {
-            depth_range.end - depth_range.start
-        } else {
-            depth_range.end
-        };
+        let viewport = self.map_viewport(rect, depth_range);
         let encoder = self.state.render.as_ref().unwrap();
-        encoder.set_viewport(mtl::MTLViewport {
-            originX: rect.x as _,
-            originY: rect.y as _,
-            width: rect.w as _,
-            height: rect.h as _,
-            znear: depth_range.start as _,
-            zfar: zfar as _,
-        });
+        encoder.set_viewport(viewport);
     }
     unsafe fn set_scissor_rect(&mut self, rect: &crate::Rect<u32>) {
         //TODO: support empty scissors by modifying the viewport
@@ -842,6 +1384,7 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
         mut offset: wgt::BufferAddress,
         draw_count: u32,
     ) {
+        check_indirect_buffer(buffer);
         let encoder = self.state.render.as_ref().unwrap();
         for _ in 0..draw_count {
             encoder.draw_primitives_indirect(self.state.raw_primitive_type, &buffer.raw, offset);
@@ -855,8 +1398,12 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
         mut offset: wgt::BufferAddress,
         draw_count: u32,
     ) {
+        check_indirect_buffer(buffer);
         let encoder = self.state.render.as_ref().unwrap();
+        // The index type isn't part of the arguments, so it has to come from
+        // the bound index buffer, whose offset has to be aligned to it.
         let index = self.state.index.as_ref().unwrap();
+        debug_assert_eq!(index.offset % index.stride, 0);
         for _ in 0..draw_count {
             encoder.draw_indexed_primitives_indirect(
                 self.state.raw_primitive_type,
@@ -897,14 +1444,27 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
         self.begin_pass();
 
         let raw = self.raw_cmd_buf.as_ref().unwrap();
-        let encoder = raw.new_compute_command_encoder();
-        if let Some(label) = desc.label {
+        let encoder = raw.new_compute_command_encoder().to_owned();
+        if let Some(label) = desc
+            .label
+            .filter(|_| self.shared.private_caps.supports_debug_markers)
+        {
             encoder.set_label(label);
         }
-        self.state.compute = Some(encoder.to_owned());
+        if let Some(fence) = self.take_untracked_wait() {
+            encoder.wait_for_fence(fence);
+        }
+        self.state.compute = Some(encoder);
     }
     unsafe fn end_compute_pass(&mut self) {
-        self.state.compute.take().unwrap().end_encoding();
+        let encoder = self.state.compute.take().unwrap();
+        if let Some(fence) = self.untracked_fence() {
+            encoder.update_fence(fence);
+        }
+        for fence in self.state.heap_fences.drain(..) {
+            encoder.update_fence(&fence);
+        }
+        encoder.end_encoding();
     }
 
     unsafe fn set_compute_pipeline(&mut self, pipeline: &super::ComputePipeline) {
@@ -956,7 +1516,239 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
     }
 
     unsafe fn dispatch_indirect(&mut self, buffer: &super::Buffer, offset: wgt::BufferAddress) {
+        check_indirect_buffer(buffer);
         let encoder = self.state.compute.as_ref().unwrap();
         encoder.dispatch_thread_groups_indirect(&buffer.raw, offset, self.state.raw_wg_size);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        clear_pass_layers, query_result_copies, resolve_mismatch, visibility_result_location,
+        visibility_result_offset,
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn multiple_queries_in_one_pass() {
+        let offsets = (0..4).map(visibility_result_offset).collect::<Vec<_>>();
+        assert_eq!(offsets, [0, 8, 16, 24]);
+
+        let stride = wgt::BufferSize::new(crate::QUERY_SIZE).unwrap();
+        let copies = query_result_copies(1..4, 64, stride).collect::<Vec<_>>();
+        assert_eq!(copies, [(8, 64, 24)]);
+
+        let stride = wgt::BufferSize::new(256).unwrap();
+        let copies = query_result_copies(1..4, 64, stride).collect::<Vec<_>>();
+        assert_eq!(copies, [(8, 64, 8), (16, 320, 8), (24, 576, 8)]);
+    }
+
+    #[test]
+    fn external_visibility_result_buffer() {
+        let range = 16..48;
+        let locations = (0..5)
+            .map(|index| visibility_result_location(&range, index))
+            .collect::<Vec<_>>();
+        assert_eq!(locations, [Some(16), Some(24), Some(32), Some(40), None]);
+    }
+
+    #[test]
+    fn indexed_indirect_index_types() {
+        use mtl::MTLIndexType as It;
+
+        assert_eq!(
+            super::conv::map_index_format(wgt::IndexFormat::Uint16),
+            (2, It::UInt16)
+        );
+        assert_eq!(
+            super::conv::map_index_format(wgt::IndexFormat::Uint32),
+            (4, It::UInt32)
+        );
+        // `indexCount`, `instanceCount`, `indexStart`, `baseVertex` and `baseInstance`
+        assert_eq!(std::mem::size_of::<wgt::DrawIndexedIndirectArgs>(), 5 * 4);
+    }
+
+    #[test]
+    fn attachment_load_actions() {
+        use crate::AttachmentOps as Ops;
+        use mtl::MTLLoadAction as Action;
+
+        let actions = [
+            Ops::STORE,
+            Ops::LOAD | Ops::STORE,
+            Ops::DONT_CARE | Ops::STORE,
+            Ops::LOAD | Ops::DONT_CARE,
+        ]
+        .iter()
+        .map(|&ops| super::conv::map_load_action(ops) as u64)
+        .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            [
+                Action::Clear as u64,
+                Action::Load as u64,
+                Action::DontCare as u64,
+                Action::Load as u64,
+            ]
+        );
+    }
+
+    #[test]
+    fn mismatched_resolve_targets() {
+        use mtl::MTLPixelFormat as Pf;
+
+        let device = super::super::AdapterShared::for_tests().device.into_inner();
+        let texture = |format, size, sample_count| {
+            let descriptor = mtl::TextureDescriptor::new();
+            descriptor.set_pixel_format(format);
+            descriptor.set_width(size);
+            descriptor.set_height(size);
+            descriptor.set_sample_count(sample_count);
+            descriptor.set_storage_mode(mtl::MTLStorageMode::Private);
+            descriptor.set_usage(mtl::MTLTextureUsage::RenderTarget);
+            if sample_count > 1 {
+                descriptor.set_texture_type(mtl::MTLTextureType::D2Multisample);
+            }
+            device.new_texture(&descriptor)
+        };
+
+        let target = texture(Pf::RGBA8Unorm, 16, 4);
+        assert_eq!(
+            resolve_mismatch(&target, &texture(Pf::RGBA8Unorm, 16, 1)),
+            None
+        );
+        assert!(resolve_mismatch(&target, &texture(Pf::BGRA8Unorm, 16, 1)).is_some());
+        assert!(resolve_mismatch(&target, &texture(Pf::RGBA8Unorm, 8, 1)).is_some());
+        assert!(resolve_mismatch(&target, &texture(Pf::RGBA8Unorm, 16, 4)).is_some());
+        let single = texture(Pf::RGBA8Unorm, 16, 1);
+        assert!(resolve_mismatch(&single, &texture(Pf::RGBA8Unorm, 16, 1)).is_some());
+    }
+
+    #[test]
+    fn per_layer_render_target_clear() {
+        use crate::{Adapter as _, CommandEncoder as _, Device as _};
+
+        const SIZE: u32 = 4;
+        const LAYERS: u32 = 4;
+        const LAYER_SIZE: u64 = (SIZE * SIZE * 4) as u64;
+
+        let mut shared = super::super::AdapterShared::for_tests();
+        shared.private_caps.layered_rendering = true;
+        shared.disabilities.broken_layered_clear_image = false;
+        assert_eq!(clear_pass_layers(1..3, &shared), [1..3]);
+        shared.disabilities.broken_layered_clear_image = true;
+        assert_eq!(clear_pass_layers(1..3, &shared), [1..2, 2..3]);
+
+        let adapter = super::super::Adapter::new(Arc::new(shared));
+        let open = unsafe { adapter.open(wgt::Features::empty()) }.unwrap();
+        let device = &open.device;
+        let buffer = |usage| unsafe {
+            device
+                .create_buffer(&crate::BufferDescriptor {
+                    label: None,
+                    size: LAYER_SIZE * LAYERS as u64,
+                    usage,
+                    memory_flags: crate::MemoryFlags::empty(),
+                })
+                .unwrap()
+        };
+        let upload = buffer(crate::BufferUses::MAP_WRITE | crate::BufferUses::COPY_SRC);
+        let readback = buffer(crate::BufferUses::MAP_READ | crate::BufferUses::COPY_DST);
+        unsafe {
+            let mapping = device
+                .map_buffer(&upload, 0..LAYER_SIZE * LAYERS as u64)
+                .unwrap();
+            std::ptr::write_bytes(
+                mapping.ptr.as_ptr(),
+                0xFF,
+                (LAYER_SIZE * LAYERS as u64) as _,
+            );
+            device.unmap_buffer(&upload).unwrap();
+        }
+        let texture = unsafe {
+            device.create_texture(&crate::TextureDescriptor {
+                label: None,
+                size: wgt::Extent3d {
+                    width: SIZE,
+                    height: SIZE,
+                    depth_or_array_layers: LAYERS,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgt::TextureDimension::D2,
+                format: wgt::TextureFormat::Rgba8Unorm,
+                usage: crate::TextureUses::COLOR_TARGET
+                    | crate::TextureUses::COPY_SRC
+                    | crate::TextureUses::COPY_DST,
+                memory_flags: crate::MemoryFlags::empty(),
+            })
+        }
+        .unwrap();
+
+        let copies = || {
+            (0..LAYERS).map(|layer| crate::BufferTextureCopy {
+                buffer_layout: wgt::ImageDataLayout {
+                    offset: layer as u64 * LAYER_SIZE,
+                    bytes_per_row: std::num::NonZeroU32::new(SIZE * 4),
+                    rows_per_image: None,
+                },
+                texture_base: crate::TextureCopyBase {
+                    mip_level: 0,
+                    array_layer: layer,
+                    origin: wgt::Origin3d::ZERO,
+                    aspect: crate::FormatAspects::COLOR,
+                },
+                size: crate::CopyExtent {
+                    width: SIZE,
+                    height: SIZE,
+                    depth: 1,
+                },
+            })
+        };
+        let mut encoder = unsafe {
+            device.create_command_encoder(&crate::CommandEncoderDescriptor {
+                label: None,
+                queue: &open.queue,
+            })
+        }
+        .unwrap();
+        let cmd_buf = unsafe {
+            encoder.begin_encoding(None).unwrap();
+            encoder.copy_buffer_to_texture(&upload, &texture, copies());
+            encoder.clear_texture(
+                &texture,
+                &wgt::ImageSubresourceRange {
+                    base_array_layer: 1,
+                    array_layer_count: std::num::NonZeroU32::new(2),
+                    ..Default::default()
+                },
+            );
+            encoder.copy_texture_to_buffer(
+                &texture,
+                crate::TextureUses::COPY_SRC,
+                &readback,
+                copies(),
+            );
+            encoder.end_encoding().unwrap()
+        };
+        cmd_buf.raw.commit();
+        cmd_buf.raw.wait_until_completed();
+
+        let data = unsafe {
+            let mapping = device
+                .map_buffer(&readback, 0..LAYER_SIZE * LAYERS as u64)
+                .unwrap();
+            std::slice::from_raw_parts(mapping.ptr.as_ptr(), (LAYER_SIZE * LAYERS as u64) as _)
+                .to_vec()
+        };
+        for (layer, texels) in data.chunks(LAYER_SIZE as usize).enumerate() {
+            let expected = if layer == 1 || layer == 2 { 0 } else { 0xFF };
+            assert!(
+                texels.iter().all(|&byte| byte == expected),
+                "layer {}",
+                layer
+            );
+        }
+    }
+}
@@ -216,10 +216,30 @@ pub fn map_vertex_format(format: wgt::VertexFormat) -> mtl::MTLVertexFormat {
     }
 }
 
-pub fn map_step_mode(mode: wgt::VertexStepMode) -> mtl::MTLVertexStepFunction {
-    match mode {
+/// Returns the step function and the step rate of a vertex buffer.
+///
+/// A zero stride fetches the same element for every vertex and instance,
+/// which Metal only allows with a constant step function.
+pub fn map_step_mode(
+    mode: wgt::VertexStepMode,
+    array_stride: wgt::BufferAddress,
+) -> (mtl::MTLVertexStepFunction, u64) {
+    if array_stride == 0 {
+        return (mtl::MTLVertexStepFunction::Constant, 0);
+    }
+    let function = match mode {
         wgt::VertexStepMode::Vertex => mtl::MTLVertexStepFunction::PerVertex,
         wgt::VertexStepMode::Instance => mtl::MTLVertexStepFunction::PerInstance,
+    };
+    (function, 1)
+}
+
+/// Returns the size of an index, and the index type that indexed draws
+/// (including the indirect ones) read the index buffer with.
+pub fn map_index_format(format: wgt::IndexFormat) -> (wgt::BufferAddress, mtl::MTLIndexType) {
+    match format {
+        wgt::IndexFormat::Uint16 => (2, mtl::MTLIndexType::UInt16),
+        wgt::IndexFormat::Uint32 => (4, mtl::MTLIndexType::UInt32),
     }
 }
 
@@ -277,6 +297,16 @@ pub fn map_origin(origin: &wgt::Origin3d) -> mtl::MTLOrigin {
     }
 }
 
+pub fn map_load_action(ops: crate::AttachmentOps) -> mtl::MTLLoadAction {
+    if ops.contains(crate::AttachmentOps::LOAD) {
+        mtl::MTLLoadAction::Load
+    } else if ops.contains(crate::AttachmentOps::DONT_CARE) {
+        mtl::MTLLoadAction::DontCare
+    } else {
+        mtl::MTLLoadAction::Clear
+    }
+}
+
 pub fn map_store_action(store: bool, resolve: bool) -> mtl::MTLStoreAction {
     use mtl::MTLStoreAction::*;
     match (store, resolve) {
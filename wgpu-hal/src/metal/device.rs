@@ -4,11 +4,22 @@ use std::{
     thread, time,
 };
 
+use foreign_types::ForeignType as _;
+use objc::{class, msg_send, sel, sel_impl};
+use parking_lot::Mutex;
+
 use super::conv;
 use crate::auxil::map_naga_stage;
 
 type DeviceResult<T> = Result<T, crate::DeviceError>;
 
+extern "C" {
+    static vm_page_size: usize;
+}
+
+/// `MTLTextureTypeTextureBuffer`, which isn't in the bindings yet.
+const TEXTURE_TYPE_TEXTURE_BUFFER: u64 = 9;
+
 struct CompiledShader {
     library: mtl::Library,
     function: mtl::Function,
@@ -18,6 +29,22 @@ struct CompiledShader {
     immutable_buffer_mask: usize,
 }
 
+/// Returns `true` if the inputs of `function` include the primitive index.
+fn reads_primitive_index(module: &naga::Module, function: &naga::Function) -> bool {
+    let is_primitive_index = |binding: &Option<naga::Binding>| {
+        *binding == Some(naga::Binding::BuiltIn(naga::BuiltIn::PrimitiveIndex))
+    };
+    function.arguments.iter().any(|arg| {
+        is_primitive_index(&arg.binding)
+            || match module.types[arg.ty].inner {
+                naga::TypeInner::Struct { ref members, .. } => members
+                    .iter()
+                    .any(|member| is_primitive_index(&member.binding)),
+                _ => false,
+            }
+    })
+}
+
 fn create_stencil_desc(
     face: &wgt::StencilFaceState,
     read_mask: u32,
@@ -47,7 +74,209 @@ fn create_depth_stencil_desc(state: &wgt::DepthStencilState) -> mtl::DepthStenci
     desc
 }
 
+fn buffer_options(usage: crate::BufferUses, untracked: bool) -> mtl::MTLResourceOptions {
+    let map_read = usage.contains(crate::BufferUses::MAP_READ);
+    let map_write = usage.contains(crate::BufferUses::MAP_WRITE);
+
+    let mut options = mtl::MTLResourceOptions::empty();
+    // Never use managed storage, which would need explicit synchronization
+    // between CPU and GPU writes (e.g. for indirect arguments).
+    options |= if map_read || map_write {
+        // `crate::MemoryFlags::PREFER_COHERENT` is ignored here
+        mtl::MTLResourceOptions::StorageModeShared
+    } else {
+        mtl::MTLResourceOptions::StorageModePrivate
+    };
+    options.set(
+        mtl::MTLResourceOptions::CPUCacheModeWriteCombined,
+        map_write,
+    );
+    options.set(
+        mtl::MTLResourceOptions::HazardTrackingModeUntracked,
+        untracked,
+    );
+    options
+}
+
+/// Returns the format of the views sampling the stencil aspect of `format`,
+/// which reads the stencil values as unsigned integers.
+fn stencil_view_format(format: mtl::MTLPixelFormat) -> Option<mtl::MTLPixelFormat> {
+    match format {
+        mtl::MTLPixelFormat::Depth32Float_Stencil8 => Some(mtl::MTLPixelFormat::X32_Stencil8),
+        mtl::MTLPixelFormat::Depth24Unorm_Stencil8 => Some(mtl::MTLPixelFormat::X24_Stencil8),
+        mtl::MTLPixelFormat::Stencil8 => Some(mtl::MTLPixelFormat::Stencil8),
+        _ => None,
+    }
+}
+
+/// Marks `resource` as aliasable, if it's placed in a heap.
+fn make_aliasable(resource: &mtl::ResourceRef) -> DeviceResult<()> {
+    let heap: *mut objc::runtime::Object = unsafe { msg_send![resource, heap] };
+    if heap.is_null() {
+        log::error!("Only resources placed in a heap can be made aliasable");
+        return Err(crate::DeviceError::OutOfMemory);
+    }
+    resource.make_aliasable();
+    Ok(())
+}
+
+/// Fills the color attachment of a pipeline descriptor from `ct`.
+fn set_color_target(
+    at_descriptor: &mtl::RenderPipelineColorAttachmentDescriptorRef,
+    ct: &wgt::ColorTargetState,
+    raw_format: mtl::MTLPixelFormat,
+) {
+    at_descriptor.set_pixel_format(raw_format);
+    at_descriptor.set_write_mask(conv::map_color_write(ct.write_mask));
+
+    if let Some(ref blend) = ct.blend {
+        at_descriptor.set_blending_enabled(true);
+        let (color_op, color_src, color_dst) = conv::map_blend_component(&blend.color);
+        let (alpha_op, alpha_src, alpha_dst) = conv::map_blend_component(&blend.alpha);
+
+        at_descriptor.set_rgb_blend_operation(color_op);
+        at_descriptor.set_source_rgb_blend_factor(color_src);
+        at_descriptor.set_destination_rgb_blend_factor(color_dst);
+
+        at_descriptor.set_alpha_blend_operation(alpha_op);
+        at_descriptor.set_source_alpha_blend_factor(alpha_src);
+        at_descriptor.set_destination_alpha_blend_factor(alpha_dst);
+    } else {
+        at_descriptor.set_blending_enabled(false);
+    }
+}
+
+/// Returns an autoreleased file URL for `path`.
+///
+/// The bindings only make URLs from strings that are already URLs.
+unsafe fn file_url(path: &str) -> *mut objc::runtime::Object {
+    const UTF8_ENCODING: mtl::NSUInteger = 4;
+    let ns_path: *mut objc::runtime::Object = msg_send![class!(NSString), alloc];
+    let ns_path: *mut objc::runtime::Object = msg_send![
+        ns_path,
+        initWithBytes: path.as_ptr()
+        length: path.len() as mtl::NSUInteger
+        encoding: UTF8_ENCODING
+    ];
+    let url: *mut objc::runtime::Object = msg_send![class!(NSURL), fileURLWithPath: ns_path];
+    let () = msg_send![ns_path, release];
+    url
+}
+
+/// Returns a path in the temporary directory for moving binary archives in
+/// and out of memory, as they can only be read from and written to files.
+fn temp_archive_path() -> std::path::PathBuf {
+    static COUNTER: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+    std::env::temp_dir().join(format!(
+        "wgpu-pipeline-cache-{}-{}.metallib",
+        std::process::id(),
+        COUNTER.fetch_add(1, atomic::Ordering::Relaxed)
+    ))
+}
+
+/// Creates a counter sample buffer holding `count` samples of the timestamp counter set.
+fn create_timestamp_sample_buffer(
+    device: &mtl::DeviceRef,
+    count: u32,
+) -> DeviceResult<super::CounterSampleBuffer> {
+    unsafe {
+        let timestamp_set = match super::timestamp_counter_set(device) {
+            Some(set) => set.as_ptr(),
+            None => {
+                log::error!("Device has no timestamp counter set");
+                return Err(crate::DeviceError::OutOfMemory);
+            }
+        };
+
+        let descriptor: *mut objc::runtime::Object =
+            msg_send![class!(MTLCounterSampleBufferDescriptor), new];
+        let () = msg_send![descriptor, setCounterSet: timestamp_set];
+        let () = msg_send![descriptor, setStorageMode: mtl::MTLStorageMode::Shared];
+        let () = msg_send![descriptor, setSampleCount: count as mtl::NSUInteger];
+        let mut error: *mut objc::runtime::Object = ptr::null_mut();
+        let raw: *mut objc::runtime::Object = msg_send![
+            device,
+            newCounterSampleBufferWithDescriptor: descriptor
+            error: &mut error
+        ];
+        let () = msg_send![descriptor, release];
+        match ptr::NonNull::new(raw) {
+            Some(raw) => Ok(super::CounterSampleBuffer(raw)),
+            None => {
+                log::error!(
+                    "Failed to create a counter sample buffer of {} samples",
+                    count
+                );
+                Err(crate::DeviceError::OutOfMemory)
+            }
+        }
+    }
+}
+
 impl super::Device {
+    /// Returns the size in bytes the device can allocate before its performance
+    /// suffers, if the OS reports it.
+    pub fn recommended_max_working_set_size(&self) -> Option<u64> {
+        if self.shared.private_caps.supports_working_set_size {
+            Some(self.shared.device.lock().recommended_max_working_set_size())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the total size in bytes of the resources allocated by the device,
+    /// if the OS reports it.
+    pub fn current_allocated_size(&self) -> Option<u64> {
+        if self.shared.private_caps.supports_working_set_size {
+            Some(self.shared.device.lock().current_allocated_size())
+        } else {
+            None
+        }
+    }
+
+    /// Sets the callback called when creating a buffer, texture or heap makes the
+    /// allocated size of the device go past `threshold` times its recommended
+    /// maximum working set size, such as 0.9, or removes it with `None`.
+    ///
+    /// The callback is called once each time the threshold is crossed, and not
+    /// again until a creation sees the allocated size back below it.
+    /// It's never called if the OS doesn't report the working set size.
+    pub fn set_memory_pressure_callback(
+        &mut self,
+        threshold: f32,
+        callback: Option<super::MemoryPressureCallback>,
+    ) {
+        if callback.is_some() && !self.shared.private_caps.supports_working_set_size {
+            log::warn!("Memory pressure can't be tracked without the working set size");
+        }
+        self.memory_pressure = callback.map(|callback| super::MemoryPressure {
+            threshold,
+            callback,
+            exceeded: atomic::AtomicBool::new(false),
+        });
+    }
+
+    /// Calls the memory pressure callback if the allocated size just went past
+    /// its threshold.
+    fn check_memory_pressure(&self) {
+        let pressure = match self.memory_pressure {
+            Some(ref pressure) if self.shared.private_caps.supports_working_set_size => pressure,
+            _ => return,
+        };
+        let (allocated, budget) = {
+            let device = self.shared.device.lock();
+            (
+                device.current_allocated_size(),
+                device.recommended_max_working_set_size(),
+            )
+        };
+        let exceeded = allocated as f64 > budget as f64 * pressure.threshold as f64;
+        let was_exceeded = pressure.exceeded.swap(exceeded, atomic::Ordering::Relaxed);
+        if exceeded && !was_exceeded {
+            (pressure.callback)(allocated, budget);
+        }
+    }
+
     fn load_shader(
         &self,
         stage: &crate::ProgrammableStage<super::Api>,
@@ -64,6 +293,23 @@ impl super::Device {
         };
 
         let module = &stage.module.naga.module;
+        // `[[primitive_id]]` fails to compile where it's unsupported, with an
+        // error that doesn't point at the actual problem.
+        if !self.shared.private_caps.supports_primitive_index
+            && module.entry_points.iter().any(|ep| {
+                ep.stage == naga_stage
+                    && ep.name == stage.entry_point
+                    && reads_primitive_index(module, &ep.function)
+            })
+        {
+            return Err(crate::PipelineError::Linkage(
+                stage_bit,
+                "the primitive index isn't supported by this device, \
+                 it has to be passed from the vertex stage instead"
+                    .to_string(),
+            ));
+        }
+
         let (source, info) = naga::back::msl::write_string(
             module,
             &stage.module.naga.info,
@@ -73,7 +319,7 @@ impl super::Device {
         .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("MSL: {:?}", e)))?;
 
         let options = mtl::CompileOptions::new();
-        options.set_language_version(self.shared.private_caps.msl_version);
+        options.set_language_version(self.msl_version);
 
         let library = self
             .shared
@@ -174,30 +420,60 @@ impl super::Device {
                 .set_mutability(mtl::MTLMutability::Immutable);
         }
     }
-}
 
-impl crate::Device<super::Api> for super::Device {
-    unsafe fn exit(self, _queue: super::Queue) {}
+    /// Checks whether the buffer described by `desc` can skip hazard tracking.
+    ///
+    /// Barriers on untracked buffers rely on memory barriers within compute passes,
+    /// so the buffers are tracked as usual without them.
+    fn is_untracked(&self, desc: &crate::BufferDescriptor) -> bool {
+        if !desc.memory_flags.contains(crate::MemoryFlags::UNTRACKED) {
+            return false;
+        }
+        if !self.shared.private_caps.supports_memory_barriers {
+            log::warn!(
+                "Buffer {:?} can't be untracked without memory barrier support",
+                desc.label
+            );
+            return false;
+        }
+        self.shared
+            .untracked_buffers
+            .store(true, atomic::Ordering::Relaxed);
+        true
+    }
 
-    unsafe fn create_buffer(&self, desc: &crate::BufferDescriptor) -> DeviceResult<super::Buffer> {
-        let map_read = desc.usage.contains(crate::BufferUses::MAP_READ);
-        let map_write = desc.usage.contains(crate::BufferUses::MAP_WRITE);
+    /// Creates a buffer filled with `data`, which has to be `desc.size` bytes long.
+    ///
+    /// CPU-accessible buffers are created with their contents in a single call.
+    /// Buffers in private storage get them uploaded from a staging buffer on `queue`,
+    /// so they are ready for any work submitted to `queue` afterwards.
+    pub unsafe fn create_buffer_with_data(
+        &self,
+        queue: &super::Queue,
+        desc: &crate::BufferDescriptor,
+        data: &[u8],
+    ) -> DeviceResult<super::Buffer> {
+        debug_assert_eq!(data.len() as wgt::BufferAddress, desc.size);
+        let options = buffer_options(desc.usage, self.is_untracked(desc));
+        let device = self.shared.device.lock();
 
-        let mut options = mtl::MTLResourceOptions::empty();
-        options |= if map_read || map_write {
-            // `crate::MemoryFlags::PREFER_COHERENT` is ignored here
-            mtl::MTLResourceOptions::StorageModeShared
+        let raw = if options.contains(mtl::MTLResourceOptions::StorageModePrivate) {
+            let raw = device.new_buffer(desc.size, options);
+            let staging = device.new_buffer_with_data(
+                data.as_ptr() as *const _,
+                desc.size,
+                mtl::MTLResourceOptions::StorageModeShared,
+            );
+            let queue = queue.raw.lock();
+            let cmd_buf = queue.new_command_buffer();
+            let encoder = cmd_buf.new_blit_command_encoder();
+            encoder.copy_from_buffer(&staging, 0, &raw, 0, desc.size);
+            encoder.end_encoding();
+            cmd_buf.commit();
+            raw
         } else {
-            mtl::MTLResourceOptions::StorageModePrivate
+            device.new_buffer_with_data(data.as_ptr() as *const _, desc.size, options)
         };
-        options.set(
-            mtl::MTLResourceOptions::CPUCacheModeWriteCombined,
-            map_write,
-        );
-
-        //TODO: HazardTrackingModeUntracked
-
-        let raw = self.shared.device.lock().new_buffer(desc.size, options);
         if let Some(label) = desc.label {
             raw.set_label(label);
         }
@@ -205,34 +481,29 @@ impl crate::Device<super::Api> for super::Device {
             raw,
             size: desc.size,
             options,
+            owned: true,
         })
     }
-    unsafe fn destroy_buffer(&self, _buffer: super::Buffer) {}
 
-    unsafe fn map_buffer(
-        &self,
-        buffer: &super::Buffer,
-        range: crate::MemoryRange,
-    ) -> DeviceResult<crate::BufferMapping> {
-        let ptr = buffer.raw.contents() as *mut u8;
-        assert!(!ptr.is_null());
-        Ok(crate::BufferMapping {
-            ptr: ptr::NonNull::new(ptr.offset(range.start as isize)).unwrap(),
-            is_coherent: true,
-        })
-    }
-
-    unsafe fn unmap_buffer(&self, _buffer: &super::Buffer) -> DeviceResult<()> {
-        Ok(())
-    }
-    unsafe fn flush_mapped_ranges<I>(&self, _buffer: &super::Buffer, _ranges: I) {}
-    unsafe fn invalidate_mapped_ranges<I>(&self, _buffer: &super::Buffer, _ranges: I) {}
-
-    unsafe fn create_texture(
+    /// Creates a texture placed in `heap`, or in its own allocation if there's none.
+    unsafe fn create_texture_in(
         &self,
         desc: &crate::TextureDescriptor,
+        heap: Option<&super::Heap>,
     ) -> DeviceResult<super::Texture> {
         let mtl_format = self.shared.private_caps.map_format(desc.format);
+        let sample_count_mask = self
+            .shared
+            .private_caps
+            .format_sample_count_mask(desc.format);
+        if sample_count_mask as u32 & desc.sample_count == 0 {
+            log::error!(
+                "{:?} doesn't support {} samples",
+                desc.format,
+                desc.sample_count
+            );
+            return Err(crate::DeviceError::OutOfMemory);
+        }
 
         let descriptor = mtl::TextureDescriptor::new();
         let mut array_layers = desc.size.depth_or_array_layers;
@@ -274,10 +545,27 @@ impl crate::Device<super::Api> for super::Device {
         descriptor.set_height(desc.size.height as u64);
         descriptor.set_mipmap_level_count(desc.mip_level_count as u64);
         descriptor.set_pixel_format(mtl_format);
-        descriptor.set_usage(conv::map_texture_usage(desc.usage));
+        let mut usage = conv::map_texture_usage(desc.usage);
+        if desc.usage.contains(crate::TextureUses::RESOURCE)
+            && stencil_view_format(mtl_format).is_some()
+        {
+            // Needed by the stencil views sampling the texture.
+            usage |= mtl::MTLTextureUsage::PixelFormatView;
+        }
+        descriptor.set_usage(usage);
         descriptor.set_storage_mode(mtl::MTLStorageMode::Private);
 
-        let raw = self.shared.device.lock().new_texture(&descriptor);
+        let raw = match heap {
+            Some(heap) => heap.raw.new_texture(&descriptor).ok_or_else(|| {
+                log::error!("Heap has no room left for texture {:?}", desc.label);
+                crate::DeviceError::OutOfMemory
+            })?,
+            None => {
+                let raw = self.shared.device.lock().new_texture(&descriptor);
+                self.check_memory_pressure();
+                raw
+            }
+        };
         if let Some(label) = desc.label {
             raw.set_label(label);
         }
@@ -290,17 +578,701 @@ impl crate::Device<super::Api> for super::Device {
             mip_levels: desc.mip_level_count,
             array_layers,
             copy_size,
+            drop_guard: None,
+            owned: heap.is_none(),
+        })
+    }
+
+    /// Creates a heap of `size` bytes, which textures and buffers can be placed in
+    /// with `create_texture_in_heap` and `create_buffer_in_heap`.
+    pub unsafe fn create_heap(
+        &self,
+        size: wgt::BufferAddress,
+        label: crate::Label,
+    ) -> DeviceResult<super::Heap> {
+        // Barriers on the resources of a heap rely on memory barriers within
+        // compute passes, as they aren't tracked.
+        let caps = &self.shared.private_caps;
+        if !caps.resource_heaps || !caps.supports_memory_barriers {
+            log::error!("Resource heaps are not supported");
+            return Err(crate::DeviceError::OutOfMemory);
+        }
+
+        let descriptor = mtl::HeapDescriptor::new();
+        descriptor.set_size(size);
+        descriptor.set_storage_mode(mtl::MTLStorageMode::Private);
+        let (raw, fence) = {
+            let device = self.shared.device.lock();
+            (device.new_heap(&descriptor), device.new_fence())
+        };
+        if let Some(label) = label {
+            raw.set_label(label);
+        }
+        self.check_memory_pressure();
+        Ok(super::Heap { raw, fence })
+    }
+
+    /// Creates a texture placed in `heap`.
+    ///
+    /// # Safety
+    ///
+    /// - `heap` has to be created by the same device, and outlive the texture.
+    /// - Accesses to the texture have to follow `CommandEncoder::use_heap`.
+    pub unsafe fn create_texture_in_heap(
+        &self,
+        heap: &super::Heap,
+        desc: &crate::TextureDescriptor,
+    ) -> DeviceResult<super::Texture> {
+        if desc
+            .usage
+            .intersects(crate::TextureUses::COLOR_TARGET | crate::TextureUses::DEPTH_STENCIL_WRITE)
+            && !self.shared.private_caps.render_target_heaps
+        {
+            log::error!(
+                "Texture {:?} is a render target, which can't be placed in a heap",
+                desc.label
+            );
+            return Err(crate::DeviceError::OutOfMemory);
+        }
+        self.create_texture_in(desc, Some(heap))
+    }
+
+    /// Creates a buffer placed in `heap`. The heap is in private storage, so
+    /// the buffer can't be mapped.
+    ///
+    /// # Safety
+    ///
+    /// - `heap` has to be created by the same device, and outlive the buffer.
+    /// - Accesses to the buffer have to follow `CommandEncoder::use_heap`.
+    pub unsafe fn create_buffer_in_heap(
+        &self,
+        heap: &super::Heap,
+        desc: &crate::BufferDescriptor,
+    ) -> DeviceResult<super::Buffer> {
+        if desc
+            .usage
+            .intersects(crate::BufferUses::MAP_READ | crate::BufferUses::MAP_WRITE)
+        {
+            log::error!(
+                "Buffer {:?} is mappable, which can't be placed in a heap",
+                desc.label
+            );
+            return Err(crate::DeviceError::OutOfMemory);
+        }
+        let options = buffer_options(desc.usage, true);
+        let raw = heap.raw.new_buffer(desc.size, options).ok_or_else(|| {
+            log::error!("Heap has no room left for buffer {:?}", desc.label);
+            crate::DeviceError::OutOfMemory
+        })?;
+        if let Some(label) = desc.label {
+            raw.set_label(label);
+        }
+        Ok(super::Buffer {
+            raw,
+            size: desc.size,
+            options,
+            owned: false,
+        })
+    }
+
+    /// Lets the memory of `texture` be reused by the resources placed in its heap
+    /// afterwards, which then alias it.
+    ///
+    /// # Safety
+    ///
+    /// - The texture must not be accessed by the work encoded after this point.
+    /// - The aliasing resources have to be accessed after `CommandEncoder::use_heap`,
+    ///   so that they wait for the accesses to the texture encoded before.
+    pub unsafe fn make_texture_aliasable(&self, texture: &super::Texture) -> DeviceResult<()> {
+        make_aliasable(&texture.raw)
+    }
+
+    /// Lets the memory of `buffer` be reused by the resources placed in its heap
+    /// afterwards, which then alias it.
+    ///
+    /// # Safety
+    ///
+    /// - The buffer must not be accessed by the work encoded after this point.
+    /// - The aliasing resources have to be accessed after `CommandEncoder::use_heap`,
+    ///   so that they wait for the accesses to the buffer encoded before.
+    pub unsafe fn make_buffer_aliasable(&self, buffer: &super::Buffer) -> DeviceResult<()> {
+        make_aliasable(&buffer.raw)
+    }
+
+    /// Starts capturing the work submitted to `queue` into a GPU trace document
+    /// at `path`, which can be opened in Xcode afterwards.
+    ///
+    /// The capture goes on until `stop_capture` is called. Captures are only
+    /// enabled by the OS for applications run with `MTL_CAPTURE_ENABLED=1`,
+    /// or with the `MetalCaptureEnabled` key in their `Info.plist`.
+    pub unsafe fn start_capture_to_file(
+        &self,
+        queue: &super::Queue,
+        path: &std::path::Path,
+    ) -> Result<(), super::CaptureError> {
+        if !self.shared.private_caps.supports_capture_to_file {
+            return Err(super::CaptureError::Unsupported);
+        }
+        let capture_manager = mtl::CaptureManager::shared();
+        if !capture_manager.supports_destination(mtl::MTLCaptureDestination::GpuTraceDocument) {
+            return Err(super::CaptureError::Unsupported);
+        }
+        let path_str = path
+            .to_str()
+            .ok_or_else(|| super::CaptureError::InvalidPath(path.to_path_buf()))?;
+
+        objc::rc::autoreleasepool(|| {
+            let descriptor = mtl::CaptureDescriptor::new();
+            descriptor.set_capture_command_queue(&queue.raw.lock());
+            descriptor.set_destination(mtl::MTLCaptureDestination::GpuTraceDocument);
+            // `CaptureDescriptor::set_output_url` passes a string where an URL
+            // is expected, so the URL is set here.
+            let () = msg_send![descriptor.as_ref(), setOutputURL: file_url(path_str)];
+            capture_manager
+                .start_capture(&descriptor)
+                .map_err(super::CaptureError::Failed)
+        })
+    }
+
+    /// Makes the pipelines created from now on go through a cache, which starts
+    /// with the contents of `data` if given, as returned by `pipeline_cache_data`
+    /// on a previous run.
+    ///
+    /// Pipelines found in the cache are loaded instead of being compiled, and the
+    /// others are added to it. Invalid `data` is ignored, starting with an empty
+    /// cache. Returns `false` if the device doesn't support binary archives, in
+    /// which case pipelines are always compiled.
+    pub fn enable_pipeline_cache(&mut self, data: Option<&[u8]>) -> bool {
+        if !self.shared.private_caps.supports_binary_archives {
+            return false;
+        }
+
+        let raw = objc::rc::autoreleasepool(|| {
+            let device = self.shared.device.lock();
+            if let Some(data) = data {
+                let path = temp_archive_path();
+                let loaded = match (std::fs::write(&path, data), path.to_str()) {
+                    (Ok(()), Some(path_str)) => {
+                        let descriptor = mtl::BinaryArchiveDescriptor::new();
+                        let () =
+                            unsafe { msg_send![descriptor.as_ref(), setUrl: file_url(path_str)] };
+                        device.new_binary_archive_with_descriptor(&descriptor)
+                    }
+                    (Err(e), _) => Err(e.to_string()),
+                    (Ok(()), None) => Err(format!("{:?} is not valid UTF-8", path)),
+                };
+                let _ = std::fs::remove_file(&path);
+                match loaded {
+                    Ok(raw) => return Ok(raw),
+                    Err(e) => log::warn!("Pipeline cache data can't be loaded: {}", e),
+                }
+            }
+            device.new_binary_archive_with_descriptor(&mtl::BinaryArchiveDescriptor::new())
+        });
+        match raw {
+            Ok(raw) => {
+                self.pipeline_cache = Some(super::PipelineCache {
+                    raw: Mutex::new(raw),
+                });
+                true
+            }
+            Err(e) => {
+                log::error!("Pipeline cache can't be created: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Returns the contents of the pipeline cache, which can be given to
+    /// `enable_pipeline_cache` on the next run, if it's enabled.
+    pub fn pipeline_cache_data(&self) -> Option<Vec<u8>> {
+        let cache = self.pipeline_cache.as_ref()?;
+        let path = temp_archive_path();
+        let path_str = path.to_str()?;
+        let serialized = objc::rc::autoreleasepool(|| {
+            let url = unsafe { file_url(path_str) };
+            cache
+                .raw
+                .lock()
+                .serialize_to_url(unsafe { &*(url as *const mtl::URLRef) })
+        });
+        let data = match serialized {
+            Ok(_) => std::fs::read(&path).map_err(|e| e.to_string()),
+            Err(e) => Err(e),
+        };
+        let _ = std::fs::remove_file(&path);
+        data.map_err(|e| log::error!("Pipeline cache can't be serialized: {}", e))
+            .ok()
+    }
+
+    /// Uploads `data` into every mip level and array layer of `texture`, with all
+    /// the copies encoded into a single blit encoder on `queue`.
+    ///
+    /// `data` holds the mip levels of each layer in turn, each level being tightly
+    /// packed rows of blocks, rounded up to whole blocks. The upload is ready for
+    /// any work submitted to `queue` afterwards.
+    pub unsafe fn write_texture_mips(
+        &self,
+        queue: &super::Queue,
+        texture: &super::Texture,
+        data: &[u8],
+    ) -> DeviceResult<()> {
+        let format_desc = texture.format.describe();
+        let (block_width, block_height) = (
+            format_desc.block_dimensions.0 as u64,
+            format_desc.block_dimensions.1 as u64,
+        );
+        let block_size = format_desc.block_size as u64;
+
+        // (offset, bytes per row, bytes per image, size) of each level
+        let mut levels = Vec::with_capacity(texture.mip_levels as usize);
+        let mut offset = 0;
+        for mip_level in 0..texture.mip_levels {
+            let size = mtl::MTLSize::new(
+                (texture.copy_size.width as u64 >> mip_level).max(1),
+                (texture.copy_size.height as u64 >> mip_level).max(1),
+                (texture.copy_size.depth as u64 >> mip_level).max(1),
+            );
+            let bytes_per_row = (size.width + block_width - 1) / block_width * block_size;
+            let bytes_per_image = (size.height + block_height - 1) / block_height * bytes_per_row;
+            levels.push((offset, bytes_per_row, bytes_per_image, size));
+            offset += bytes_per_image * size.depth;
+        }
+        let layer_size = offset;
+        if data.len() as u64 != layer_size * texture.array_layers as u64 {
+            log::error!(
+                "Mip chain data is {} bytes, expected {} for each of the {} layers",
+                data.len(),
+                layer_size,
+                texture.array_layers
+            );
+            return Err(crate::DeviceError::OutOfMemory);
+        }
+
+        let staging = self.shared.device.lock().new_buffer_with_data(
+            data.as_ptr() as *const _,
+            data.len() as u64,
+            mtl::MTLResourceOptions::StorageModeShared,
+        );
+        let queue = queue.raw.lock();
+        let cmd_buf = queue.new_command_buffer();
+        let encoder = cmd_buf.new_blit_command_encoder();
+        for layer in 0..texture.array_layers as u64 {
+            for (mip_level, &(offset, bytes_per_row, bytes_per_image, size)) in
+                levels.iter().enumerate()
+            {
+                encoder.copy_from_buffer_to_texture(
+                    &staging,
+                    layer * layer_size + offset,
+                    bytes_per_row,
+                    bytes_per_image,
+                    size,
+                    &texture.raw,
+                    layer,
+                    mip_level as u64,
+                    mtl::MTLOrigin { x: 0, y: 0, z: 0 },
+                    mtl::MTLBlitOption::empty(),
+                );
+            }
+        }
+        encoder.end_encoding();
+        cmd_buf.commit();
+        Ok(())
+    }
+
+    /// Wraps `desc.size` bytes of host memory at `ptr` into a buffer in shared
+    /// storage, without copying them.
+    ///
+    /// `drop_callback` is called once Metal is done with the memory, which may be
+    /// after [`crate::Device::destroy_buffer`] if the GPU still uses the buffer.
+    ///
+    /// # Safety
+    ///
+    /// - `ptr` has to be page-aligned, and `desc.size` a multiple of the page size.
+    /// - The memory has to stay valid until `drop_callback` is called, or until
+    ///   the buffer is released if there is no callback.
+    pub unsafe fn create_buffer_from_host_memory(
+        &self,
+        desc: &crate::BufferDescriptor,
+        ptr: ptr::NonNull<u8>,
+        drop_callback: Option<Box<dyn FnOnce() + Send>>,
+    ) -> DeviceResult<super::Buffer> {
+        let page_mask = vm_page_size as u64 - 1;
+        if ptr.as_ptr() as u64 & page_mask != 0 || desc.size & page_mask != 0 {
+            log::error!(
+                "Host memory {:?} of size {} isn't aligned to the page size {}",
+                ptr,
+                desc.size,
+                vm_page_size
+            );
+            return Err(crate::DeviceError::OutOfMemory);
+        }
+
+        let options = mtl::MTLResourceOptions::StorageModeShared;
+        let device = self.shared.device.lock();
+        let raw = match drop_callback {
+            Some(callback) => {
+                let callback = Mutex::new(Some(callback));
+                let deallocator = block::ConcreteBlock::new(
+                    move |_ptr: *const std::ffi::c_void, _length: u64| {
+                        if let Some(callback) = callback.lock().take() {
+                            callback();
+                        }
+                    },
+                )
+                .copy();
+                device.new_buffer_with_bytes_no_copy(
+                    ptr.as_ptr() as *const _,
+                    desc.size,
+                    options,
+                    Some(&deallocator),
+                )
+            }
+            None => device.new_buffer_with_bytes_no_copy(
+                ptr.as_ptr() as *const _,
+                desc.size,
+                options,
+                None,
+            ),
+        };
+        if let Some(label) = desc.label {
+            raw.set_label(label);
+        }
+        Ok(super::Buffer {
+            raw,
+            size: desc.size,
+            options,
+            owned: false,
+        })
+    }
+
+    /// Wraps a texture created outside of wgpu, e.g. one backed by an `IOSurface`.
+    ///
+    /// `drop_callback` is called when the texture is destroyed, by which point
+    /// no submitted work uses it anymore, so its storage can be recycled.
+    ///
+    /// # Safety
+    ///
+    /// - `raw` has to be created by the same device, with a pixel format
+    ///   matching `format`.
+    /// - `raw` has to stay valid until `drop_callback` is called, or until the
+    ///   texture is destroyed if there is no callback.
+    pub unsafe fn texture_from_raw(
+        &self,
+        raw: mtl::Texture,
+        format: wgt::TextureFormat,
+        drop_callback: Option<Box<dyn FnOnce() + Send>>,
+    ) -> super::Texture {
+        let raw_type = raw.texture_type();
+        let (array_layers, depth) = match raw_type {
+            mtl::MTLTextureType::D3 => (1, raw.depth() as u32),
+            _ => (raw.array_length() as u32, 1),
+        };
+        super::Texture {
+            format,
+            raw_format: raw.pixel_format(),
+            raw_type,
+            array_layers,
+            mip_levels: raw.mipmap_level_count() as u32,
+            copy_size: crate::CopyExtent {
+                width: raw.width() as u32,
+                height: raw.height() as u32,
+                depth,
+            },
+            drop_guard: drop_callback.map(super::DropGuard),
+            owned: false,
+            raw,
+        }
+    }
+
+    /// Checks that `event` was created by this device, which is required to
+    /// signal or wait for it in command buffers.
+    fn check_shared_event(&self, event: &mtl::SharedEventRef) -> DeviceResult<()> {
+        let device = self.shared.device.lock();
+        if event.device().registry_id() != device.registry_id() {
+            log::error!("Shared event belongs to another device");
+            return Err(crate::DeviceError::OutOfMemory);
+        }
+        Ok(())
+    }
+
+    /// Fills a sampler descriptor from `desc`, lowering the anisotropy clamp
+    /// to what the device supports.
+    fn sampler_descriptor(
+        &self,
+        desc: &crate::SamplerDescriptor,
+    ) -> DeviceResult<mtl::SamplerDescriptor> {
+        let caps = &self.shared.private_caps;
+        let descriptor = mtl::SamplerDescriptor::new();
+
+        descriptor.set_min_filter(conv::map_filter_mode(desc.min_filter));
+        descriptor.set_mag_filter(conv::map_filter_mode(desc.mag_filter));
+        descriptor.set_mip_filter(match desc.mipmap_filter {
+            wgt::FilterMode::Nearest if desc.lod_clamp.is_none() => {
+                mtl::MTLSamplerMipFilter::NotMipmapped
+            }
+            wgt::FilterMode::Nearest => mtl::MTLSamplerMipFilter::Nearest,
+            wgt::FilterMode::Linear => mtl::MTLSamplerMipFilter::Linear,
+        });
+
+        if let Some(aniso) = desc.anisotropy_clamp {
+            // Metal would clamp it as well, but silently.
+            let max_anisotropy = aniso.get().min(caps.max_sampler_anisotropy);
+            if max_anisotropy < aniso.get() {
+                log::warn!(
+                    "Anisotropy clamp {} is lowered to the maximum of {}",
+                    aniso,
+                    max_anisotropy
+                );
+            }
+            descriptor.set_max_anisotropy(max_anisotropy as _);
+        }
+
+        let map_address_mode = |mode| match (mode, desc.border_color) {
+            // Transparent black borders don't need `ClampToBorderColor`,
+            // which isn't available on all devices.
+            (
+                wgt::AddressMode::ClampToBorder,
+                None | Some(wgt::SamplerBorderColor::TransparentBlack),
+            ) => mtl::MTLSamplerAddressMode::ClampToZero,
+            _ => conv::map_address_mode(mode),
+        };
+        let [s, t, r] = desc.address_modes;
+        descriptor.set_address_mode_s(map_address_mode(s));
+        descriptor.set_address_mode_t(map_address_mode(t));
+        descriptor.set_address_mode_r(map_address_mode(r));
+
+        if let Some(ref range) = desc.lod_clamp {
+            descriptor.set_lod_min_clamp(range.start);
+            descriptor.set_lod_max_clamp(range.end);
+        }
+
+        if caps.sampler_lod_average {
+            descriptor.set_lod_average(true); // optimization
+        }
+
+        if let Some(fun) = desc.compare {
+            if !caps.mutable_comparison_samplers {
+                log::error!(
+                    "Comparison function {:?} can't be set on a sampler object",
+                    fun
+                );
+                return Err(crate::DeviceError::OutOfMemory);
+            }
+            descriptor.set_compare_function(conv::map_compare_function(fun));
+        }
+        if let Some(border_color) = desc.border_color {
+            if caps.sampler_clamp_to_border {
+                descriptor.set_border_color(conv::map_border_color(border_color));
+            }
+        }
+
+        // Samplers can't be encoded into argument buffers without it.
+        if caps.argument_buffer_samplers {
+            descriptor.set_support_argument_buffers(true);
+        }
+
+        if let Some(label) = desc.label {
+            descriptor.set_label(label);
+        }
+        Ok(descriptor)
+    }
+
+    /// Creates a view of `texel_count` texels of `buffer` starting at `offset`,
+    /// which shaders access as a `texture_buffer`.
+    ///
+    /// Fails if buffer textures aren't supported, if the format isn't an
+    /// uncompressed color one, or if `offset` isn't aligned to the minimum
+    /// alignment of the format.
+    ///
+    /// # Safety
+    ///
+    /// The view must not outlive `buffer`.
+    pub unsafe fn create_buffer_texture_view(
+        &self,
+        buffer: &super::Buffer,
+        format: wgt::TextureFormat,
+        offset: wgt::BufferAddress,
+        texel_count: u32,
+    ) -> DeviceResult<super::TextureView> {
+        if !self.shared.private_caps.supports_texture_buffer {
+            log::error!("Buffer textures are not supported");
+            return Err(crate::DeviceError::OutOfMemory);
+        }
+        let info = format.describe();
+        if info.block_dimensions != (1, 1) || info.sample_type == wgt::TextureSampleType::Depth {
+            log::error!("Format {:?} can't be used for a buffer texture", format);
+            return Err(crate::DeviceError::OutOfMemory);
+        }
+
+        let raw_format = self.shared.private_caps.map_format(format);
+        let device = self.shared.device.lock();
+        let alignment = device.minimum_texture_buffer_alignment_for_pixel_format(raw_format);
+        let row_size = texel_count as u64 * info.block_size as u64;
+        if offset % alignment != 0 || offset + row_size > buffer.size {
+            log::error!(
+                "Buffer texture of {} bytes at offset {} doesn't fit a buffer of {} bytes \
+                with an alignment of {}",
+                row_size,
+                offset,
+                buffer.size,
+                alignment
+            );
+            return Err(crate::DeviceError::OutOfMemory);
+        }
+
+        let descriptor = mtl::TextureDescriptor::new();
+        let () = msg_send![descriptor, setTextureType: TEXTURE_TYPE_TEXTURE_BUFFER];
+        descriptor.set_pixel_format(raw_format);
+        descriptor.set_width(texel_count as u64);
+        descriptor.set_resource_options(buffer.options);
+        descriptor.set_usage(mtl::MTLTextureUsage::ShaderRead);
+        let raw = buffer
+            .raw
+            .new_texture_with_descriptor(&descriptor, offset, row_size);
+        Ok(super::TextureView {
+            raw,
+            aspects: crate::FormatAspects::COLOR,
         })
     }
 
-    unsafe fn destroy_texture(&self, _texture: super::Texture) {}
+    /// Creates a `MTLSharedEvent`, which can be shared with other processes
+    /// with `export_shared_event`.
+    pub unsafe fn create_shared_event(&self) -> DeviceResult<mtl::SharedEvent> {
+        if !self.shared.private_caps.supports_shared_event {
+            log::error!("Shared events are not supported");
+            return Err(crate::DeviceError::OutOfMemory);
+        }
+        Ok(self.shared.device.lock().new_shared_event())
+    }
+
+    /// Creates a handle to `event` that another process can import with
+    /// `import_shared_event`, on the same physical device.
+    pub unsafe fn export_shared_event(
+        &self,
+        event: &mtl::SharedEventRef,
+    ) -> DeviceResult<super::SharedEventHandle> {
+        self.check_shared_event(event)?;
+        let raw: *mut objc::runtime::Object = msg_send![event, newSharedEventHandle];
+        match ptr::NonNull::new(raw) {
+            Some(raw) => Ok(super::SharedEventHandle::from_raw(raw)),
+            None => Err(crate::DeviceError::OutOfMemory),
+        }
+    }
+
+    /// Imports a shared event exported by another process.
+    ///
+    /// Fails if the event was created on another device.
+    pub unsafe fn import_shared_event(
+        &self,
+        handle: &super::SharedEventHandle,
+    ) -> DeviceResult<mtl::SharedEvent> {
+        if !self.shared.private_caps.supports_shared_event {
+            log::error!("Shared events are not supported");
+            return Err(crate::DeviceError::OutOfMemory);
+        }
+        let raw: *mut mtl::MTLSharedEvent = {
+            let device = self.shared.device.lock();
+            let device: &mtl::DeviceRef = &device;
+            msg_send![device, newSharedEventWithHandle: handle.as_ptr()]
+        };
+        if raw.is_null() {
+            log::error!("Shared event handle couldn't be imported");
+            return Err(crate::DeviceError::OutOfMemory);
+        }
+        let event = mtl::SharedEvent::from_ptr(raw);
+        self.check_shared_event(&event)?;
+        Ok(event)
+    }
+}
+
+impl crate::Device<super::Api> for super::Device {
+    unsafe fn exit(self, _queue: super::Queue) {}
+
+    unsafe fn create_buffer(&self, desc: &crate::BufferDescriptor) -> DeviceResult<super::Buffer> {
+        let options = buffer_options(desc.usage, self.is_untracked(desc));
+        let raw = self.shared.device.lock().new_buffer(desc.size, options);
+        if let Some(label) = desc.label {
+            raw.set_label(label);
+        }
+        self.check_memory_pressure();
+        Ok(super::Buffer {
+            raw,
+            size: desc.size,
+            options,
+            owned: true,
+        })
+    }
+    unsafe fn destroy_buffer(&self, buffer: super::Buffer) {
+        // The resource is no longer in use by the GPU at this point, but it may
+        // still be retained elsewhere, so discard the memory right away instead
+        // of waiting for the last reference to go. Memory of the heap or the
+        // host it was placed in isn't the buffer's to discard.
+        if buffer.owned {
+            buffer
+                .raw
+                .set_purgeable_state(mtl::MTLPurgeableState::Empty);
+        }
+    }
+
+    unsafe fn map_buffer(
+        &self,
+        buffer: &super::Buffer,
+        range: crate::MemoryRange,
+    ) -> DeviceResult<crate::BufferMapping> {
+        let ptr = buffer.raw.contents() as *mut u8;
+        assert!(!ptr.is_null());
+        Ok(crate::BufferMapping {
+            ptr: ptr::NonNull::new(ptr.offset(range.start as isize)).unwrap(),
+            is_coherent: true,
+        })
+    }
+
+    unsafe fn unmap_buffer(&self, _buffer: &super::Buffer) -> DeviceResult<()> {
+        Ok(())
+    }
+    unsafe fn flush_mapped_ranges<I>(&self, _buffer: &super::Buffer, _ranges: I) {}
+    unsafe fn invalidate_mapped_ranges<I>(&self, _buffer: &super::Buffer, _ranges: I) {}
+
+    unsafe fn create_texture(
+        &self,
+        desc: &crate::TextureDescriptor,
+    ) -> DeviceResult<super::Texture> {
+        self.create_texture_in(desc, None)
+    }
+
+    unsafe fn destroy_texture(&self, texture: super::Texture) {
+        if let Some(super::DropGuard(callback)) = texture.drop_guard {
+            // No GPU work uses the external texture anymore at this point.
+            callback();
+        } else if texture.owned {
+            // Views may still retain the texture, so its memory is discarded
+            // right away, like in `destroy_buffer`. External textures and the
+            // ones placed in a heap don't own theirs.
+            texture
+                .raw
+                .set_purgeable_state(mtl::MTLPurgeableState::Empty);
+        }
+    }
 
     unsafe fn create_texture_view(
         &self,
         texture: &super::Texture,
         desc: &crate::TextureViewDescriptor,
     ) -> DeviceResult<super::TextureView> {
-        let raw_format = self.shared.private_caps.map_format(desc.format);
+        let aspects =
+            crate::FormatAspects::from(desc.format) & crate::FormatAspects::from(desc.range.aspect);
+        let raw_format = if aspects == crate::FormatAspects::STENCIL {
+            match stencil_view_format(texture.raw_format) {
+                Some(format) => format,
+                None => {
+                    log::error!("{:?} has no stencil aspect to view", desc.format);
+                    return Err(crate::DeviceError::OutOfMemory);
+                }
+            }
+        } else {
+            self.shared.private_caps.map_format(desc.format)
+        };
 
         let raw_type = if texture.raw_type == mtl::MTLTextureType::D2Multisample {
             texture.raw_type
@@ -343,7 +1315,6 @@ impl crate::Device<super::Api> for super::Device {
             raw
         };
 
-        let aspects = crate::FormatAspects::from(desc.format);
         Ok(super::TextureView { raw, aspects })
     }
     unsafe fn destroy_texture_view(&self, _view: super::TextureView) {}
@@ -352,47 +1323,7 @@ impl crate::Device<super::Api> for super::Device {
         &self,
         desc: &crate::SamplerDescriptor,
     ) -> DeviceResult<super::Sampler> {
-        let caps = &self.shared.private_caps;
-        let descriptor = mtl::SamplerDescriptor::new();
-
-        descriptor.set_min_filter(conv::map_filter_mode(desc.min_filter));
-        descriptor.set_mag_filter(conv::map_filter_mode(desc.mag_filter));
-        descriptor.set_mip_filter(match desc.mipmap_filter {
-            wgt::FilterMode::Nearest if desc.lod_clamp.is_none() => {
-                mtl::MTLSamplerMipFilter::NotMipmapped
-            }
-            wgt::FilterMode::Nearest => mtl::MTLSamplerMipFilter::Nearest,
-            wgt::FilterMode::Linear => mtl::MTLSamplerMipFilter::Linear,
-        });
-
-        if let Some(aniso) = desc.anisotropy_clamp {
-            descriptor.set_max_anisotropy(aniso.get() as _);
-        }
-
-        let [s, t, r] = desc.address_modes;
-        descriptor.set_address_mode_s(conv::map_address_mode(s));
-        descriptor.set_address_mode_t(conv::map_address_mode(t));
-        descriptor.set_address_mode_r(conv::map_address_mode(r));
-
-        if let Some(ref range) = desc.lod_clamp {
-            descriptor.set_lod_min_clamp(range.start);
-            descriptor.set_lod_max_clamp(range.end);
-        }
-
-        if caps.sampler_lod_average {
-            descriptor.set_lod_average(true); // optimization
-        }
-
-        if let Some(fun) = desc.compare {
-            descriptor.set_compare_function(conv::map_compare_function(fun));
-        }
-        if let Some(border_color) = desc.border_color {
-            descriptor.set_border_color(conv::map_border_color(border_color));
-        }
-
-        if let Some(label) = desc.label {
-            descriptor.set_label(label);
-        }
+        let descriptor = self.sampler_descriptor(desc)?;
         let raw = self.shared.device.lock().new_sampler(&descriptor);
 
         Ok(super::Sampler { raw })
@@ -409,6 +1340,7 @@ impl crate::Device<super::Api> for super::Device {
             raw_cmd_buf: None,
             state: super::CommandState::default(),
             temp: super::Temp::default(),
+            untracked_fence: None,
         })
     }
     unsafe fn destroy_command_encoder(&self, _encoder: super::CommandEncoder) {}
@@ -417,6 +1349,26 @@ impl crate::Device<super::Api> for super::Device {
         &self,
         desc: &crate::BindGroupLayoutDescriptor,
     ) -> DeviceResult<super::BindGroupLayout> {
+        for entry in desc.entries {
+            if let wgt::BindingType::Buffer {
+                ty: wgt::BufferBindingType::Storage { read_only: false },
+                ..
+            } = entry.ty
+            {
+                if !self
+                    .shared
+                    .private_caps
+                    .supports_read_write_buffer(entry.visibility)
+                {
+                    log::error!(
+                        "Read-write storage buffer at binding {} can't be visible to {:?}",
+                        entry.binding,
+                        entry.visibility
+                    );
+                    return Err(crate::DeviceError::OutOfMemory);
+                }
+            }
+        }
         Ok(super::BindGroupLayout {
             entries: Arc::from(desc.entries),
         })
@@ -553,17 +1505,27 @@ impl crate::Device<super::Api> for super::Device {
         }
 
         // Finally, make sure we fit the limits
-        for info in stage_data.iter_mut() {
+        let pc = &self.shared.private_caps;
+        let stage_names = ["vertex", "fragment", "compute"];
+        for (stage_name, info) in stage_names.iter().zip(stage_data.iter_mut()) {
             // handle the sizes buffer assignment and shader overrides
             if info.sizes_count != 0 {
                 info.sizes_buffer = Some(info.counters.buffers);
                 info.counters.buffers += 1;
             }
-            if info.counters.buffers > self.shared.private_caps.max_buffers_per_stage
-                || info.counters.textures > self.shared.private_caps.max_textures_per_stage
-                || info.counters.samplers > self.shared.private_caps.max_samplers_per_stage
+            if info.counters.buffers > pc.max_buffers_per_stage
+                || info.counters.textures > pc.max_textures_per_stage
+                || info.counters.samplers > pc.max_samplers_per_stage
             {
-                log::error!("Resource limit exceeded: {:?}", info);
+                log::error!(
+                    "The {} bind groups need {:?} {} stage resources, which exceeds the limits of {} buffers, {} textures and {} samplers",
+                    desc.bind_group_layouts.len(),
+                    info.counters,
+                    stage_name,
+                    pc.max_buffers_per_stage,
+                    pc.max_textures_per_stage,
+                    pc.max_samplers_per_stage,
+                );
                 return Err(crate::DeviceError::OutOfMemory);
             }
         }
@@ -588,7 +1550,7 @@ impl crate::Device<super::Api> for super::Device {
             }),
             total_counters: stage_data.map(|info| info.counters.clone()),
             naga_options: naga::back::msl::Options {
-                lang_version: match self.shared.private_caps.msl_version {
+                lang_version: match self.msl_version {
                     mtl::MTLLanguageVersion::V1_0 => (1, 0),
                     mtl::MTLLanguageVersion::V1_1 => (1, 1),
                     mtl::MTLLanguageVersion::V1_2 => (1, 2),
@@ -623,6 +1585,28 @@ impl crate::Device<super::Api> for super::Device {
         &self,
         desc: &crate::BindGroupDescriptor<super::Api>,
     ) -> DeviceResult<super::BindGroup> {
+        for (entry, layout) in desc.entries.iter().zip(desc.layout.entries.iter()) {
+            if let wgt::BindingType::Texture {
+                sample_type: wgt::TextureSampleType::Float { filterable: true },
+                ..
+            } = layout.ty
+            {
+                let start = entry.resource_index as usize;
+                let end = start + layout.count.map_or(1, |c| c.get()) as usize;
+                for tex in desc.textures[start..end].iter() {
+                    let format = tex.view.raw.pixel_format();
+                    if !self.shared.private_caps.supports_depth_filtering(format) {
+                        log::error!(
+                            "{:?} texture at binding {} can't be sampled with filtering",
+                            format,
+                            layout.binding
+                        );
+                        return Err(crate::DeviceError::OutOfMemory);
+                    }
+                }
+            }
+        }
+
         let mut bg = super::BindGroup::default();
         for (&stage, counter) in super::NAGA_STAGES.iter().zip(bg.counters.iter_mut()) {
             let stage_bit = map_naga_stage(stage);
@@ -767,33 +1751,40 @@ impl crate::Device<super::Api> for super::Device {
             }
         };
 
+        let max_color_targets = self.shared.private_caps.max_color_render_targets as usize;
+        if desc.color_targets.len() > max_color_targets {
+            return Err(crate::PipelineError::Linkage(
+                wgt::ShaderStages::FRAGMENT,
+                format!(
+                    "{} color targets exceed the limit of {}",
+                    desc.color_targets.len(),
+                    max_color_targets
+                ),
+            ));
+        }
+        // Each attachment gets its own blend state, so blended and opaque targets
+        // can be mixed. Depth-only pipelines, like the ones rendering shadow maps,
+        // leave all the color formats invalid.
         for (i, ct) in desc.color_targets.iter().enumerate() {
             let at_descriptor = descriptor.color_attachments().object_at(i as u64).unwrap();
-
             let raw_format = self.shared.private_caps.map_format(ct.format);
-            at_descriptor.set_pixel_format(raw_format);
-            at_descriptor.set_write_mask(conv::map_color_write(ct.write_mask));
-
-            if let Some(ref blend) = ct.blend {
-                at_descriptor.set_blending_enabled(true);
-                let (color_op, color_src, color_dst) = conv::map_blend_component(&blend.color);
-                let (alpha_op, alpha_src, alpha_dst) = conv::map_blend_component(&blend.alpha);
-
-                at_descriptor.set_rgb_blend_operation(color_op);
-                at_descriptor.set_source_rgb_blend_factor(color_src);
-                at_descriptor.set_destination_rgb_blend_factor(color_dst);
-
-                at_descriptor.set_alpha_blend_operation(alpha_op);
-                at_descriptor.set_source_alpha_blend_factor(alpha_src);
-                at_descriptor.set_destination_alpha_blend_factor(alpha_dst);
-            }
+            set_color_target(at_descriptor, ct, raw_format);
         }
 
         let depth_stencil = match desc.depth_stencil {
             Some(ref ds) => {
                 let raw_format = self.shared.private_caps.map_format(ds.format);
                 let aspects = crate::FormatAspects::from(ds.format);
-                if aspects.contains(crate::FormatAspects::DEPTH) {
+                if aspects.contains(crate::FormatAspects::COLOR) {
+                    return Err(crate::PipelineError::Linkage(
+                        wgt::ShaderStages::VERTEX | wgt::ShaderStages::FRAGMENT,
+                        format!("{:?} is not a depth-stencil format", ds.format),
+                    ));
+                }
+                // Combined formats have to be set for both aspects.
+                if aspects.contains(crate::FormatAspects::DEPTH)
+                    || raw_format == mtl::MTLPixelFormat::Depth32Float_Stencil8
+                {
                     descriptor.set_depth_attachment_pixel_format(raw_format);
                 }
                 if aspects.contains(crate::FormatAspects::STENCIL) {
@@ -832,8 +1823,23 @@ impl crate::Device<super::Api> for super::Device {
                     self.shared.private_caps.max_buffers_per_stage as u64 - 1 - i as u64;
                 let buffer_desc = vertex_descriptor.layouts().object_at(buffer_index).unwrap();
 
-                buffer_desc.set_stride(vb.array_stride);
-                buffer_desc.set_step_function(conv::map_step_mode(vb.step_mode));
+                let (step_function, step_rate) = conv::map_step_mode(vb.step_mode, vb.array_stride);
+                let stride = if vb.array_stride == 0 {
+                    // The stride is unused with constant data, but still has to be
+                    // a non-zero multiple of 4.
+                    let end = vb
+                        .attributes
+                        .iter()
+                        .map(|at| at.offset + at.format.size())
+                        .max()
+                        .unwrap_or(0);
+                    ((end + 3) & !3).max(4)
+                } else {
+                    vb.array_stride
+                };
+                buffer_desc.set_stride(stride);
+                buffer_desc.set_step_function(step_function);
+                buffer_desc.set_step_rate(step_rate);
 
                 for at in vb.attributes {
                     let attribute_desc = vertex_descriptor
@@ -848,6 +1854,26 @@ impl crate::Device<super::Api> for super::Device {
             descriptor.set_vertex_descriptor(Some(vertex_descriptor));
         }
 
+        // Every attachment has to support the sample count, or the pipeline
+        // can't be used with any render pass.
+        let attachment_formats = desc
+            .color_targets
+            .iter()
+            .map(|ct| ct.format)
+            .chain(desc.depth_stencil.as_ref().map(|ds| ds.format));
+        for format in attachment_formats {
+            let mask = self.shared.private_caps.format_sample_count_mask(format);
+            if mask as u32 & desc.multisample.count == 0 {
+                return Err(crate::PipelineError::Linkage(
+                    wgt::ShaderStages::FRAGMENT,
+                    format!(
+                        "{:?} attachments don't support {} samples",
+                        format, desc.multisample.count
+                    ),
+                ));
+            }
+        }
+
         if desc.multisample.count != 1 {
             //TODO: handle sample mask
             descriptor.set_sample_count(desc.multisample.count as u64);
@@ -859,6 +1885,14 @@ impl crate::Device<super::Api> for super::Device {
             descriptor.set_label(name);
         }
 
+        if let Some(ref cache) = self.pipeline_cache {
+            let archive = cache.raw.lock();
+            descriptor.set_binary_archives(&[&archive]);
+            if let Err(e) = archive.add_render_pipeline_functions_with_descriptor(&descriptor) {
+                log::warn!("Render pipeline can't be added to the cache: {}", e);
+            }
+        }
+
         let raw = self
             .shared
             .device
@@ -899,6 +1933,7 @@ impl crate::Device<super::Api> for super::Device {
                 None
             },
             depth_stencil,
+            sample_count: desc.multisample.count,
         })
     }
     unsafe fn destroy_render_pipeline(&self, _pipeline: super::RenderPipeline) {}
@@ -917,6 +1952,23 @@ impl crate::Device<super::Api> for super::Device {
         )?;
         descriptor.set_compute_function(Some(&cs.function));
 
+        // Each threadgroup memory binding is rounded up to 16 bytes.
+        let wg_memory_size = cs
+            .wg_memory_sizes
+            .iter()
+            .map(|&size| (size + 15) & !15)
+            .sum::<u32>();
+        let max_wg_memory_size = self.shared.private_caps.max_total_threadgroup_memory;
+        if wg_memory_size > max_wg_memory_size {
+            return Err(crate::PipelineError::Linkage(
+                wgt::ShaderStages::COMPUTE,
+                format!(
+                    "workgroup memory size {} exceeds the limit of {}",
+                    wg_memory_size, max_wg_memory_size
+                ),
+            ));
+        }
+
         if self.shared.private_caps.supports_mutability {
             Self::set_buffers_mutability(descriptor.buffers().unwrap(), cs.immutable_buffer_mask);
         }
@@ -925,6 +1977,14 @@ impl crate::Device<super::Api> for super::Device {
             descriptor.set_label(name);
         }
 
+        if let Some(ref cache) = self.pipeline_cache {
+            let archive = cache.raw.lock();
+            descriptor.set_binary_archives(&[&archive]);
+            if let Err(e) = archive.add_compute_pipeline_functions_with_descriptor(&descriptor) {
+                log::warn!("Compute pipeline can't be added to the cache: {}", e);
+            }
+        }
+
         let raw = self
             .shared
             .device
@@ -957,6 +2017,15 @@ impl crate::Device<super::Api> for super::Device {
     ) -> DeviceResult<super::QuerySet> {
         match desc.ty {
             wgt::QueryType::Occlusion => {
+                let max_queries = self.shared.private_caps.max_visibility_queries;
+                if desc.count > max_queries {
+                    log::error!(
+                        "Occlusion query set of {} queries exceeds the maximum of {}",
+                        desc.count,
+                        max_queries
+                    );
+                    return Err(crate::DeviceError::OutOfMemory);
+                }
                 let size = desc.count as u64 * crate::QUERY_SIZE;
                 let options = mtl::MTLResourceOptions::empty();
                 //TODO: HazardTrackingModeUntracked
@@ -964,14 +2033,42 @@ impl crate::Device<super::Api> for super::Device {
                 if let Some(label) = desc.label {
                     raw_buffer.set_label(label);
                 }
+                let visibility_mode = if self
+                    .features
+                    .contains(wgt::Features::PRECISE_OCCLUSION_QUERY)
+                {
+                    mtl::MTLVisibilityResultMode::Counting
+                } else {
+                    mtl::MTLVisibilityResultMode::Boolean
+                };
                 Ok(super::QuerySet {
                     raw_buffer,
+                    counter_sample_buffer: None,
                     ty: desc.ty,
+                    visibility_mode,
                 })
             }
-            wgt::QueryType::Timestamp | wgt::QueryType::PipelineStatistics(_) => {
-                Err(crate::DeviceError::OutOfMemory)
+            wgt::QueryType::Timestamp => {
+                if !self.shared.private_caps.supports_timestamp_query {
+                    log::error!("Timestamp queries are not supported");
+                    return Err(crate::DeviceError::OutOfMemory);
+                }
+                let device = self.shared.device.lock();
+                let counter_sample_buffer = create_timestamp_sample_buffer(&device, desc.count)?;
+                let size = desc.count as u64 * crate::QUERY_SIZE;
+                let raw_buffer =
+                    device.new_buffer(size, mtl::MTLResourceOptions::StorageModePrivate);
+                if let Some(label) = desc.label {
+                    raw_buffer.set_label(label);
+                }
+                Ok(super::QuerySet {
+                    raw_buffer,
+                    counter_sample_buffer: Some(counter_sample_buffer),
+                    ty: desc.ty,
+                    visibility_mode: mtl::MTLVisibilityResultMode::Disabled,
+                })
             }
+            wgt::QueryType::PipelineStatistics(_) => Err(crate::DeviceError::OutOfMemory),
         }
     }
     unsafe fn destroy_query_set(&self, _set: super::QuerySet) {}
@@ -986,8 +2083,13 @@ impl crate::Device<super::Api> for super::Device {
     unsafe fn get_fence_value(&self, fence: &super::Fence) -> DeviceResult<crate::FenceValue> {
         let mut max_value = fence.completed_value.load(atomic::Ordering::Acquire);
         for &(value, ref cmd_buf) in fence.pending_command_buffers.iter() {
-            if cmd_buf.status() == mtl::MTLCommandBufferStatus::Completed {
-                max_value = value;
+            match cmd_buf.status() {
+                mtl::MTLCommandBufferStatus::Completed => max_value = value,
+                mtl::MTLCommandBufferStatus::Error => {
+                    super::report_command_buffer_error(cmd_buf);
+                    return Err(crate::DeviceError::Lost);
+                }
+                _ => {}
             }
         }
         Ok(max_value)
@@ -1016,8 +2118,13 @@ impl crate::Device<super::Api> for super::Device {
 
         let start = time::Instant::now();
         loop {
-            if let mtl::MTLCommandBufferStatus::Completed = cmd_buf.status() {
-                return Ok(true);
+            match cmd_buf.status() {
+                mtl::MTLCommandBufferStatus::Completed => return Ok(true),
+                mtl::MTLCommandBufferStatus::Error => {
+                    super::report_command_buffer_error(cmd_buf);
+                    return Err(crate::DeviceError::Lost);
+                }
+                _ => {}
             }
             if start.elapsed().as_millis() >= timeout_ms as u128 {
                 return Ok(false);
@@ -1046,3 +2153,225 @@ impl crate::Device<super::Api> for super::Device {
         shared_capture_manager.stop_capture();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{conv, reads_primitive_index, set_color_target};
+
+    #[test]
+    fn primitive_index_inputs() {
+        let module = naga::front::wgsl::parse_str(
+            "
+            struct FragmentInput {
+                [[builtin(primitive_index)]] index: u32;
+            };
+
+            [[stage(fragment)]]
+            fn direct([[builtin(primitive_index)]] index: u32) -> [[location(0)]] u32 {
+                return index;
+            }
+
+            [[stage(fragment)]]
+            fn nested(input: FragmentInput) -> [[location(0)]] u32 {
+                return input.index;
+            }
+
+            [[stage(fragment)]]
+            fn position([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+                return position;
+            }
+            ",
+        )
+        .unwrap();
+        let reads = module
+            .entry_points
+            .iter()
+            .map(|ep| reads_primitive_index(&module, &ep.function))
+            .collect::<Vec<_>>();
+        assert_eq!(reads, [true, true, false]);
+    }
+
+    #[test]
+    fn stencil_view_formats() {
+        use super::stencil_view_format;
+        use mtl::MTLPixelFormat as Pf;
+
+        assert_eq!(
+            stencil_view_format(Pf::Depth32Float_Stencil8),
+            Some(Pf::X32_Stencil8)
+        );
+        assert_eq!(
+            stencil_view_format(Pf::Depth24Unorm_Stencil8),
+            Some(Pf::X24_Stencil8)
+        );
+        assert_eq!(stencil_view_format(Pf::Depth32Float), None);
+        assert_eq!(stencil_view_format(Pf::RGBA8Unorm), None);
+    }
+
+    #[test]
+    fn vertex_step_functions() {
+        use mtl::MTLVertexStepFunction as Sf;
+        use wgt::VertexStepMode as Sm;
+
+        assert_eq!(conv::map_step_mode(Sm::Vertex, 16), (Sf::PerVertex, 1));
+        assert_eq!(conv::map_step_mode(Sm::Instance, 16), (Sf::PerInstance, 1));
+        assert_eq!(conv::map_step_mode(Sm::Vertex, 0), (Sf::Constant, 0));
+        assert_eq!(conv::map_step_mode(Sm::Instance, 0), (Sf::Constant, 0));
+    }
+
+    #[test]
+    fn independent_blend_states() {
+        let blended = wgt::ColorTargetState {
+            format: wgt::TextureFormat::Rgba8Unorm,
+            blend: Some(wgt::BlendState::ALPHA_BLENDING),
+            write_mask: wgt::ColorWrites::ALL,
+        };
+        let opaque = wgt::ColorTargetState {
+            blend: None,
+            ..blended.clone()
+        };
+
+        let descriptor = mtl::RenderPipelineDescriptor::new();
+        let attachments = descriptor.color_attachments();
+        for (i, ct) in [blended, opaque].iter().enumerate() {
+            let at_descriptor = attachments.object_at(i as u64).unwrap();
+            set_color_target(at_descriptor, ct, mtl::MTLPixelFormat::RGBA8Unorm);
+        }
+
+        let first = attachments.object_at(0).unwrap();
+        assert!(first.is_blending_enabled());
+        assert_eq!(
+            first.source_rgb_blend_factor(),
+            mtl::MTLBlendFactor::SourceAlpha
+        );
+        assert_eq!(
+            first.destination_rgb_blend_factor(),
+            mtl::MTLBlendFactor::OneMinusSourceAlpha
+        );
+        assert!(!attachments.object_at(1).unwrap().is_blending_enabled());
+    }
+
+    #[test]
+    fn only_owned_buffers_purged() {
+        use crate::{Adapter as _, Device as _};
+
+        let adapter = super::super::Adapter::for_tests(|_| {});
+        let open = unsafe { adapter.open(wgt::Features::empty()) }.unwrap();
+        let page_size = unsafe { super::vm_page_size };
+        let desc = crate::BufferDescriptor {
+            label: None,
+            size: page_size as u64,
+            usage: crate::BufferUses::MAP_READ,
+            memory_flags: crate::MemoryFlags::empty(),
+        };
+        let purged = |buffer: super::super::Buffer| {
+            let raw = buffer.raw.clone();
+            unsafe { open.device.destroy_buffer(buffer) };
+            raw.set_purgeable_state(mtl::MTLPurgeableState::KeepCurrent)
+                == mtl::MTLPurgeableState::Empty
+        };
+
+        let owned = unsafe { open.device.create_buffer(&desc) }.unwrap();
+        assert!(purged(owned));
+
+        let layout = std::alloc::Layout::from_size_align(page_size, page_size).unwrap();
+        let memory = std::ptr::NonNull::new(unsafe { std::alloc::alloc_zeroed(layout) }).unwrap();
+        unsafe { memory.as_ptr().write(0xFF) };
+        let host = unsafe {
+            open.device
+                .create_buffer_from_host_memory(&desc, memory, None)
+        }
+        .unwrap();
+        assert!(!purged(host));
+        // The host memory keeps its contents.
+        assert_eq!(unsafe { memory.as_ptr().read() }, 0xFF);
+        unsafe { std::alloc::dealloc(memory.as_ptr(), layout) };
+    }
+
+    #[test]
+    fn sampler_anisotropy_limit() {
+        use crate::Adapter as _;
+        use objc::{msg_send, sel, sel_impl};
+
+        let adapter = super::super::Adapter::for_tests(|_| {});
+        let open = unsafe { adapter.open(wgt::Features::empty()) }.unwrap();
+        let max_anisotropy = |clamp| {
+            let desc = crate::SamplerDescriptor {
+                label: None,
+                address_modes: [wgt::AddressMode::Repeat; 3],
+                mag_filter: wgt::FilterMode::Linear,
+                min_filter: wgt::FilterMode::Linear,
+                mipmap_filter: wgt::FilterMode::Linear,
+                lod_clamp: None,
+                compare: None,
+                anisotropy_clamp: std::num::NonZeroU8::new(clamp),
+                border_color: None,
+            };
+            let descriptor = open.device.sampler_descriptor(&desc).unwrap();
+            let value: mtl::NSUInteger = unsafe { msg_send![descriptor.as_ref(), maxAnisotropy] };
+            value
+        };
+
+        let max = adapter.max_sampler_anisotropy();
+        assert_eq!(max_anisotropy(0), 1);
+        assert_eq!(max_anisotropy(2), 2);
+        assert_eq!(max_anisotropy(max), max as mtl::NSUInteger);
+        // Clamped to the limit instead of failing.
+        assert_eq!(max_anisotropy(max * 2), max as mtl::NSUInteger);
+    }
+
+    #[test]
+    fn argument_buffer_samplers() {
+        use crate::{Adapter as _, Device as _};
+        use objc::{msg_send, sel, sel_impl};
+
+        let desc = crate::SamplerDescriptor {
+            label: None,
+            address_modes: [wgt::AddressMode::Repeat; 3],
+            mag_filter: wgt::FilterMode::Linear,
+            min_filter: wgt::FilterMode::Linear,
+            mipmap_filter: wgt::FilterMode::Linear,
+            lod_clamp: None,
+            compare: None,
+            anisotropy_clamp: None,
+            border_color: None,
+        };
+        for &supported in [false, true].iter() {
+            let adapter = super::super::Adapter::for_tests(|shared| {
+                shared.private_caps.argument_buffer_samplers = supported
+            });
+            let open = unsafe { adapter.open(wgt::Features::empty()) }.unwrap();
+            let descriptor = open.device.sampler_descriptor(&desc).unwrap();
+            let support: objc::runtime::BOOL =
+                unsafe { msg_send![descriptor.as_ref(), supportArgumentBuffers] };
+            assert_eq!(support == objc::runtime::YES, supported);
+        }
+
+        let adapter = super::super::Adapter::for_tests(|_| {});
+        let open = unsafe { adapter.open(wgt::Features::empty()) }.unwrap();
+        assert!(unsafe { open.device.create_sampler(&desc) }.is_ok());
+    }
+
+    #[test]
+    fn external_textures_not_purged() {
+        use crate::{Adapter as _, Device as _};
+
+        let adapter = super::super::Adapter::for_tests(|_| {});
+        let open = unsafe { adapter.open(wgt::Features::empty()) }.unwrap();
+        let descriptor = mtl::TextureDescriptor::new();
+        descriptor.set_pixel_format(mtl::MTLPixelFormat::RGBA8Unorm);
+        descriptor.set_width(4);
+        descriptor.set_height(4);
+        let raw = adapter.as_raw_device().new_texture(&descriptor);
+
+        let texture = unsafe {
+            open.device
+                .texture_from_raw(raw.clone(), wgt::TextureFormat::Rgba8Unorm, None)
+        };
+        unsafe { open.device.destroy_texture(texture) };
+        assert_ne!(
+            raw.set_purgeable_state(mtl::MTLPurgeableState::KeepCurrent),
+            mtl::MTLPurgeableState::Empty
+        );
+    }
+}
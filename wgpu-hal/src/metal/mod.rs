@@ -11,6 +11,18 @@ We put push constants first (if any) in the table, followed by bind group 0
 resources, followed by other bind groups. The vertex buffers are bound at the very
 end of the VS buffer table.
 
+## Blending
+
+Metal doesn't have logic operations, only the regular blend equations, so these
+are never exposed.
+
+## Buffer Layout
+
+Buffer contents follow the WGSL layout rules that naga resolves before generating MSL.
+Members that would get a different alignment in Metal (e.g. `vec3` followed by a
+scalar) are emitted as packed types with explicit padding, so the data the shader reads
+matches what a WGSL-conforming host structure contains.
+
 !*/
 
 mod adapter;
@@ -20,7 +32,7 @@ mod device;
 mod surface;
 
 use std::{
-    iter, ops,
+    fmt, iter, ops,
     ptr::NonNull,
     sync::{atomic, Arc},
     thread,
@@ -28,7 +40,8 @@ use std::{
 
 use arrayvec::ArrayVec;
 use foreign_types::ForeignTypeRef as _;
-use parking_lot::Mutex;
+use objc::{msg_send, sel, sel_impl};
+use parking_lot::{Condvar, Mutex};
 
 #[derive(Clone)]
 pub struct Api;
@@ -63,19 +76,38 @@ impl crate::Api for Api {
 
 pub struct Instance {
     managed_metal_layer_delegate: surface::HalManagedMetalLayerDelegate,
+    flags: crate::InstanceFlags,
 }
 
 impl Instance {
     pub fn create_surface_from_layer(&self, layer: &mtl::MetalLayerRef) -> Surface {
         unsafe { Surface::from_layer(layer) }
     }
+
+    /// Exposes an adapter for `device`, which may come from another framework.
+    ///
+    /// The capabilities are queried from the device as for the enumerated ones,
+    /// and opening the adapter creates its own command queue on the device.
+    pub fn expose_adapter(&self, device: mtl::Device) -> crate::ExposedAdapter<Api> {
+        let shared = AdapterShared::new(device, self.flags);
+        let features = shared.private_caps.features();
+        let capabilities = shared.private_caps.capabilities();
+        let adapter = Adapter::new(Arc::new(shared));
+        crate::ExposedAdapter {
+            info: adapter.info(),
+            features,
+            capabilities,
+            adapter,
+        }
+    }
 }
 
 impl crate::Instance<Api> for Instance {
-    unsafe fn init(_desc: &crate::InstanceDescriptor) -> Result<Self, crate::InstanceError> {
+    unsafe fn init(desc: &crate::InstanceDescriptor) -> Result<Self, crate::InstanceError> {
         //TODO: enable `METAL_DEVICE_WRAPPER_TYPE` environment based on the flags?
         Ok(Instance {
             managed_metal_layer_delegate: surface::HalManagedMetalLayerDelegate::new(),
+            flags: desc.flags,
         })
     }
 
@@ -106,26 +138,7 @@ impl crate::Instance<Api> for Instance {
         let devices = mtl::Device::all();
         let mut adapters: Vec<crate::ExposedAdapter<Api>> = devices
             .into_iter()
-            .map(|dev| {
-                let name = dev.name().into();
-                let shared = AdapterShared::new(dev);
-                crate::ExposedAdapter {
-                    info: wgt::AdapterInfo {
-                        name,
-                        vendor: 0,
-                        device: 0,
-                        device_type: if shared.private_caps.low_power {
-                            wgt::DeviceType::IntegratedGpu
-                        } else {
-                            wgt::DeviceType::DiscreteGpu
-                        },
-                        backend: wgt::Backend::Metal,
-                    },
-                    features: shared.private_caps.features(),
-                    capabilities: shared.private_caps.capabilities(),
-                    adapter: Adapter::new(Arc::new(shared)),
-                }
-            })
+            .map(|dev| self.expose_adapter(dev))
             .collect();
         adapters.sort_by_key(|ad| {
             (
@@ -137,14 +150,69 @@ impl crate::Instance<Api> for Instance {
     }
 }
 
+#[repr(C)]
+struct MachTimebaseInfo {
+    numer: u32,
+    denom: u32,
+}
+
+extern "C" {
+    fn mach_timebase_info(info: *mut MachTimebaseInfo) -> i32;
+}
+
+/// Samples the CPU and GPU timestamps at the same moment.
+///
+/// The CPU timestamp is in `mach_absolute_time` units.
+fn sample_timestamps(device: &mtl::DeviceRef) -> (u64, u64) {
+    let mut cpu_timestamp = 0u64;
+    let mut gpu_timestamp = 0u64;
+    unsafe {
+        let () = msg_send![
+            device,
+            sampleTimestamps: &mut cpu_timestamp
+            gpuTimestamp: &mut gpu_timestamp
+        ];
+    }
+    (cpu_timestamp, gpu_timestamp)
+}
+
+/// Returns the `timestamp` counter set of the device, if it has one.
+///
+/// Needs counter sampling support.
+fn timestamp_counter_set(device: &mtl::DeviceRef) -> Option<NonNull<objc::runtime::Object>> {
+    unsafe {
+        let counter_sets: *mut objc::runtime::Object = msg_send![device, counterSets];
+        let num_sets: mtl::NSUInteger = if counter_sets.is_null() {
+            0
+        } else {
+            msg_send![counter_sets, count]
+        };
+        (0..num_sets)
+            .map(|index| -> *mut objc::runtime::Object {
+                msg_send![counter_sets, objectAtIndex: index]
+            })
+            .find(|&set| {
+                let name: *mut objc::runtime::Object = msg_send![set, name];
+                let name: *const std::os::raw::c_char = msg_send![name, UTF8String];
+                std::ffi::CStr::from_ptr(name).to_bytes() == b"timestamp"
+            })
+            .and_then(NonNull::new)
+    }
+}
+
 #[derive(Clone, Debug)]
 struct PrivateCapabilities {
     family_check: bool,
     msl_version: mtl::MTLLanguageVersion,
-    exposed_queues: usize,
     read_write_texture_tier: mtl::MTLReadWriteTextureTier,
     resource_heaps: bool,
+    /// Textures with render target usage can be placed in a heap. Where this is
+    /// not set, they need dedicated allocations even if `resource_heaps` is.
+    render_target_heaps: bool,
     argument_buffers: bool,
+    /// Samplers are created with `supportArgumentBuffers`, so that they can
+    /// be encoded into argument buffers.
+    argument_buffer_samplers: bool,
     shared_textures: bool,
     mutable_comparison_samplers: bool,
     sampler_clamp_to_border: bool,
@@ -157,8 +225,13 @@ struct PrivateCapabilities {
     layered_rendering: bool,
     function_specialization: bool,
     depth_clip_mode: bool,
+    depth_bias_clamp: bool,
+    counting_occlusion_query: bool,
     texture_cube_array: bool,
     format_depth24_stencil8: bool,
+    /// `Stencil8` has a dedicated pixel format. If not, it's backed by
+    /// `Depth32Float_Stencil8`, whose depth has to be attached alongside.
+    format_stencil8: bool,
     format_depth32_stencil8_filter: bool,
     format_depth32_stencil8_none: bool,
     format_min_srgb_channels: u8,
@@ -169,6 +242,7 @@ struct PrivateCapabilities {
     format_any8_unorm_srgb_all: bool,
     format_any8_unorm_srgb_no_write: bool,
     format_any8_snorm_all: bool,
+    format_r8unorm_write: bool,
     format_r16_norm_all: bool,
     format_r32_all: bool,
     format_r32_no_write: bool,
@@ -206,24 +280,86 @@ struct PrivateCapabilities {
     max_samplers_per_stage: ResourceIndex,
     buffer_alignment: u64,
     max_buffer_size: u64,
+    max_visibility_queries: u32,
     max_texture_size: u64,
+    /// Maximum width of 1D textures, which has its own row in the feature set tables.
+    max_texture_1d_size: u64,
     max_texture_3d_size: u64,
     max_texture_layers: u64,
     max_fragment_input_components: u64,
     max_color_render_targets: u8,
+    max_threadgroups_per_dimension: u32,
+    /// Maximum number of threads along each dimension of a threadgroup.
+    max_threads_per_threadgroup: [u32; 3],
+    /// Maximum number of threads in a threadgroup, across all dimensions.
+    max_total_threads_per_threadgroup: u32,
     max_total_threadgroup_memory: u32,
     sample_count_mask: u8,
     supports_debug_markers: bool,
     supports_binary_archives: bool,
+    /// Indirect command buffers can encode many draws executed by one call.
+    supports_indirect_command_buffers: bool,
     supports_capture_manager: bool,
+    /// Captures can be written to GPU trace documents, instead of only being
+    /// shown by an attached Xcode.
+    supports_capture_to_file: bool,
+    /// The device reports its allocated size and recommended maximum working set size.
+    supports_working_set_size: bool,
     can_set_maximum_drawables_count: bool,
     can_set_display_sync: bool,
+    /// Screens report their extended dynamic range headroom, so `Rgba16Float`
+    /// surfaces can be advertised on the displays supporting EDR.
+    supports_edr: bool,
+    /// Fragment shaders can read `[[primitive_id]]`. It needs MSL 2.2 on macOS,
+    /// while iOS only has it since MSL 2.3, which isn't requested there yet.
+    supports_primitive_index: bool,
+    /// `half` can be used for arithmetic, in buffers and for stage I/O.
+    supports_shader_f16: bool,
+    /// Fragment shader resources can be given `[[raster_order_group(n)]]`,
+    /// which needs MSL 2.0.
+    supports_raster_order_groups: bool,
+    /// SIMD-group functions, like `simd_shuffle`, `simd_sum` and `simd_ballot`,
+    /// can be used in compute kernels along with `[[thread_index_in_simdgroup]]`.
+    supports_simd_group_functions: bool,
+    /// Thread execution width of an empty kernel, or `None` if it couldn't be
+    /// compiled.
+    simd_width: Option<u32>,
     can_set_next_drawable_timeout: bool,
     supports_arrays_of_textures: bool,
     supports_arrays_of_textures_write: bool,
     supports_mutability: bool,
+    supports_registry_id: bool,
+    supports_memory_barriers: bool,
+    supports_counter_sampling: bool,
+    /// Timestamps can be sampled between the commands of an encoder.
+    supports_timestamp_query: bool,
+    supports_command_buffer_error_options: bool,
+    supports_shared_event: bool,
+    /// Buffers can be viewed as 1D textures of type `texture_buffer`.
+    supports_texture_buffer: bool,
+    non_uniform_derivatives: bool,
+    tile_based_rendering: bool,
+    /// Shader stages that can write to storage buffers.
+    read_write_buffer_stages: wgt::ShaderStages,
+    /// Highest count accepted by vertex amplification, 1 if unsupported.
+    /// Anything above 2 goes beyond the stereo case.
+    max_vertex_amplification_count: u32,
+    /// Viewports that can be set at once, 1 if only a single one is supported.
+    max_viewports: u32,
+    /// Highest `maxAnisotropy` of samplers. Higher clamps are lowered to it.
+    max_sampler_anisotropy: u8,
 }
 
+bitflags::bitflags!(
+    /// Known issues of a device that the backend works around.
+    pub struct Disabilities: u32 {
+        /// Near depth is not respected properly on some Intel GPUs.
+        const BROKEN_VIEWPORT_NEAR_DEPTH = 1 << 0;
+        /// Multi-target clears don't appear to work properly on Intel GPUs.
+        const BROKEN_LAYERED_CLEAR_IMAGE = 1 << 1;
+    }
+);
+
 #[derive(Clone, Debug)]
 struct PrivateDisabilities {
     /// Near depth is not respected properly on some Intel GPUs.
@@ -235,6 +371,9 @@ struct PrivateDisabilities {
 #[derive(Debug, Default)]
 struct Settings {
     retain_command_buffer_references: bool,
+    /// Record the execution status of each encoder in the command buffers,
+    /// so that GPU-side failures can be attributed to a specific pass.
+    gpu_validation: bool,
 }
 
 // Using max copyable texture row
@@ -248,16 +387,25 @@ struct AdapterShared {
     private_caps: PrivateCapabilities,
     settings: Settings,
     zero_buffer: mtl::Buffer,
+    /// Set once a buffer without hazard tracking is created, after which
+    /// encoders start signaling `CommandEncoder::untracked_fence`.
+    untracked_buffers: atomic::AtomicBool,
 }
 
 unsafe impl Send for AdapterShared {}
 unsafe impl Sync for AdapterShared {}
 
 impl AdapterShared {
-    fn new(device: mtl::Device) -> Self {
+    fn new(device: mtl::Device, flags: crate::InstanceFlags) -> Self {
         let private_caps = PrivateCapabilities::new(&device);
         log::debug!("{:#?}", private_caps);
 
+        let settings = Settings {
+            gpu_validation: flags.contains(crate::InstanceFlags::VALIDATION)
+                && private_caps.supports_command_buffer_error_options,
+            ..Settings::default()
+        };
+
         // buffers created this way are zero initialized
         // see https://developer.apple.com/documentation/metal/mtldevice/1433375-newbufferwithlength?language=objc
         let zero_buffer = device.new_buffer(
@@ -268,28 +416,140 @@ impl AdapterShared {
 
         Self {
             disabilities: PrivateDisabilities::new(&device),
-            private_caps: PrivateCapabilities::new(&device),
+            private_caps,
             device: Mutex::new(device),
-            settings: Settings::default(),
+            settings,
             zero_buffer,
+            untracked_buffers: atomic::AtomicBool::new(false),
         }
     }
+
+    /// Shared state of the system default device, for the tests.
+    ///
+    /// Metal tests only run on Apple platforms, which always have a device,
+    /// so they fail without one instead of passing silently.
+    #[cfg(test)]
+    fn for_tests() -> Self {
+        let device = mtl::Device::system_default().expect("no Metal device");
+        Self::new(device, crate::InstanceFlags::empty())
+    }
 }
 
 pub struct Adapter {
     shared: Arc<AdapterShared>,
 }
 
+#[cfg(test)]
+impl Adapter {
+    /// Creates an adapter for the system default device, after letting
+    /// `configure` override its capabilities and disabilities.
+    fn for_tests(configure: impl FnOnce(&mut AdapterShared)) -> Self {
+        let mut shared = AdapterShared::for_tests();
+        configure(&mut shared);
+        Self::new(Arc::new(shared))
+    }
+}
+
 pub struct Queue {
     raw: Arc<Mutex<mtl::CommandQueue>>,
+    /// CPU and GPU timestamps sampled together when the queue was created,
+    /// if the device supports it. They give the GPU timestamp period.
+    timestamp_origin: Option<(u64, u64)>,
+    frames_in_flight: Arc<FramesInFlight>,
+    /// Set by `Queue::set_max_frames_in_flight`, follows the drawable count
+    /// of the presented surface otherwise.
+    max_frames_in_flight: Option<u32>,
 }
 
 unsafe impl Send for Queue {}
 unsafe impl Sync for Queue {}
 
+impl Queue {
+    /// Caps how many presented frames the GPU can be working on at once.
+    ///
+    /// Presenting a frame blocks until the GPU is done with enough of the
+    /// previous ones to stay within the limit, which keeps the CPU from getting
+    /// ahead and adding latency. By default, or with `None`, the limit is the
+    /// maximum drawable count of the presented surface, which follows the
+    /// configured `swap_chain_size`. A limit of 1 waits for each frame to be
+    /// done before presenting the next one.
+    pub fn set_max_frames_in_flight(&mut self, count: Option<u32>) {
+        self.max_frames_in_flight = count.map(|count| count.max(1));
+    }
+}
+
+/// Number of presented frames the GPU isn't done with, decremented by the
+/// completion handlers of their command buffers.
+#[derive(Default)]
+struct FramesInFlight {
+    count: Mutex<u32>,
+    completed: Condvar,
+}
+
+impl FramesInFlight {
+    /// Blocks until fewer than `max` frames are in flight, then adds one.
+    fn begin(&self, max: u32) {
+        let mut count = self.count.lock();
+        while *count >= max {
+            self.completed.wait(&mut count);
+        }
+        *count += 1;
+    }
+
+    fn end(&self) {
+        *self.count.lock() -= 1;
+        self.completed.notify_one();
+    }
+}
+
+/// Error returned by `Device::start_capture_to_file`.
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
+pub enum CaptureError {
+    #[error("GPU captures to a file are not supported")]
+    Unsupported,
+    #[error("capture path {0:?} is not valid UTF-8")]
+    InvalidPath(std::path::PathBuf),
+    #[error("GPU capture failed to start: {0}")]
+    Failed(String),
+}
+
+/// Error returned by `Adapter::open_with_queues`.
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
+pub enum QueueCountError {
+    #[error("at least one queue has to be opened")]
+    Zero,
+    #[error("requested {requested} queues, but the adapter exposes at most {exposed}")]
+    TooMany { requested: usize, exposed: usize },
+}
+
+/// Called with the size allocated by the device and its recommended maximum
+/// working set size, in bytes, when the former goes past the threshold given to
+/// `Device::set_memory_pressure_callback`.
+pub type MemoryPressureCallback = Box<dyn Fn(u64, u64) + Send + Sync>;
+
+struct MemoryPressure {
+    threshold: f32,
+    callback: MemoryPressureCallback,
+    /// The allocated size was past the threshold at the last check, so the
+    /// callback isn't called again until it goes back below.
+    exceeded: atomic::AtomicBool,
+}
+
+/// Binary archive the compiled pipeline states are recorded into.
+struct PipelineCache {
+    raw: Mutex<mtl::BinaryArchive>,
+}
+
+unsafe impl Send for PipelineCache {}
+unsafe impl Sync for PipelineCache {}
+
 pub struct Device {
     shared: Arc<AdapterShared>,
     features: wgt::Features,
+    /// Language version used for shader compilation.
+    msl_version: mtl::MTLLanguageVersion,
+    memory_pressure: Option<MemoryPressure>,
+    pipeline_cache: Option<PipelineCache>,
 }
 
 pub struct Surface {
@@ -298,10 +558,22 @@ pub struct Surface {
     swapchain_format: wgt::TextureFormat,
     raw_swapchain_format: mtl::MTLPixelFormat,
     extent: wgt::Extent3d,
+    /// Last size of the view seen on the main thread or set by `configure`,
+    /// for the queries made from other threads.
+    cached_extent: Mutex<Option<wgt::Extent3d>>,
+    /// Whether the screen showed EDR support the last time it was queried
+    /// on the main thread.
+    cached_edr: atomic::AtomicBool,
     main_thread_id: thread::ThreadId,
     // Useful for UI-intensive applications that are sensitive to
     // window resizing.
     pub present_with_transaction: bool,
+    /// Makes `Queue::present` wait until the frame is done on the GPU before
+    /// returning, so that frames complete deterministically one at a time.
+    ///
+    /// This serializes the CPU and the GPU: it's meant for golden-image tests
+    /// and frame-by-frame tools, not for interactive applications.
+    pub present_synchronously: bool,
 }
 
 unsafe impl Send for Surface {}
@@ -312,6 +584,7 @@ pub struct SurfaceTexture {
     texture: Texture,
     drawable: mtl::MetalDrawable,
     present_with_transaction: bool,
+    present_synchronously: bool,
 }
 
 impl std::borrow::Borrow<Texture> for SurfaceTexture {
@@ -373,14 +646,27 @@ impl crate::Queue<Api> for Queue {
     }
     unsafe fn present(
         &mut self,
-        _surface: &mut Surface,
+        surface: &mut Surface,
         texture: SurfaceTexture,
     ) -> Result<(), crate::SurfaceError> {
+        let max_frames_in_flight = match self.max_frames_in_flight {
+            Some(count) => count,
+            None => (surface.render_layer.lock().maximum_drawable_count() as u32).max(1),
+        };
+        self.frames_in_flight.begin(max_frames_in_flight);
+
         let queue = &self.raw.lock();
         objc::rc::autoreleasepool(|| {
             let command_buffer = queue.new_command_buffer();
             command_buffer.set_label("_Present");
 
+            let frames_in_flight = Arc::clone(&self.frames_in_flight);
+            let block = block::ConcreteBlock::new(move |_cmd_buf| {
+                frames_in_flight.end();
+            })
+            .copy();
+            command_buffer.add_completed_handler(&block);
+
             // https://developer.apple.com/documentation/quartzcore/cametallayer/1478157-presentswithtransaction?language=objc
             if !texture.present_with_transaction {
                 command_buffer.present_drawable(&texture.drawable);
@@ -392,13 +678,29 @@ impl crate::Queue<Api> for Queue {
                 command_buffer.wait_until_scheduled();
                 texture.drawable.present();
             }
+            if texture.present_synchronously {
+                command_buffer.wait_until_completed();
+            }
         });
         Ok(())
     }
 
     unsafe fn get_timestamp_period(&self) -> f32 {
-        // TODO: This is hard, see https://github.com/gpuweb/gpuweb/issues/1325
-        1.0
+        // GPU timestamps have no defined unit, see https://github.com/gpuweb/gpuweb/issues/1325.
+        // Compare how much they advanced since the queue was created with the CPU time.
+        let (cpu_start, gpu_start) = match self.timestamp_origin {
+            Some(origin) => origin,
+            None => return 1.0,
+        };
+        let (cpu_end, gpu_end) = sample_timestamps(self.raw.lock().device());
+        if gpu_end <= gpu_start {
+            return 1.0;
+        }
+        let mut timebase = MachTimebaseInfo { numer: 0, denom: 0 };
+        mach_timebase_info(&mut timebase);
+        let cpu_nanos =
+            (cpu_end - cpu_start) as f64 * timebase.numer as f64 / timebase.denom as f64;
+        (cpu_nanos / (gpu_end - gpu_start) as f64) as f32
     }
 }
 
@@ -407,6 +709,9 @@ pub struct Buffer {
     raw: mtl::Buffer,
     size: wgt::BufferAddress,
     options: mtl::MTLResourceOptions,
+    /// The memory was allocated by the device for this buffer alone, so it can
+    /// be discarded when the buffer is destroyed.
+    owned: bool,
 }
 
 unsafe impl Send for Buffer {}
@@ -418,6 +723,15 @@ impl Buffer {
     }
 }
 
+/// Called when an external resource wrapped by the device is no longer used.
+struct DropGuard(Box<dyn FnOnce() + Send>);
+
+impl fmt::Debug for DropGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DropGuard")
+    }
+}
+
 #[derive(Debug)]
 pub struct Texture {
     raw: mtl::Texture,
@@ -427,11 +741,44 @@ pub struct Texture {
     array_layers: u32,
     mip_levels: u32,
     copy_size: crate::CopyExtent,
+    drop_guard: Option<DropGuard>,
+    /// The memory was allocated by the device for this texture alone, so it
+    /// can be discarded when the texture is destroyed.
+    owned: bool,
 }
 
 unsafe impl Send for Texture {}
 unsafe impl Sync for Texture {}
 
+/// Memory in private storage that textures and buffers can be placed in.
+///
+/// Metal doesn't track hazards of the resources placed in a heap, so the
+/// encoders accessing them have to be ordered with `CommandEncoder::use_heap`.
+/// Resources made aliasable let the memory they occupy be reused by the ones
+/// placed afterwards.
+#[derive(Debug)]
+pub struct Heap {
+    raw: mtl::Heap,
+    /// Updated by every encoder using the heap, and waited for by the next one.
+    fence: mtl::Fence,
+}
+
+unsafe impl Send for Heap {}
+unsafe impl Sync for Heap {}
+
+impl Heap {
+    /// Returns the size of the heap, in bytes.
+    pub fn size(&self) -> wgt::BufferAddress {
+        self.raw.size()
+    }
+
+    /// Returns the size of the memory used by the resources placed in the heap,
+    /// not counting the aliasable ones.
+    pub fn used_size(&self) -> wgt::BufferAddress {
+        self.raw.used_size()
+    }
+}
+
 #[derive(Debug)]
 pub struct TextureView {
     raw: mtl::Texture,
@@ -646,6 +993,7 @@ pub struct RenderPipeline {
     raw_cull_mode: mtl::MTLCullMode,
     raw_depth_clip_mode: Option<mtl::MTLDepthClipMode>,
     depth_stencil: Option<(mtl::DepthStencilState, wgt::DepthBiasState)>,
+    sample_count: u32,
 }
 
 unsafe impl Send for RenderPipeline {}
@@ -663,15 +1011,102 @@ pub struct ComputePipeline {
 unsafe impl Send for ComputePipeline {}
 unsafe impl Sync for ComputePipeline {}
 
+impl ComputePipeline {
+    /// Returns the number of threads executed together, in lockstep.
+    pub fn thread_execution_width(&self) -> u32 {
+        self.raw.thread_execution_width() as u32
+    }
+
+    /// Returns the maximum number of threads in a workgroup of this pipeline,
+    /// which can be lower than the device limit for shaders using many registers.
+    pub fn max_total_threads_per_threadgroup(&self) -> u32 {
+        self.raw.max_total_threads_per_threadgroup() as u32
+    }
+
+    /// Returns the recommended `[width, height]` of the workgroups processing
+    /// an image with this pipeline.
+    ///
+    /// The width is the thread execution width, so that every row fills whole
+    /// SIMD-groups accessing adjacent texels, and the height is the number of
+    /// such rows fitting in `max_total_threads_per_threadgroup`. The grid is
+    /// then the image size divided by the tile size, rounded up.
+    pub fn preferred_tile_size(&self) -> [u32; 2] {
+        preferred_tile_size(
+            self.thread_execution_width(),
+            self.max_total_threads_per_threadgroup(),
+        )
+    }
+}
+
+fn preferred_tile_size(execution_width: u32, max_threads: u32) -> [u32; 2] {
+    let width = execution_width.min(max_threads).max(1);
+    [width, (max_threads / width).max(1)]
+}
+
+/// Retained `MTLCounterSampleBuffer`, which isn't wrapped by `metal-rs`.
+#[derive(Debug)]
+struct CounterSampleBuffer(NonNull<objc::runtime::Object>);
+
+impl CounterSampleBuffer {
+    fn as_ptr(&self) -> *mut objc::runtime::Object {
+        self.0.as_ptr()
+    }
+}
+
+impl Drop for CounterSampleBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            let () = msg_send![self.as_ptr(), release];
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct QuerySet {
+    /// Query results: the visibility result buffer for occlusion queries,
+    /// or the resolved counters for timestamp queries.
     raw_buffer: mtl::Buffer,
+    /// Samples of the timestamp queries, resolved into `raw_buffer`.
+    counter_sample_buffer: Option<CounterSampleBuffer>,
     ty: wgt::QueryType,
+    /// Visibility mode used by the occlusion queries of this set.
+    visibility_mode: mtl::MTLVisibilityResultMode,
 }
 
 unsafe impl Send for QuerySet {}
 unsafe impl Sync for QuerySet {}
 
+/// Retained `MTLSharedEventHandle`, which can be sent to another process
+/// (e.g. over XPC) to import the shared event there.
+#[derive(Debug)]
+pub struct SharedEventHandle(NonNull<objc::runtime::Object>);
+
+unsafe impl Send for SharedEventHandle {}
+unsafe impl Sync for SharedEventHandle {}
+
+impl SharedEventHandle {
+    /// Takes ownership of a `MTLSharedEventHandle` received from another process.
+    ///
+    /// # Safety
+    ///
+    /// `raw` has to be a retained `MTLSharedEventHandle`.
+    pub unsafe fn from_raw(raw: NonNull<objc::runtime::Object>) -> Self {
+        Self(raw)
+    }
+
+    pub fn as_ptr(&self) -> *mut objc::runtime::Object {
+        self.0.as_ptr()
+    }
+}
+
+impl Drop for SharedEventHandle {
+    fn drop(&mut self) {
+        unsafe {
+            let () = msg_send![self.as_ptr(), release];
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Fence {
     completed_value: Arc<atomic::AtomicU64>,
@@ -700,6 +1135,32 @@ impl Fence {
     }
 }
 
+/// Reports the error of a command buffer that failed on the GPU.
+///
+/// With `Settings::gpu_validation`, the error description includes the
+/// execution status of every encoder in the command buffer.
+fn report_command_buffer_error(cmd_buf: &mtl::CommandBufferRef) {
+    let description = unsafe {
+        let error: *mut objc::runtime::Object = msg_send![cmd_buf, error];
+        if error.is_null() {
+            None
+        } else {
+            let text: *mut objc::runtime::Object = msg_send![error, localizedDescription];
+            let bytes: *const std::os::raw::c_char = msg_send![text, UTF8String];
+            Some(
+                std::ffi::CStr::from_ptr(bytes)
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        }
+    };
+    log::error!(
+        "Command buffer {:?} failed: {}",
+        cmd_buf.label(),
+        description.as_deref().unwrap_or("unknown error")
+    );
+}
+
 struct IndexState {
     buffer_ptr: BufferPtr,
     offset: wgt::BufferAddress,
@@ -717,11 +1178,22 @@ struct CommandState {
     render: Option<mtl::RenderCommandEncoder>,
     compute: Option<mtl::ComputeCommandEncoder>,
     raw_primitive_type: mtl::MTLPrimitiveType,
+    /// Sample count of the current render pass.
+    sample_count: u32,
     index: Option<IndexState>,
     raw_wg_size: mtl::MTLSize,
     stage_infos: MultiStageData<PipelineStageInfo>,
     storage_buffer_length_map: fxhash::FxHashMap<naga::ResourceBinding, wgt::BufferSize>,
     work_group_memory_sizes: Vec<u32>,
+    /// The next encoder has to wait for `CommandEncoder::untracked_fence`.
+    pending_untracked_barrier: bool,
+    /// Fences of the heaps used by the current encoder, updated when it ends.
+    heap_fences: Vec<mtl::Fence>,
+    /// Visibility result buffer and offset provided for the next render pass.
+    pending_visibility_result: Option<(mtl::Buffer, wgt::BufferAddress)>,
+    /// Bytes of the visibility result buffer available to the occlusion
+    /// queries of the current render pass, if provided by the user.
+    visibility_result_range: Option<ops::Range<wgt::BufferAddress>>,
 }
 
 pub struct CommandEncoder {
@@ -730,6 +1202,9 @@ pub struct CommandEncoder {
     raw_cmd_buf: Option<mtl::CommandBuffer>,
     state: CommandState,
     temp: Temp,
+    /// Updated at the end of each encoder, so that barriers on untracked
+    /// buffers can make the following encoders wait for it.
+    untracked_fence: Option<mtl::Fence>,
 }
 
 unsafe impl Send for CommandEncoder {}
@@ -741,3 +1216,16 @@ pub struct CommandBuffer {
 
 unsafe impl Send for CommandBuffer {}
 unsafe impl Sync for CommandBuffer {}
+
+#[cfg(test)]
+mod tests {
+    use super::preferred_tile_size;
+
+    #[test]
+    fn compute_tile_sizes() {
+        assert_eq!(preferred_tile_size(32, 1024), [32, 32]);
+        assert_eq!(preferred_tile_size(32, 896), [32, 28]);
+        assert_eq!(preferred_tile_size(64, 256), [64, 4]);
+        assert_eq!(preferred_tile_size(32, 16), [16, 1]);
+    }
+}
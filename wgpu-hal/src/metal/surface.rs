@@ -1,4 +1,10 @@
-use std::{mem, os::raw::c_void, ptr::NonNull, sync::Once, thread};
+use std::{
+    mem,
+    os::raw::c_void,
+    ptr::NonNull,
+    sync::{atomic, Once},
+    thread,
+};
 
 use core_graphics_types::{
     base::CGFloat,
@@ -9,7 +15,7 @@ use objc::{
     declare::ClassDecl,
     msg_send,
     rc::autoreleasepool,
-    runtime::{Class, Object, Sel, BOOL, YES},
+    runtime::{Class, Object, Sel, BOOL, NO, YES},
     sel, sel_impl,
 };
 use parking_lot::Mutex;
@@ -63,8 +69,11 @@ impl super::Surface {
             swapchain_format: wgt::TextureFormat::Bgra8UnormSrgb, // no value invalid, pick something not too far-fetched
             raw_swapchain_format: mtl::MTLPixelFormat::Invalid,
             extent: wgt::Extent3d::default(),
+            cached_extent: Mutex::new(None),
+            cached_edr: atomic::AtomicBool::new(false),
             main_thread_id: thread::current().id(),
             present_with_transaction: false,
+            present_synchronously: false,
         }
     }
 
@@ -167,7 +176,65 @@ impl super::Surface {
         Self::new(None, layer.to_owned())
     }
 
-    pub(super) fn dimensions(&self) -> wgt::Extent3d {
+    /// Returns the current size of the view, which is only available on the main thread.
+    ///
+    /// Other threads get the last size seen on the main thread or configured,
+    /// which may lag one frame behind a resize in flight.
+    pub(super) fn current_extent(&self) -> Option<wgt::Extent3d> {
+        if self.main_thread_id == thread::current().id() {
+            let extent = self.dimensions();
+            *self.cached_extent.lock() = Some(extent);
+            Some(extent)
+        } else {
+            *self.cached_extent.lock()
+        }
+    }
+
+    /// Returns whether the screen showing the surface supports extended dynamic range.
+    ///
+    /// Like the extent, this is only queried on the main thread, and other
+    /// threads get the last known answer.
+    pub(super) fn supports_edr(&self) -> bool {
+        if self.main_thread_id != thread::current().id() {
+            return self.cached_edr.load(atomic::Ordering::Relaxed);
+        }
+        let supported = self.edr_headroom() > 1.0;
+        self.cached_edr.store(supported, atomic::Ordering::Relaxed);
+        supported
+    }
+
+    /// Returns the highest color component value the screen can show.
+    #[cfg(target_os = "macos")]
+    fn edr_headroom(&self) -> CGFloat {
+        unsafe {
+            let screen: *mut Object = match self.view {
+                Some(view) => {
+                    let window: *mut Object = msg_send![view.as_ptr(), window];
+                    if window.is_null() {
+                        msg_send![class!(NSScreen), mainScreen]
+                    } else {
+                        msg_send![window, screen]
+                    }
+                }
+                None => msg_send![class!(NSScreen), mainScreen],
+            };
+            if screen.is_null() {
+                1.0
+            } else {
+                msg_send![
+                    screen,
+                    maximumPotentialExtendedDynamicRangeColorComponentValue
+                ]
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn edr_headroom(&self) -> CGFloat {
+        1.0
+    }
+
+    fn dimensions(&self) -> wgt::Extent3d {
         let (size, scale): (CGSize, CGFloat) = match self.view {
             Some(view) if !cfg!(target_os = "macos") => unsafe {
                 let bounds: CGRect = msg_send![view.as_ptr(), bounds];
@@ -211,13 +278,25 @@ impl crate::Surface<super::Api> for super::Surface {
         log::info!("build swapchain {:?}", config);
 
         let caps = &device.shared.private_caps;
+        if !caps.surface_usage(config.format).contains(config.usage) {
+            log::error!("Surface usage {:?} is not supported", config.usage);
+            return Err(crate::SurfaceError::Other("unsupported surface usage"));
+        }
         self.swapchain_format = config.format;
         self.raw_swapchain_format = caps.map_format(config.format);
-        self.extent = config.extent;
 
         let render_layer = self.render_layer.lock();
+        // Framebuffer-only drawables let the compositor skip work when presenting,
+        // but they can't be copied from or to, nor accessed by shaders.
         let framebuffer_only = config.usage == crate::TextureUses::COLOR_TARGET;
-        let display_sync = config.present_mode != wgt::PresentMode::Immediate;
+        if !framebuffer_only {
+            log::info!(
+                "Surface usage {:?} disables the framebuffer-only optimization",
+                config.usage
+            );
+        }
+        // Headless devices have no vblank to wait for.
+        let display_sync = !caps.headless && config.present_mode != wgt::PresentMode::Immediate;
         let drawable_size = CGSize::new(config.extent.width as f64, config.extent.height as f64);
 
         match config.composite_alpha_mode {
@@ -243,11 +322,41 @@ impl crate::Surface<super::Api> for super::Surface {
         render_layer.set_pixel_format(self.raw_swapchain_format);
         render_layer.set_framebuffer_only(framebuffer_only);
         render_layer.set_presents_with_transaction(self.present_with_transaction);
+        if caps.supports_edr {
+            let wants_edr = if config.format == wgt::TextureFormat::Rgba16Float {
+                YES
+            } else {
+                NO
+            };
+            let () = msg_send![*render_layer, setWantsExtendedDynamicRangeContent: wants_edr];
+        }
 
+        // Mailbox needs a third drawable to render into while the other two
+        // are queued for display.
+        let drawable_count = if config.present_mode == wgt::PresentMode::Mailbox {
+            config.swap_chain_size.max(3)
+        } else {
+            config.swap_chain_size
+        };
         // this gets ignored on iOS for certain OS/device combinations (iphone5s iOS 10.3)
-        let () = msg_send![*render_layer, setMaximumDrawableCount: config.swap_chain_size as u64];
+        let () = msg_send![*render_layer, setMaximumDrawableCount: drawable_count as u64];
 
         render_layer.set_drawable_size(drawable_size);
+        // The layer may adjust the requested size, so track the one it actually uses.
+        let actual_size = render_layer.drawable_size();
+        self.extent = wgt::Extent3d {
+            width: actual_size.width as u32,
+            height: actual_size.height as u32,
+            depth_or_array_layers: 1,
+        };
+        if self.extent != config.extent {
+            log::warn!(
+                "Requested drawable size {:?} is adjusted to {:?}",
+                config.extent,
+                self.extent
+            );
+        }
+        *self.cached_extent.lock() = Some(self.extent);
         if caps.can_set_next_drawable_timeout {
             let () = msg_send![*render_layer, setAllowsNextDrawableTimeout:false];
         }
@@ -271,6 +380,12 @@ impl crate::Surface<super::Api> for super::Surface {
             let drawable = render_layer.next_drawable().unwrap();
             (drawable.to_owned(), drawable.texture().to_owned())
         });
+        // The drawable may not match the configured extent if the layer got resized.
+        let copy_size = crate::CopyExtent {
+            width: texture.width() as u32,
+            height: texture.height() as u32,
+            depth: 1,
+        };
 
         let suf_texture = super::SurfaceTexture {
             texture: super::Texture {
@@ -280,14 +395,13 @@ impl crate::Surface<super::Api> for super::Surface {
                 raw_type: mtl::MTLTextureType::D2,
                 array_layers: 1,
                 mip_levels: 1,
-                copy_size: crate::CopyExtent {
-                    width: self.extent.width,
-                    height: self.extent.height,
-                    depth: 1,
-                },
+                copy_size,
+                drop_guard: None,
+                owned: false,
             },
             drawable,
             present_with_transaction: self.present_with_transaction,
+            present_synchronously: self.present_synchronously,
         };
 
         Ok(Some(crate::AcquiredSurfaceTexture {
@@ -69,7 +69,11 @@ impl PhysicalDeviceFeatures {
                 .multi_draw_indirect(
                     requested_features.contains(wgt::Features::MULTI_DRAW_INDIRECT),
                 )
+                .draw_indirect_first_instance(
+                    requested_features.contains(wgt::Features::INDIRECT_FIRST_INSTANCE),
+                )
                 .depth_clamp(requested_features.contains(wgt::Features::DEPTH_CLAMPING))
+                .depth_bias_clamp(downlevel_flags.contains(wgt::DownlevelFlags::DEPTH_BIAS_CLAMP))
                 .fill_mode_non_solid(requested_features.intersects(
                     wgt::Features::POLYGON_MODE_LINE | wgt::Features::POLYGON_MODE_POINT,
                 ))
@@ -248,10 +252,18 @@ impl PhysicalDeviceFeatures {
 
         dl_flags.set(Df::CUBE_ARRAY_TEXTURES, self.core.image_cube_array != 0);
         dl_flags.set(Df::ANISOTROPIC_FILTERING, self.core.sampler_anisotropy != 0);
+        dl_flags.set(Df::DEPTH_BIAS_CLAMP, self.core.depth_bias_clamp != 0);
         dl_flags.set(
             Df::FRAGMENT_WRITABLE_STORAGE,
             self.core.fragment_stores_and_atomics != 0,
         );
+        // Tile-based GPUs run the vertex stage of a pass ahead of its fragments.
+        dl_flags.set(
+            Df::CROSS_STAGE_WRITABLE_STORAGE,
+            !crate::auxil::db::is_tile_based(caps.properties.vendor_id),
+        );
+        // Vulkan leaves derivatives in non-uniform control flow undefined.
+        dl_flags.remove(Df::NON_UNIFORM_DERIVATIVES);
 
         //if self.core.dual_src_blend != 0
         features.set(F::MULTI_DRAW_INDIRECT, self.core.multi_draw_indirect != 0);
@@ -290,6 +302,10 @@ impl PhysicalDeviceFeatures {
             self.core.shader_sampled_image_array_dynamic_indexing != 0,
         );
         features.set(F::SHADER_PRIMITIVE_INDEX, self.core.geometry_shader != 0);
+        features.set(
+            F::INDIRECT_FIRST_INSTANCE,
+            self.core.draw_indirect_first_instance != 0,
+        );
         if Self::all_features_supported(
             &features,
             &[
@@ -502,9 +518,22 @@ impl PhysicalDeviceCapabilities {
                 .min(crate::MAX_VERTEX_BUFFERS as u32),
             max_vertex_attributes: limits.max_vertex_input_attributes,
             max_vertex_buffer_array_stride: limits.max_vertex_input_binding_stride,
+            max_inter_stage_shader_components: limits
+                .max_vertex_output_components
+                .min(limits.max_fragment_input_components),
             max_push_constant_size: limits.max_push_constants_size,
             min_uniform_buffer_offset_alignment: limits.min_uniform_buffer_offset_alignment as u32,
             min_storage_buffer_offset_alignment: limits.min_storage_buffer_offset_alignment as u32,
+            max_compute_invocations_per_workgroup: limits.max_compute_work_group_invocations,
+            max_compute_workgroup_size_x: limits.max_compute_work_group_size[0],
+            max_compute_workgroup_size_y: limits.max_compute_work_group_size[1],
+            max_compute_workgroup_size_z: limits.max_compute_work_group_size[2],
+            max_compute_workgroups_per_dimension: limits
+                .max_compute_work_group_count
+                .iter()
+                .cloned()
+                .min()
+                .unwrap(),
         }
     }
 
@@ -628,7 +657,7 @@ impl super::Instance {
             backend: wgt::Backend::Vulkan,
         };
 
-        let (available_features, downlevel_flags) = phd_features.to_wgpu(&phd_capabilities);
+        let (mut available_features, downlevel_flags) = phd_features.to_wgpu(&phd_capabilities);
         {
             use crate::auxil::db;
             // see https://github.com/gfx-rs/gfx/issues/1930
@@ -696,6 +725,13 @@ impl super::Instance {
                     .optimal_tiling_features
                     .contains(vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT)
             },
+            texture_d32_s8: unsafe {
+                self.shared
+                    .raw
+                    .get_physical_device_format_properties(phd, vk::Format::D32_SFLOAT_S8_UINT)
+                    .optimal_tiling_features
+                    .contains(vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT)
+            },
             non_coherent_map_mask: phd_capabilities.properties.limits.non_coherent_atom_size - 1,
             can_present: true,
         };
@@ -710,6 +746,31 @@ impl super::Instance {
             },
         };
 
+        available_features.set(
+            wgt::Features::DEPTH32FLOAT_STENCIL8,
+            private_caps.texture_d32_s8,
+        );
+        // The unorm formats are guaranteed to be renderable by the feature.
+        let sampled = vk::FormatFeatureFlags::SAMPLED_IMAGE;
+        let renderable = sampled | vk::FormatFeatureFlags::COLOR_ATTACHMENT;
+        let texture_norm16 = [
+            (vk::Format::R16_UNORM, renderable),
+            (vk::Format::R16_SNORM, sampled),
+            (vk::Format::R16G16_UNORM, renderable),
+            (vk::Format::R16G16_SNORM, sampled),
+            (vk::Format::R16G16B16A16_UNORM, renderable),
+            (vk::Format::R16G16B16A16_SNORM, sampled),
+        ]
+        .iter()
+        .all(|&(format, usage)| unsafe {
+            self.shared
+                .raw
+                .get_physical_device_format_properties(phd, format)
+                .optimal_tiling_features
+                .contains(usage)
+        });
+        available_features.set(wgt::Features::TEXTURE_FORMAT_16BIT_NORM, texture_norm16);
+
         let adapter = super::Adapter {
             raw: phd,
             instance: Arc::clone(&self.shared),
@@ -13,6 +13,8 @@ impl super::PrivateCapabilities {
             Tf::R16Uint => F::R16_UINT,
             Tf::R16Sint => F::R16_SINT,
             Tf::R16Float => F::R16_SFLOAT,
+            Tf::R16Unorm => F::R16_UNORM,
+            Tf::R16Snorm => F::R16_SNORM,
             Tf::Rg8Unorm => F::R8G8_UNORM,
             Tf::Rg8Snorm => F::R8G8_SNORM,
             Tf::Rg8Uint => F::R8G8_UINT,
@@ -23,6 +25,8 @@ impl super::PrivateCapabilities {
             Tf::Rg16Uint => F::R16G16_UINT,
             Tf::Rg16Sint => F::R16G16_SINT,
             Tf::Rg16Float => F::R16G16_SFLOAT,
+            Tf::Rg16Unorm => F::R16G16_UNORM,
+            Tf::Rg16Snorm => F::R16G16_SNORM,
             Tf::Rgba8Unorm => F::R8G8B8A8_UNORM,
             Tf::Rgba8UnormSrgb => F::R8G8B8A8_SRGB,
             Tf::Bgra8UnormSrgb => F::B8G8R8A8_SRGB,
@@ -31,6 +35,7 @@ impl super::PrivateCapabilities {
             Tf::Rgba8Uint => F::R8G8B8A8_UINT,
             Tf::Rgba8Sint => F::R8G8B8A8_SINT,
             Tf::Rgb10a2Unorm => F::A2B10G10R10_UNORM_PACK32,
+            Tf::Rgb10a2Uint => F::A2B10G10R10_UINT_PACK32,
             Tf::Rg11b10Float => F::B10G11R11_UFLOAT_PACK32,
             Tf::Rg32Uint => F::R32G32_UINT,
             Tf::Rg32Sint => F::R32G32_SINT,
@@ -38,9 +43,13 @@ impl super::PrivateCapabilities {
             Tf::Rgba16Uint => F::R16G16B16A16_UINT,
             Tf::Rgba16Sint => F::R16G16B16A16_SINT,
             Tf::Rgba16Float => F::R16G16B16A16_SFLOAT,
+            Tf::Rgba16Unorm => F::R16G16B16A16_UNORM,
+            Tf::Rgba16Snorm => F::R16G16B16A16_SNORM,
             Tf::Rgba32Uint => F::R32G32B32A32_UINT,
             Tf::Rgba32Sint => F::R32G32B32A32_SINT,
             Tf::Rgba32Float => F::R32G32B32A32_SFLOAT,
+            Tf::Depth32FloatStencil8 => F::D32_SFLOAT_S8_UINT,
+            Tf::Depth16Unorm => F::D16_UNORM,
             Tf::Depth32Float => F::D32_SFLOAT,
             Tf::Depth24Plus => {
                 if self.texture_d24 {
@@ -49,7 +58,8 @@ impl super::PrivateCapabilities {
                     F::D32_SFLOAT
                 }
             }
-            Tf::Depth24PlusStencil8 => {
+            // `S8_UINT` support is optional, so stencil-only textures use a combined format.
+            Tf::Stencil8 | Tf::Depth24PlusStencil8 => {
                 if self.texture_d24_s8 {
                     F::D24_UNORM_S8_UINT
                 } else {
@@ -356,6 +366,8 @@ pub fn map_attachment_ops(
 ) -> (vk::AttachmentLoadOp, vk::AttachmentStoreOp) {
     let load_op = if op.contains(crate::AttachmentOps::LOAD) {
         vk::AttachmentLoadOp::LOAD
+    } else if op.contains(crate::AttachmentOps::DONT_CARE) {
+        vk::AttachmentLoadOp::DONT_CARE
     } else {
         vk::AttachmentLoadOp::CLEAR
     };
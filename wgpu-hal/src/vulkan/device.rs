@@ -1221,6 +1221,7 @@ impl crate::Device<super::Api> for super::Device {
             .depth_clamp_enable(desc.primitive.clamp_depth)
             .polygon_mode(conv::map_polygon_mode(desc.primitive.polygon_mode))
             .front_face(conv::map_front_face(desc.primitive.front_face))
+            // Wide lines aren't exposed, see `wgt::PrimitiveTopology`.
             .line_width(1.0);
         if let Some(face) = desc.primitive.cull_mode {
             vk_rasterization = vk_rasterization.cull_mode(conv::map_cull_face(face))
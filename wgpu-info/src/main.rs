@@ -47,9 +47,15 @@ fn print_info_from_adapter(adapter: &wgpu::Adapter, idx: usize) {
         max_vertex_buffers,
         max_vertex_attributes,
         max_vertex_buffer_array_stride,
+        max_inter_stage_shader_components,
         max_push_constant_size,
         min_uniform_buffer_offset_alignment,
         min_storage_buffer_offset_alignment,
+        max_compute_invocations_per_workgroup,
+        max_compute_workgroup_size_x,
+        max_compute_workgroup_size_y,
+        max_compute_workgroup_size_z,
+        max_compute_workgroups_per_dimension,
     } = limits;
     println!("\t\tMax Texture Dimension 1d:                        {}", max_texture_dimension_1d);
     println!("\t\tMax Texture Dimension 2d:                        {}", max_texture_dimension_2d);
@@ -68,9 +74,15 @@ fn print_info_from_adapter(adapter: &wgpu::Adapter, idx: usize) {
     println!("\t\tMax Vertex Buffers:                              {}", max_vertex_buffers);
     println!("\t\tMax Vertex Attributes:                           {}", max_vertex_attributes);
     println!("\t\tMax Vertex Buffer Array Stride:                  {}", max_vertex_buffer_array_stride);
+    println!("\t\tMax Inter-Stage Shader Components:               {}", max_inter_stage_shader_components);
     println!("\t\tMax Push Constant Size:                          {}", max_push_constant_size);
     println!("\t\tMin Uniform Buffer Offset Alignment:             {}", min_uniform_buffer_offset_alignment);
     println!("\t\tMin Storage Buffer Offset Alignment:             {}", min_storage_buffer_offset_alignment);
+    println!("\t\tMax Compute Invocations Per Workgroup:           {}", max_compute_invocations_per_workgroup);
+    println!("\t\tMax Compute Workgroup Size X:                    {}", max_compute_workgroup_size_x);
+    println!("\t\tMax Compute Workgroup Size Y:                    {}", max_compute_workgroup_size_y);
+    println!("\t\tMax Compute Workgroup Size Z:                    {}", max_compute_workgroup_size_z);
+    println!("\t\tMax Compute Workgroups Per Dimension:            {}", max_compute_workgroups_per_dimension);
     println!("\tDownlevel Properties:");
     let wgpu::DownlevelCapabilities {
         shader_model,
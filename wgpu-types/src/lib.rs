@@ -40,9 +40,14 @@ pub const COPY_BUFFER_ALIGNMENT: BufferAddress = 4;
 pub const MAP_ALIGNMENT: BufferAddress = 8;
 /// Vertex buffer strides have to be aligned to this number.
 pub const VERTEX_STRIDE_ALIGNMENT: BufferAddress = 4;
+/// Vertex buffer binding offsets have to be aligned to this number.
+pub const VERTEX_BUFFER_OFFSET_ALIGNMENT: BufferAddress = 4;
 /// Alignment all push constants need
 pub const PUSH_CONSTANT_ALIGNMENT: u32 = 4;
 /// Maximum queries in a query set
+///
+/// This fits the smallest occlusion query buffer supported by the backends,
+/// which is 64KB on Metal for Apple GPUs.
 pub const QUERY_SET_MAX_QUERIES: u32 = 8192;
 /// Size of a single piece of query data.
 pub const QUERY_SIZE: u32 = 8;
@@ -205,6 +210,7 @@ bitflags::bitflags! {
         /// Supported Platforms:
         /// - Vulkan (works)
         /// - DX12 (works)
+        /// - Metal (AMD and Intel GPUs on macOS 10.15+, which can sample counters between draws, dispatches and blits)
         ///
         /// This is a web and native feature.
         const TIMESTAMP_QUERY = 1 << 2;
@@ -223,6 +229,17 @@ bitflags::bitflags! {
         ///
         /// This is a web and native feature.
         const PIPELINE_STATISTICS_QUERY = 1 << 3;
+        /// Allows the use of [`TextureFormat::Depth32FloatStencil8`], which has
+        /// 32 bit floating point depth along with 8 bit stencil.
+        ///
+        /// Supported platforms:
+        /// - Vulkan (some)
+        /// - DX12
+        /// - Metal
+        /// - GLES
+        ///
+        /// This is a web and native feature.
+        const DEPTH32FLOAT_STENCIL8 = 1 << 4;
         /// Webgpu only allows the MAP_READ and MAP_WRITE buffer usage to be matched with
         /// COPY_DST and COPY_SRC respectively. This removes this requirement.
         ///
@@ -352,6 +369,7 @@ bitflags::bitflags! {
         /// Supported platforms:
         /// - DX12
         /// - Vulkan
+        /// - Metal (Apple3+ and Mac2 families)
         ///
         /// This is a native only feature.
         const MULTI_DRAW_INDIRECT = 1 << 23;
@@ -520,9 +538,78 @@ bitflags::bitflags! {
         ///
         /// Supported platforms:
         /// - Vulkan
+        /// - Metal (macOS 10.15+)
         ///
         /// This is a native only feature.
         const SHADER_PRIMITIVE_INDEX = 1 << 38;
+        /// Enables occlusion queries counting the number of samples that passed
+        /// the depth and stencil tests, instead of only reporting whether any did.
+        ///
+        /// Supported platforms:
+        /// - Metal (macOS, A9+ iOS)
+        ///
+        /// This is a native only feature.
+        const PRECISE_OCCLUSION_QUERY = 1 << 39;
+        /// Allows the `first_instance` field of indirect draw arguments to be non-zero.
+        ///
+        /// Without it, `first_instance` has to be zero, and other values are
+        /// ignored or produce garbage depending on the hardware.
+        ///
+        /// Supported platforms:
+        /// - Vulkan (with `drawIndirectFirstInstance`)
+        /// - Metal (A9+ iOS, macOS)
+        ///
+        /// This is a native only feature.
+        const INDIRECT_FIRST_INSTANCE = 1 << 40;
+        /// Allows shaders to use 16-bit floating point types, for arithmetic
+        /// as well as in buffers and stage inputs and outputs.
+        ///
+        /// Supported platforms:
+        /// - Metal (MSL 2.0+)
+        ///
+        /// This is a native only feature.
+        const SHADER_F16 = 1 << 41;
+        /// Enables the 16-bit normalized texture formats: [`TextureFormat::R16Unorm`],
+        /// [`TextureFormat::R16Snorm`], [`TextureFormat::Rg16Unorm`], [`TextureFormat::Rg16Snorm`],
+        /// [`TextureFormat::Rgba16Unorm`] and [`TextureFormat::Rgba16Snorm`].
+        ///
+        /// Supported platforms:
+        /// - Vulkan (some)
+        /// - DX12
+        /// - Metal
+        ///
+        /// This is a native only feature.
+        const TEXTURE_FORMAT_16BIT_NORM = 1 << 42;
+        /// Allows [`TextureFormat::Rg11b10Float`] to be used as a render attachment,
+        /// multisampled and blended.
+        ///
+        /// Supported platforms:
+        /// - Metal
+        ///
+        /// This is a web and native feature.
+        const RG11B10UFLOAT_RENDERABLE = 1 << 43;
+        /// Allows fragment shaders to put the resources they access in raster order
+        /// groups, so the accesses from overlapping fragments happen in the order of
+        /// the primitives. This allows programmable blending and order-independent
+        /// transparency without sorting.
+        ///
+        /// Shaders can't declare raster order groups from WGSL or SPIR-V yet.
+        ///
+        /// Supported platforms:
+        /// - Metal (macOS 10.13+ on supported GPUs, A11+ iOS)
+        ///
+        /// This is a native only feature.
+        const RASTER_ORDER_GROUPS = 1 << 44;
+        /// Allows compute shaders to use subgroup operations, like shuffles,
+        /// reductions and ballots across the invocations executed together.
+        ///
+        /// Shaders can't use subgroup operations from WGSL or SPIR-V yet.
+        ///
+        /// Supported platforms:
+        /// - Metal (SIMD-group functions, Mac2 and Apple6 families)
+        ///
+        /// This is a native only feature.
+        const SUBGROUP = 1 << 45;
     }
 }
 
@@ -617,6 +704,9 @@ pub struct Limits {
     /// Maximum value for `VertexBufferLayout::array_stride` when creating a `RenderPipeline`.
     /// Defaults to 2048. Higher is "better".
     pub max_vertex_buffer_array_stride: u32,
+    /// Maximum number of scalar components passed from the vertex stage to the fragment stage,
+    /// summed over all the user-defined outputs. Defaults to 60. Higher is "better".
+    pub max_inter_stage_shader_components: u32,
     /// Amount of storage available for push constants in bytes. Defaults to 0. Higher is "better".
     /// Requesting more than 0 during device creation requires [`Features::PUSH_CONSTANTS`] to be enabled.
     ///
@@ -635,6 +725,21 @@ pub struct Limits {
     /// when creating a `BindGroup`, or for `set_bind_group` `dynamicOffsets`.
     /// Defaults to 256. Lower is "better".
     pub min_storage_buffer_offset_alignment: u32,
+    /// Maximum value of the product of the `workgroup_size` dimensions of a compute shader.
+    /// Defaults to 256. Higher is "better".
+    pub max_compute_invocations_per_workgroup: u32,
+    /// Maximum value of the `workgroup_size` X dimension of a compute shader.
+    /// Defaults to 256. Higher is "better".
+    pub max_compute_workgroup_size_x: u32,
+    /// Maximum value of the `workgroup_size` Y dimension of a compute shader.
+    /// Defaults to 256. Higher is "better".
+    pub max_compute_workgroup_size_y: u32,
+    /// Maximum value of the `workgroup_size` Z dimension of a compute shader.
+    /// Defaults to 64. Higher is "better".
+    pub max_compute_workgroup_size_z: u32,
+    /// Maximum value for each dimension of a `ComputePass::dispatch(x, y, z)` operation.
+    /// Defaults to 65535. Higher is "better".
+    pub max_compute_workgroups_per_dimension: u32,
 }
 
 impl Default for Limits {
@@ -657,9 +762,15 @@ impl Default for Limits {
             max_vertex_buffers: 8,
             max_vertex_attributes: 16,
             max_vertex_buffer_array_stride: 2048,
+            max_inter_stage_shader_components: 60,
             max_push_constant_size: 0,
             min_uniform_buffer_offset_alignment: 256,
             min_storage_buffer_offset_alignment: 256,
+            max_compute_invocations_per_workgroup: 256,
+            max_compute_workgroup_size_x: 256,
+            max_compute_workgroup_size_y: 256,
+            max_compute_workgroup_size_z: 64,
+            max_compute_workgroups_per_dimension: 65535,
         }
     }
 }
@@ -685,9 +796,15 @@ impl Limits {
             max_vertex_buffers: 8,
             max_vertex_attributes: 16,
             max_vertex_buffer_array_stride: 2048,
+            max_inter_stage_shader_components: 60,
             max_push_constant_size: 0,
             min_uniform_buffer_offset_alignment: 256,
             min_storage_buffer_offset_alignment: 256,
+            max_compute_invocations_per_workgroup: 256,
+            max_compute_workgroup_size_x: 256,
+            max_compute_workgroup_size_y: 256,
+            max_compute_workgroup_size_z: 64,
+            max_compute_workgroups_per_dimension: 65535,
         }
     }
 
@@ -804,6 +921,28 @@ bitflags::bitflags! {
         /// WebGPU, the implementation is allowed to completely ignore aniso clamp. This flag is
         /// here for native backends so they can comunicate to the user of aniso is enabled.
         const ANISOTROPIC_FILTERING = 1 << 11;
+
+        /// Derivatives (and implicit-LOD sampling) produce well-defined results when evaluated
+        /// in non-uniform control flow of a fragment shader. This isn't required by WebGPU,
+        /// which rejects such shaders through uniformity analysis instead.
+        const NON_UNIFORM_DERIVATIVES = 1 << 12;
+
+        /// Supports writable storage bindings that are visible to both the vertex and fragment
+        /// stages, with writes of either stage visible to the other one in subsequent draw calls
+        /// of the same pass. Tile-based GPUs run the vertex stage of a pass ahead of its
+        /// fragments, so fragment writes can't be observed by later vertex invocations there.
+        const CROSS_STAGE_WRITABLE_STORAGE = 1 << 13;
+
+        /// The `clamp` of [`DepthBiasState`] is honored. Without it, the depth bias
+        /// is applied unclamped and the value is ignored.
+        const DEPTH_BIAS_CLAMP = 1 << 14;
+
+        /// Supports comparison samplers created at runtime, with the comparison function
+        /// being part of the sampler object. Some Apple GPUs can only compare with samplers
+        /// that are declared inside the shader, which is all [`COMPARISON_SAMPLERS`] implies.
+        ///
+        /// [`COMPARISON_SAMPLERS`]: Self::COMPARISON_SAMPLERS
+        const MUTABLE_COMPARISON_SAMPLERS = 1 << 15;
     }
 }
 
@@ -815,8 +954,14 @@ impl DownlevelFlags {
     pub const fn compliant() -> Self {
         // We use manual bit twiddling to make this a const fn as `Sub` and `.remove` aren't const
 
-        // WebGPU doesn't actually require aniso
-        Self::from_bits_truncate(Self::all().bits() & !Self::ANISOTROPIC_FILTERING.bits)
+        // WebGPU doesn't actually require aniso, non-uniform derivatives,
+        // or writable storage in vertex shaders
+        Self::from_bits_truncate(
+            Self::all().bits()
+                & !Self::ANISOTROPIC_FILTERING.bits
+                & !Self::NON_UNIFORM_DERIVATIVES.bits
+                & !Self::CROSS_STAGE_WRITABLE_STORAGE.bits,
+        )
     }
 }
 
@@ -1080,6 +1225,8 @@ impl Default for BlendComponent {
 /// Describe the blend state of a render pipeline.
 ///
 /// See the OpenGL or Vulkan spec for more information.
+///
+/// Logic operations (e.g. XOR) are not exposed, since backends like Metal don't have them.
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
@@ -1145,6 +1292,9 @@ impl From<TextureFormat> for ColorTargetState {
 }
 
 /// Primitive type the input mesh is composed of.
+///
+/// Lines are always rasterized one pixel wide. There is no line width setting,
+/// since Metal and WebGPU don't support any other width.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
@@ -1232,7 +1382,7 @@ pub enum Face {
 pub enum PolygonMode {
     /// Polygons are filled
     Fill = 0,
-    /// Polygons are drawn as line segments
+    /// Polygons are drawn as line segments, which are always one pixel wide.
     Line = 1,
     /// Polygons are drawn as points
     Point = 2,
@@ -1324,6 +1474,8 @@ bitflags::bitflags! {
         /// When used as a STORAGE texture, then a texture with this format can be written to with atomics.
         // TODO: No access flag exposed as of writing
         const STORAGE_ATOMICS = 1 << 1;
+        /// When used as a STORAGE texture, then a texture with this format can be multisampled.
+        const STORAGE_MULTISAMPLE = 1 << 2;
     }
 }
 
@@ -1393,6 +1545,16 @@ pub enum TextureFormat {
     /// Red channel only. 16 bit float per channel. Float in shader.
     #[cfg_attr(feature = "serde", serde(rename = "r16float"))]
     R16Float,
+    /// Red channel only. 16 bit integer per channel. [0, 65535] converted to/from float [0, 1] in shader.
+    ///
+    /// [`Features::TEXTURE_FORMAT_16BIT_NORM`] must be enabled to use this texture format.
+    #[cfg_attr(feature = "serde", serde(rename = "r16unorm"))]
+    R16Unorm,
+    /// Red channel only. 16 bit integer per channel. [-32767, 32767] converted to/from float [-1, 1] in shader.
+    ///
+    /// [`Features::TEXTURE_FORMAT_16BIT_NORM`] must be enabled to use this texture format.
+    #[cfg_attr(feature = "serde", serde(rename = "r16snorm"))]
+    R16Snorm,
     /// Red and green channels. 8 bit integer per channel. [0, 255] converted to/from float [0, 1] in shader.
     #[cfg_attr(feature = "serde", serde(rename = "rg8unorm"))]
     Rg8Unorm,
@@ -1425,6 +1587,16 @@ pub enum TextureFormat {
     /// Red and green channels. 16 bit float per channel. Float in shader.
     #[cfg_attr(feature = "serde", serde(rename = "rg16float"))]
     Rg16Float,
+    /// Red and green channels. 16 bit integer per channel. [0, 65535] converted to/from float [0, 1] in shader.
+    ///
+    /// [`Features::TEXTURE_FORMAT_16BIT_NORM`] must be enabled to use this texture format.
+    #[cfg_attr(feature = "serde", serde(rename = "rg16unorm"))]
+    Rg16Unorm,
+    /// Red and green channels. 16 bit integer per channel. [-32767, 32767] converted to/from float [-1, 1] in shader.
+    ///
+    /// [`Features::TEXTURE_FORMAT_16BIT_NORM`] must be enabled to use this texture format.
+    #[cfg_attr(feature = "serde", serde(rename = "rg16snorm"))]
+    Rg16Snorm,
     /// Red, green, blue, and alpha channels. 8 bit integer per channel. [0, 255] converted to/from float [0, 1] in shader.
     #[cfg_attr(feature = "serde", serde(rename = "rgba8unorm"))]
     Rgba8Unorm,
@@ -1451,7 +1623,12 @@ pub enum TextureFormat {
     /// Red, green, blue, and alpha channels. 10 bit integer for RGB channels, 2 bit integer for alpha channel. [0, 1023] ([0, 3] for alpha) converted to/from float [0, 1] in shader.
     #[cfg_attr(feature = "serde", serde(rename = "rgb10a2unorm"))]
     Rgb10a2Unorm,
+    /// Red, green, blue, and alpha channels. 10 bit integer for RGB channels, 2 bit integer for alpha channel. Unsigned in shader.
+    #[cfg_attr(feature = "serde", serde(rename = "rgb10a2uint"))]
+    Rgb10a2Uint,
     /// Red, green, and blue channels. 11 bit float with no sign bit for RG channels. 10 bit float with no sign bit for blue channel. Float in shader.
+    ///
+    /// [`Features::RG11B10UFLOAT_RENDERABLE`] allows it to be used as a render attachment.
     #[cfg_attr(feature = "serde", serde(rename = "rg11b10ufloat"))]
     Rg11b10Float,
 
@@ -1474,6 +1651,16 @@ pub enum TextureFormat {
     /// Red, green, blue, and alpha channels. 16 bit float per channel. Float in shader.
     #[cfg_attr(feature = "serde", serde(rename = "rgba16float"))]
     Rgba16Float,
+    /// Red, green, blue, and alpha channels. 16 bit integer per channel. [0, 65535] converted to/from float [0, 1] in shader.
+    ///
+    /// [`Features::TEXTURE_FORMAT_16BIT_NORM`] must be enabled to use this texture format.
+    #[cfg_attr(feature = "serde", serde(rename = "rgba16unorm"))]
+    Rgba16Unorm,
+    /// Red, green, blue, and alpha channels. 16 bit integer per channel. [-32767, 32767] converted to/from float [-1, 1] in shader.
+    ///
+    /// [`Features::TEXTURE_FORMAT_16BIT_NORM`] must be enabled to use this texture format.
+    #[cfg_attr(feature = "serde", serde(rename = "rgba16snorm"))]
+    Rgba16Snorm,
 
     // Normal 128 bit formats
     /// Red, green, blue, and alpha channels. 32 bit integer per channel. Unsigned in shader.
@@ -1487,6 +1674,12 @@ pub enum TextureFormat {
     Rgba32Float,
 
     // Depth and stencil formats
+    /// Stencil format with 8 bit integer stencil.
+    #[cfg_attr(feature = "serde", serde(rename = "stencil8"))]
+    Stencil8,
+    /// Special depth format with 16 bit integer depth.
+    #[cfg_attr(feature = "serde", serde(rename = "depth16unorm"))]
+    Depth16Unorm,
     /// Special depth format with 32 bit floating point depth.
     #[cfg_attr(feature = "serde", serde(rename = "depth32float"))]
     Depth32Float,
@@ -1496,6 +1689,11 @@ pub enum TextureFormat {
     /// Special depth/stencil format with at least 24 bit integer depth and 8 bits integer stencil.
     #[cfg_attr(feature = "serde", serde(rename = "depth24plus-stencil8"))]
     Depth24PlusStencil8,
+    /// Special depth/stencil format with 32 bit floating point depth and 8 bits integer stencil.
+    ///
+    /// [`Features::DEPTH32FLOAT_STENCIL8`] must be enabled to use this texture format.
+    #[cfg_attr(feature = "serde", serde(rename = "depth32float-stencil8"))]
+    Depth32FloatStencil8,
 
     // Packed uncompressed texture formats
     /// Packed unsigned float with 9 bits mantisa for each RGB component, then a common 5 bits exponent
@@ -1810,6 +2008,8 @@ impl TextureFormat {
     pub fn describe(&self) -> TextureFormatInfo {
         // Features
         let native = Features::empty();
+        let d32_s8 = Features::DEPTH32FLOAT_STENCIL8;
+        let norm16 = Features::TEXTURE_FORMAT_16BIT_NORM;
         let bc = Features::TEXTURE_COMPRESSION_BC;
         let etc2 = Features::TEXTURE_COMPRESSION_ETC2;
         let astc_ldr = Features::TEXTURE_COMPRESSION_ASTC_LDR;
@@ -1845,6 +2045,8 @@ impl TextureFormat {
                 Self::R16Uint => (native, uint, linear, (1, 1), 2, attachment),
                 Self::R16Sint => (native, sint, linear, (1, 1), 2, attachment),
                 Self::R16Float => (native, float, linear, (1, 1), 2, attachment),
+                Self::R16Unorm => (norm16, float, linear, (1, 1), 2, attachment),
+                Self::R16Snorm => (norm16, float, linear, (1, 1), 2, basic),
                 Self::Rg8Unorm => (native, float, linear, (1, 1), 2, attachment),
                 Self::Rg8Snorm => (native, float, linear, (1, 1), 2, attachment),
                 Self::Rg8Uint => (native, uint, linear, (1, 1), 2, attachment),
@@ -1857,6 +2059,8 @@ impl TextureFormat {
                 Self::Rg16Uint => (native, uint, linear, (1, 1), 4, attachment),
                 Self::Rg16Sint => (native, sint, linear, (1, 1), 4, attachment),
                 Self::Rg16Float => (native, float, linear, (1, 1), 4, attachment),
+                Self::Rg16Unorm => (norm16, float, linear, (1, 1), 4, attachment),
+                Self::Rg16Snorm => (norm16, float, linear, (1, 1), 4, basic),
                 Self::Rgba8Unorm => (native, float, linear, (1, 1), 4, all_flags),
                 Self::Rgba8UnormSrgb => (native, float, srgb, (1, 1), 4, attachment),
                 Self::Rgba8Snorm => (native, float, linear, (1, 1), 4, storage),
@@ -1867,6 +2071,7 @@ impl TextureFormat {
 
                 // Packed 32 bit textures
                 Self::Rgb10a2Unorm => (native, float, linear, (1, 1), 4, attachment),
+                Self::Rgb10a2Uint => (native, uint, linear, (1, 1), 4, attachment),
                 Self::Rg11b10Float => (native, float, linear, (1, 1), 4, basic),
 
                 // Packed 32 bit textures
@@ -1876,6 +2081,8 @@ impl TextureFormat {
                 Self::Rgba16Uint => (native, uint, linear, (1, 1), 8, all_flags),
                 Self::Rgba16Sint => (native, sint, linear, (1, 1), 8, all_flags),
                 Self::Rgba16Float => (native, float, linear, (1, 1), 8, all_flags),
+                Self::Rgba16Unorm => (norm16, float, linear, (1, 1), 8, attachment),
+                Self::Rgba16Snorm => (norm16, float, linear, (1, 1), 8, basic),
 
                 // Packed 32 bit textures
                 Self::Rgba32Uint => (native, uint, linear, (1, 1), 16, all_flags),
@@ -1883,9 +2090,12 @@ impl TextureFormat {
                 Self::Rgba32Float => (native, nearest, linear, (1, 1), 16, all_flags),
 
                 // Depth-stencil textures
+                Self::Stencil8 => (native, uint, linear, (1, 1), 1, attachment),
+                Self::Depth16Unorm => (native, depth, linear, (1, 1), 2, attachment),
                 Self::Depth32Float => (native, depth, linear, (1, 1), 4, attachment),
                 Self::Depth24Plus => (native, depth, linear, (1, 1), 4, attachment),
                 Self::Depth24PlusStencil8 => (native, depth, linear, (1, 1), 4, attachment),
+                Self::Depth32FloatStencil8 => (d32_s8, depth, linear, (1, 1), 5, attachment),
 
                 // Packed uncompressed
                 Self::Rgb9e5Ufloat => (native, float, linear, (1, 1), 4, basic),
@@ -2035,6 +2245,8 @@ pub struct DepthBiasState {
     /// Slope depth biasing factor.
     pub slope_scale: f32,
     /// Depth bias clamp value (absolute).
+    ///
+    /// Ignored unless the adapter supports `DownlevelFlags::DEPTH_BIAS_CLAMP`.
     pub clamp: f32,
 }
 
@@ -2500,7 +2712,12 @@ bitflags_serde_shim::impl_serde_for_bitflags!(TextureUsages);
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct SurfaceConfiguration {
-    /// The usage of the swap chain. The only supported usage is `RENDER_ATTACHMENT`.
+    /// The usage of the swap chain. The only guaranteed usage is `RENDER_ATTACHMENT`.
+    ///
+    /// Some backends also support copies from and to the surface textures, or
+    /// `STORAGE_BINDING` when the format allows it, to write the final image from a
+    /// compute shader. This may come at a performance cost: on Metal, it prevents the
+    /// compositor from using its framebuffer-only fast path.
     pub usage: TextureUsages,
     /// The texture format of the swap chain. The only formats that are guaranteed are
     /// `Bgra8Unorm` and `Bgra8UnormSrgb`
@@ -3345,6 +3562,9 @@ impl<L> QuerySetDescriptor<L> {
 #[cfg_attr(feature = "replay", derive(serde::Deserialize))]
 pub enum QueryType {
     /// Query returns a single 64-bit number, serving as an occlusion boolean.
+    ///
+    /// With [`Features::PRECISE_OCCLUSION_QUERY`] enabled, the number is
+    /// the count of samples that passed the depth and stencil tests.
     Occlusion,
     /// Query returns up to 5 64-bit numbers based on the given flags.
     ///
@@ -3413,6 +3633,8 @@ pub struct DrawIndirectArgs {
     /// Offset into the vertex buffers, in vertices, to begin drawing from.
     pub first_vertex: u32,
     /// First instance to draw.
+    ///
+    /// Has to be zero unless [`Features::INDIRECT_FIRST_INSTANCE`] is enabled.
     pub first_instance: u32,
 }
 
@@ -3429,6 +3651,8 @@ pub struct DrawIndexedIndirectArgs {
     /// Added to each index value before indexing into the vertex buffers.
     pub base_vertex: i32,
     /// First instance to draw.
+    ///
+    /// Has to be zero unless [`Features::INDIRECT_FIRST_INSTANCE`] is enabled.
     pub first_instance: u32,
 }
 
@@ -496,9 +496,12 @@ fn map_texture_format(texture_format: wgt::TextureFormat) -> web_sys::GpuTexture
         TextureFormat::Rgba32Uint => tf::Rgba32uint,
         TextureFormat::Rgba32Sint => tf::Rgba32sint,
         TextureFormat::Rgba32Float => tf::Rgba32float,
+        TextureFormat::Stencil8 => tf::Stencil8,
+        TextureFormat::Depth16Unorm => tf::Depth16unorm,
         TextureFormat::Depth32Float => tf::Depth32float,
         TextureFormat::Depth24Plus => tf::Depth24plus,
         TextureFormat::Depth24PlusStencil8 => tf::Depth24plusStencil8,
+        TextureFormat::Depth32FloatStencil8 => tf::Depth32floatStencil8,
         _ => unimplemented!(),
     }
 }
@@ -544,9 +547,12 @@ fn map_texture_format_from_web_sys(
         tf::Rgba32uint => TextureFormat::Rgba32Uint,
         tf::Rgba32sint => TextureFormat::Rgba32Sint,
         tf::Rgba32float => TextureFormat::Rgba32Float,
+        tf::Stencil8 => TextureFormat::Stencil8,
+        tf::Depth16unorm => TextureFormat::Depth16Unorm,
         tf::Depth32float => TextureFormat::Depth32Float,
         tf::Depth24plus => TextureFormat::Depth24Plus,
         tf::Depth24plusStencil8 => TextureFormat::Depth24PlusStencil8,
+        tf::Depth32floatStencil8 => TextureFormat::Depth32FloatStencil8,
         _ => unimplemented!(),
     }
 }
@@ -1008,7 +1014,10 @@ impl crate::Context for Context {
         let possible_features = [
             (wgt::Features::DEPTH_CLAMPING, Gfn::DepthClamping),
             // TODO (_, Gfn::Depth24unormStencil8),
-            // TODO (_, Gfn::Depth32floatStencil8),
+            (
+                wgt::Features::DEPTH32FLOAT_STENCIL8,
+                Gfn::Depth32floatStencil8,
+            ),
             (
                 wgt::Features::PIPELINE_STATISTICS_QUERY,
                 Gfn::PipelineStatisticsQuery,
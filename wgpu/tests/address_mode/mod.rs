@@ -0,0 +1,140 @@
+use wgpu::util::DeviceExt;
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+// A 2x1 texture with a red texel on the left and a green one on the right.
+const TEXELS: [u8; 8] = [255, 0, 0, 255, 0, 255, 0, 255];
+const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+
+/// Samples the test texture just outside of `[0, 1]` with the given address mode.
+fn sample_outside(
+    ctx: &TestingContext,
+    address_mode: wgpu::AddressMode,
+    border_color: Option<wgpu::SamplerBorderColor>,
+) -> [f32; 4] {
+    let shader = ctx
+        .device
+        .create_shader_module(&wgpu::include_wgsl!("sample.wgsl"));
+
+    let texture = ctx.device.create_texture_with_data(
+        &ctx.queue,
+        &wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: 2,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        },
+        &TEXELS,
+    );
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let sampler = ctx.device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: address_mode,
+        address_mode_v: address_mode,
+        address_mode_w: address_mode,
+        border_color,
+        ..Default::default()
+    });
+
+    let output = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: std::mem::size_of::<[f32; 4]>() as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let pipeline = ctx
+        .device
+        .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: None,
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+    let bg = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: output.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bg, &[]);
+        cpass.dispatch(1, 1, 1);
+    }
+
+    ctx.queue.submit(Some(encoder.finish()));
+    let slice = output.slice(..);
+    let _ = slice.map_async(wgpu::MapMode::Read);
+    ctx.device.poll(wgpu::Maintain::Wait);
+    let data: Vec<f32> = bytemuck::cast_slice(&*slice.get_mapped_range()).to_vec();
+    [data[0], data[1], data[2], data[3]]
+}
+
+#[test]
+fn sample_outside_texture() {
+    initialize_test(
+        TestParameters::default()
+            .test_features()
+            .downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS),
+        |ctx| {
+            let cases = [
+                (wgpu::AddressMode::Repeat, GREEN),
+                (wgpu::AddressMode::MirrorRepeat, RED),
+                (wgpu::AddressMode::ClampToEdge, RED),
+            ];
+            for &(address_mode, expected) in cases.iter() {
+                let color = sample_outside(&ctx, address_mode, None);
+                assert_eq!(color, expected, "{:?}", address_mode);
+            }
+        },
+    )
+}
+
+#[test]
+fn sample_outside_texture_clamp_to_border() {
+    initialize_test(
+        TestParameters::default()
+            .test_features()
+            .features(wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER)
+            .downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS),
+        |ctx| {
+            let cases = [
+                (wgpu::SamplerBorderColor::TransparentBlack, [0.0; 4]),
+                (wgpu::SamplerBorderColor::OpaqueBlack, [0.0, 0.0, 0.0, 1.0]),
+                (wgpu::SamplerBorderColor::OpaqueWhite, [1.0; 4]),
+            ];
+            for &(border_color, expected) in cases.iter() {
+                let color =
+                    sample_outside(&ctx, wgpu::AddressMode::ClampToBorder, Some(border_color));
+                assert_eq!(color, expected, "{:?}", border_color);
+            }
+        },
+    )
+}
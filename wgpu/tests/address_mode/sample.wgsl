@@ -0,0 +1,19 @@
+[[block]]
+struct Output {
+    color: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var texture: texture_2d<f32>;
+
+[[group(0), binding(1)]]
+var texture_sampler: sampler;
+
+[[group(0), binding(2)]]
+var<storage, read_write> output: Output;
+
+[[stage(compute), workgroup_size(1)]]
+fn cs_main() {
+    // A quarter texel to the left of the texture, in the middle of the row.
+    output.color = textureSampleLevel(texture, texture_sampler, vec2<f32>(-0.25, 0.5), 0.0);
+}
@@ -0,0 +1,81 @@
+use wgpu::util::DeviceExt;
+
+use crate::common::{initialize_test, TestParameters};
+
+// Uniform data laid out by hand following the WGSL rules, which is what
+// naga emits for every backend: `a: vec3<f32>` is followed by `b: f32` in
+// its padding, `c: vec2<f32>` sits at 16 and `d: vec4<f32>` is aligned to 32.
+const INPUT: [f32; 12] = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 0.0, 0.0, 7.0, 8.0, 9.0, 10.0];
+const EXPECTED: [f32; 10] = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];
+
+#[test]
+fn uniform_struct_layout() {
+    initialize_test(
+        TestParameters::default()
+            .test_features()
+            .downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS),
+        |ctx| {
+            let shader = ctx
+                .device
+                .create_shader_module(&wgpu::include_wgsl!("read.wgsl"));
+
+            let input = ctx
+                .device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: None,
+                    contents: bytemuck::cast_slice(&INPUT),
+                    usage: wgpu::BufferUsages::UNIFORM,
+                });
+
+            let output = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: std::mem::size_of_val(&EXPECTED) as u64,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+
+            let pipeline = ctx
+                .device
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: None,
+                    layout: None,
+                    module: &shader,
+                    entry_point: "cs_main",
+                });
+
+            let bg = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: input.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: output.as_entire_binding(),
+                    },
+                ],
+            });
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            {
+                let mut cpass =
+                    encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+                cpass.set_pipeline(&pipeline);
+                cpass.set_bind_group(0, &bg, &[]);
+                cpass.dispatch(1, 1, 1);
+            }
+
+            ctx.queue.submit(Some(encoder.finish()));
+            let slice = output.slice(..);
+            let _ = slice.map_async(wgpu::MapMode::Read);
+            ctx.device.poll(wgpu::Maintain::Wait);
+            let data: Vec<f32> = bytemuck::cast_slice(&*slice.get_mapped_range()).to_vec();
+
+            assert_eq!(data, EXPECTED);
+        },
+    )
+}
@@ -0,0 +1,32 @@
+[[block]]
+struct Input {
+    a: vec3<f32>;
+    b: f32;
+    c: vec2<f32>;
+    d: vec4<f32>;
+};
+
+[[block]]
+struct Output {
+    arr: array<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> input: Input;
+
+[[group(0), binding(1)]]
+var<storage, read_write> output: Output;
+
+[[stage(compute), workgroup_size(1)]]
+fn cs_main() {
+    output.arr[0] = input.a.x;
+    output.arr[1] = input.a.y;
+    output.arr[2] = input.a.z;
+    output.arr[3] = input.b;
+    output.arr[4] = input.c.x;
+    output.arr[5] = input.c.y;
+    output.arr[6] = input.d.x;
+    output.arr[7] = input.d.y;
+    output.arr[8] = input.d.z;
+    output.arr[9] = input.d.w;
+}
@@ -0,0 +1,107 @@
+use std::num::NonZeroU32;
+
+use crate::common::{initialize_test, TestParameters};
+
+const SIZE: u32 = 4;
+
+#[test]
+fn draw_behind_near_plane() {
+    initialize_test(
+        TestParameters::default().features(wgpu::Features::DEPTH_CLAMPING),
+        |ctx| {
+            let shader = ctx
+                .device
+                .create_shader_module(&wgpu::include_wgsl!("draw.wgsl"));
+
+            let pipeline = ctx
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: None,
+                    layout: None,
+                    vertex: wgpu::VertexState {
+                        buffers: &[],
+                        entry_point: "vs_main",
+                        module: &shader,
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        clamp_depth: true,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    fragment: Some(wgpu::FragmentState {
+                        entry_point: "fs_main",
+                        module: &shader,
+                        targets: &[wgpu::TextureFormat::Rgba8Unorm.into()],
+                    }),
+                });
+
+            let target = ctx.device.create_texture(&wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width: SIZE,
+                    height: SIZE,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            });
+            let target_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+            let bytes_per_row = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+            let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: (bytes_per_row * SIZE) as u64,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            {
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[wgpu::RenderPassColorAttachment {
+                        view: &target_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations::default(),
+                    }],
+                    depth_stencil_attachment: None,
+                });
+                rpass.set_pipeline(&pipeline);
+                rpass.draw(0..3, 0..1);
+            }
+            encoder.copy_texture_to_buffer(
+                target.as_image_copy(),
+                wgpu::ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: NonZeroU32::new(bytes_per_row),
+                        rows_per_image: None,
+                    },
+                },
+                wgpu::Extent3d {
+                    width: SIZE,
+                    height: SIZE,
+                    depth_or_array_layers: 1,
+                },
+            );
+
+            ctx.queue.submit(Some(encoder.finish()));
+            let slice = buffer.slice(..);
+            let _ = slice.map_async(wgpu::MapMode::Read);
+            ctx.device.poll(wgpu::Maintain::Wait);
+            let data = slice.get_mapped_range();
+
+            // The triangle would be entirely clipped without clamping.
+            for row in data.chunks(bytes_per_row as usize) {
+                assert!(row[..(SIZE * 4) as usize].iter().all(|&value| value == 255));
+            }
+        },
+    )
+}
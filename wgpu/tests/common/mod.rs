@@ -59,9 +59,15 @@ pub fn lowest_reasonable_limits() -> Limits {
         max_vertex_buffers: 4,
         max_vertex_attributes: 4,
         max_vertex_buffer_array_stride: 32,
+        max_inter_stage_shader_components: 16,
         max_push_constant_size: 0,
         min_uniform_buffer_offset_alignment: 256,
         min_storage_buffer_offset_alignment: 256,
+        max_compute_invocations_per_workgroup: 64,
+        max_compute_workgroup_size_x: 64,
+        max_compute_workgroup_size_y: 64,
+        max_compute_workgroup_size_z: 16,
+        max_compute_workgroups_per_dimension: 1024,
     }
 }
 
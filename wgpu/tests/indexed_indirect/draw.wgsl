@@ -0,0 +1,20 @@
+[[block]]
+struct Indices {
+    arr: array<u32>;
+};
+
+[[group(0), binding(0)]]
+var<storage, read_write> indices: Indices;
+
+[[stage(vertex)]]
+fn vs_main([[builtin(instance_index)]] instance: u32, [[builtin(vertex_index)]] index: u32) -> [[builtin(position)]] vec4<f32> {
+    // Offset by one, so that the vertices that aren't drawn stay zero. Only the
+    // first instance is drawn, so adding it catches a garbage `first_instance`.
+    indices.arr[index] = index + 1u + instance;
+    return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_main() -> [[location(0)]] vec4<f32> {
+    return vec4<f32>(0.0);
+}
@@ -0,0 +1,180 @@
+use wgpu::util::DeviceExt;
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+const INDICES: [u32; 6] = [5, 4, 3, 2, 1, 0];
+
+/// Draws `INDICES` with an indexed indirect draw, reading them as `format`,
+/// and checks that every vertex was fetched. The draw is recorded in a render
+/// bundle if `bundle` is set.
+fn draw_indexed_indirect(ctx: TestingContext, format: wgpu::IndexFormat, bundle: bool) {
+    let shader = ctx
+        .device
+        .create_shader_module(&wgpu::include_wgsl!("draw.wgsl"));
+
+    let indices_u16 = INDICES
+        .iter()
+        .map(|&index| index as u16)
+        .collect::<Vec<_>>();
+    let index_data: &[u8] = match format {
+        wgpu::IndexFormat::Uint16 => bytemuck::cast_slice(&indices_u16),
+        wgpu::IndexFormat::Uint32 => bytemuck::cast_slice(&INDICES),
+    };
+    let index_buffer = ctx
+        .device
+        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: index_data,
+            usage: wgpu::BufferUsages::INDEX,
+        });
+    // `index_count`, `instance_count`, `first_index`, `base_vertex` and `first_instance`
+    let indirect_buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: 5 * 4,
+        usage: wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    // `first_instance` is left to the zero initialization, which only happens if
+    // the draw is known to read all of the arguments.
+    ctx.queue.write_buffer(
+        &indirect_buffer,
+        0,
+        bytemuck::cast_slice(&[INDICES.len() as u32, 1, 0, 0]),
+    );
+
+    let output = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: std::mem::size_of_val(&INDICES) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let pipeline = ctx
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: None,
+            vertex: wgpu::VertexState {
+                buffers: &[],
+                entry_point: "vs_main",
+                module: &shader,
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::PointList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                entry_point: "fs_main",
+                module: &shader,
+                targets: &[wgpu::TextureFormat::Rgba8Unorm.into()],
+            }),
+        });
+
+    let bg = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: output.as_entire_binding(),
+        }],
+    });
+
+    let target = ctx
+        .device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default());
+
+    let bundle = if bundle {
+        let mut encoder =
+            ctx.device
+                .create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+                    label: None,
+                    color_formats: &[wgpu::TextureFormat::Rgba8Unorm],
+                    depth_stencil: None,
+                    sample_count: 1,
+                });
+        encoder.set_pipeline(&pipeline);
+        encoder.set_bind_group(0, &bg, &[]);
+        encoder.set_index_buffer(index_buffer.slice(..), format);
+        encoder.draw_indexed_indirect(&indirect_buffer, 0);
+        Some(encoder.finish(&wgpu::RenderBundleDescriptor { label: None }))
+    } else {
+        None
+    };
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view: &target,
+                resolve_target: None,
+                ops: wgpu::Operations::default(),
+            }],
+            depth_stencil_attachment: None,
+        });
+        match bundle {
+            Some(ref bundle) => rpass.execute_bundles(std::iter::once(bundle)),
+            None => {
+                rpass.set_pipeline(&pipeline);
+                rpass.set_bind_group(0, &bg, &[]);
+                rpass.set_index_buffer(index_buffer.slice(..), format);
+                rpass.draw_indexed_indirect(&indirect_buffer, 0);
+            }
+        }
+    }
+
+    ctx.queue.submit(Some(encoder.finish()));
+    let slice = output.slice(..);
+    let _ = slice.map_async(wgpu::MapMode::Read);
+    ctx.device.poll(wgpu::Maintain::Wait);
+    let data: Vec<u32> = bytemuck::cast_slice(&*slice.get_mapped_range()).to_vec();
+
+    assert_eq!(data, [1, 2, 3, 4, 5, 6]);
+}
+
+#[test]
+fn draw_indexed_indirect_u16() {
+    initialize_test(
+        TestParameters::default()
+            .test_features()
+            .downlevel_flags(wgpu::DownlevelFlags::INDIRECT_EXECUTION),
+        |ctx| draw_indexed_indirect(ctx, wgpu::IndexFormat::Uint16, false),
+    )
+}
+
+#[test]
+fn draw_indexed_indirect_u32() {
+    initialize_test(
+        TestParameters::default()
+            .test_features()
+            .downlevel_flags(wgpu::DownlevelFlags::INDIRECT_EXECUTION),
+        |ctx| draw_indexed_indirect(ctx, wgpu::IndexFormat::Uint32, false),
+    )
+}
+
+#[test]
+fn draw_indexed_indirect_bundle() {
+    initialize_test(
+        TestParameters::default()
+            .test_features()
+            .downlevel_flags(wgpu::DownlevelFlags::INDIRECT_EXECUTION),
+        |ctx| draw_indexed_indirect(ctx, wgpu::IndexFormat::Uint32, true),
+    )
+}
@@ -0,0 +1,151 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+/// Requests a device with all the limits of the adapter, and a flag that gets
+/// set by the validation errors it reports.
+fn device_at_limits(ctx: &TestingContext) -> (wgpu::Device, Arc<AtomicBool>) {
+    let (device, _queue) = pollster::block_on(ctx.adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: None,
+            features: wgpu::Features::empty(),
+            limits: ctx.adapter.limits(),
+        },
+        None,
+    ))
+    .unwrap();
+    let failed = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&failed);
+    device.on_uncaptured_error(move |_| {
+        flag.store(true, Ordering::Relaxed);
+    });
+    (device, failed)
+}
+
+#[test]
+fn texture_1d_at_limit() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let (device, failed) = device_at_limits(&ctx);
+        let max = device.limits().max_texture_dimension_1d;
+        let create = |width| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D1,
+                format: wgpu::TextureFormat::R8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            })
+        };
+
+        let _texture = create(max);
+        assert!(!failed.load(Ordering::Relaxed));
+        let _texture = create(max + 1);
+        assert!(failed.load(Ordering::Relaxed));
+    })
+}
+
+/// Creates a render pipeline passing `components` components from the vertex
+/// to the fragment stage, packed in `vec4<f32>` varyings as far as possible.
+fn create_inter_stage_pipeline(device: &wgpu::Device, components: u32) {
+    let remainder = match components % 4 {
+        0 => None,
+        1 => Some("f32"),
+        2 => Some("vec2<f32>"),
+        _ => Some("vec3<f32>"),
+    };
+    let types = (0..components / 4)
+        .map(|_| "vec4<f32>")
+        .chain(remainder)
+        .collect::<Vec<_>>();
+
+    let fields = types
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| format!("[[location({0})]] v{0}: {1};\n", i, ty))
+        .collect::<String>();
+    let writes = types
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| format!("out.v{} = {}(0.0);\n", i, ty))
+        .collect::<String>();
+    let inputs = types
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| format!("[[location({0})]] v{0}: {1}", i, ty))
+        .collect::<Vec<_>>()
+        .join(", ");
+    // Every component is read, so that none of them can be optimized out.
+    let sum = types
+        .iter()
+        .enumerate()
+        .map(|(i, &ty)| match ty {
+            "f32" => format!("v{}", i),
+            _ => format!("dot(v{0}, v{0})", i),
+        })
+        .collect::<Vec<_>>()
+        .join(" + ");
+    let source = format!(
+        "struct VertexOutput {{
+            [[builtin(position)]] position: vec4<f32>;
+            {}
+        }};
+
+        [[stage(vertex)]]
+        fn vs_main() -> VertexOutput {{
+            var out: VertexOutput;
+            out.position = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+            {}
+            return out;
+        }}
+
+        [[stage(fragment)]]
+        fn fs_main({}) -> [[location(0)]] vec4<f32> {{
+            return vec4<f32>({});
+        }}",
+        fields, writes, inputs, sum
+    );
+    let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+
+    let _pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: None,
+        vertex: wgpu::VertexState {
+            buffers: &[],
+            entry_point: "vs_main",
+            module: &shader,
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            entry_point: "fs_main",
+            module: &shader,
+            targets: &[wgpu::TextureFormat::Rgba8Unorm.into()],
+        }),
+    });
+}
+
+#[test]
+fn inter_stage_components_at_limit() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let (device, failed) = device_at_limits(&ctx);
+        let max = device.limits().max_inter_stage_shader_components;
+
+        create_inter_stage_pipeline(&device, max);
+        assert!(!failed.load(Ordering::Relaxed));
+        create_inter_stage_pipeline(&device, max + 1);
+        assert!(failed.load(Ordering::Relaxed));
+    })
+}
@@ -0,0 +1,18 @@
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] index: u32) -> [[builtin(position)]] vec4<f32> {
+    // a single triangle covering the whole target
+    let x = f32(i32(index & 1u) * 4 - 1);
+    let y = f32(i32(index & 2u) * 2 - 1);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+struct FragmentOutput {
+    [[location(0)]] blended: vec4<f32>;
+    [[location(1)]] opaque: vec4<f32>;
+};
+
+[[stage(fragment)]]
+fn fs_main() -> FragmentOutput {
+    let color = vec4<f32>(1.0, 1.0, 1.0, 0.5);
+    return FragmentOutput(color, color);
+}
@@ -0,0 +1,90 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+const SIZE: u32 = 4;
+
+fn create_target(
+    ctx: &TestingContext,
+    format: wgpu::TextureFormat,
+    size: u32,
+    sample_count: u32,
+) -> wgpu::TextureView {
+    ctx.device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// Resolves a multisampled `Rgba8Unorm` target into `resolve_target`, and returns
+/// whether the pass was rejected.
+fn resolve_fails(ctx: &TestingContext, resolve_target: &wgpu::TextureView) -> bool {
+    let failed = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&failed);
+    ctx.device.on_uncaptured_error(move |_| {
+        flag.store(true, Ordering::Relaxed);
+    });
+
+    let target = create_target(ctx, wgpu::TextureFormat::Rgba8Unorm, SIZE, 4);
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[wgpu::RenderPassColorAttachment {
+            view: &target,
+            resolve_target: Some(resolve_target),
+            ops: wgpu::Operations::default(),
+        }],
+        depth_stencil_attachment: None,
+    });
+    let _ = encoder.finish();
+
+    failed.load(Ordering::Relaxed)
+}
+
+#[test]
+fn matching_resolve_target() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let resolve_target = create_target(&ctx, wgpu::TextureFormat::Rgba8Unorm, SIZE, 1);
+        assert!(!resolve_fails(&ctx, &resolve_target));
+    })
+}
+
+#[test]
+fn resolve_target_format_mismatch() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let resolve_target = create_target(&ctx, wgpu::TextureFormat::Bgra8Unorm, SIZE, 1);
+        assert!(resolve_fails(&ctx, &resolve_target));
+    })
+}
+
+#[test]
+fn resolve_target_size_mismatch() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let resolve_target = create_target(&ctx, wgpu::TextureFormat::Rgba8Unorm, SIZE * 2, 1);
+        assert!(resolve_fails(&ctx, &resolve_target));
+    })
+}
+
+#[test]
+fn multisampled_resolve_target() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let resolve_target = create_target(&ctx, wgpu::TextureFormat::Rgba8Unorm, SIZE, 4);
+        assert!(resolve_fails(&ctx, &resolve_target));
+    })
+}
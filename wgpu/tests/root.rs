@@ -1,7 +1,19 @@
 // All files containing tests
 mod common;
 
+mod address_mode;
+mod buffer_layout;
+mod clamp_depth;
 mod device;
 mod example_wgsl;
+mod indexed_indirect;
 mod instance;
+mod limits;
+mod mrt_blend;
+mod resolve_target;
+mod shadow_map;
+mod stencil_sampling;
+mod storage_texture;
+mod timestamp_query;
 mod vertex_indices;
+mod write_mask;
@@ -0,0 +1,111 @@
+use std::num::NonZeroU32;
+
+use crate::common::{initialize_test, TestParameters};
+
+const SIZE: u32 = 4;
+
+#[test]
+fn depth_only_pipeline() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let shader = ctx
+            .device
+            .create_shader_module(&wgpu::include_wgsl!("shadow.wgsl"));
+
+        // No fragment stage nor color targets, like a shadow map pass.
+        let pipeline = ctx
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: None,
+                vertex: wgpu::VertexState {
+                    buffers: &[],
+                    entry_point: "vs_main",
+                    module: &shader,
+                },
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                fragment: None,
+            });
+
+        let shadow_map = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: SIZE,
+                height: SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth32Float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        });
+        let view = shadow_map.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bytes_per_row = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (bytes_per_row * SIZE) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            // Only the left half gets the occluder.
+            rpass.set_scissor_rect(0, 0, SIZE / 2, SIZE);
+            rpass.set_pipeline(&pipeline);
+            rpass.draw(0..3, 0..1);
+        }
+        encoder.copy_texture_to_buffer(
+            shadow_map.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: SIZE,
+                height: SIZE,
+                depth_or_array_layers: 1,
+            },
+        );
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let _ = slice.map_async(wgpu::MapMode::Read);
+        ctx.device.poll(wgpu::Maintain::Wait);
+        let data = slice.get_mapped_range();
+        for row in data.chunks(bytes_per_row as usize) {
+            let depths: &[f32] = bytemuck::cast_slice(&row[..(SIZE * 4) as usize]);
+            for (x, &depth) in depths.iter().enumerate() {
+                let expected = if (x as u32) < SIZE / 2 { 0.5 } else { 1.0 };
+                assert_eq!(depth, expected);
+            }
+        }
+    })
+}
@@ -0,0 +1,7 @@
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] index: u32) -> [[builtin(position)]] vec4<f32> {
+    // a single triangle covering the whole target, halfway into the depth range
+    let x = f32(i32(index & 1u) * 4 - 1);
+    let y = f32(i32(index & 2u) * 2 - 1);
+    return vec4<f32>(x, y, 0.5, 1.0);
+}
@@ -0,0 +1,150 @@
+use crate::common::{initialize_test, TestParameters};
+
+const SIZE: u32 = 4;
+const CLEAR_VALUE: u32 = 1;
+const REFERENCE: u32 = 0x42;
+
+#[test]
+fn sample_written_stencil() {
+    initialize_test(
+        TestParameters::default()
+            .test_features()
+            .downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+            // Stencil views are only implemented for Metal and Vulkan.
+            .backend_failure(wgpu::Backends::DX12 | wgpu::Backends::GL),
+        |ctx| {
+            let shader = ctx
+                .device
+                .create_shader_module(&wgpu::include_wgsl!("shader.wgsl"));
+
+            let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width: SIZE,
+                    height: SIZE,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Depth24PlusStencil8,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+            });
+            let attachment_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let stencil_view = texture.create_view(&wgpu::TextureViewDescriptor {
+                aspect: wgpu::TextureAspect::StencilOnly,
+                ..Default::default()
+            });
+
+            let stencil_face = wgpu::StencilFaceState {
+                compare: wgpu::CompareFunction::Always,
+                fail_op: wgpu::StencilOperation::Keep,
+                depth_fail_op: wgpu::StencilOperation::Keep,
+                pass_op: wgpu::StencilOperation::Replace,
+            };
+            let render_pipeline =
+                ctx.device
+                    .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: None,
+                        layout: None,
+                        vertex: wgpu::VertexState {
+                            buffers: &[],
+                            entry_point: "vs_main",
+                            module: &shader,
+                        },
+                        primitive: wgpu::PrimitiveState::default(),
+                        depth_stencil: Some(wgpu::DepthStencilState {
+                            format: wgpu::TextureFormat::Depth24PlusStencil8,
+                            depth_write_enabled: false,
+                            depth_compare: wgpu::CompareFunction::Always,
+                            stencil: wgpu::StencilState {
+                                front: stencil_face,
+                                back: stencil_face,
+                                read_mask: !0,
+                                write_mask: !0,
+                            },
+                            bias: wgpu::DepthBiasState::default(),
+                        }),
+                        multisample: wgpu::MultisampleState::default(),
+                        fragment: None,
+                    });
+            let compute_pipeline =
+                ctx.device
+                    .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                        label: None,
+                        layout: None,
+                        module: &shader,
+                        entry_point: "cs_main",
+                    });
+
+            let output = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: (SIZE * SIZE * 4) as u64,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+            let bg = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &compute_pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&stencil_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: output.as_entire_binding(),
+                    },
+                ],
+            });
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            {
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &attachment_view,
+                        depth_ops: None,
+                        stencil_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(CLEAR_VALUE),
+                            store: true,
+                        }),
+                    }),
+                });
+                // Only the left half gets the reference written.
+                rpass.set_scissor_rect(0, 0, SIZE / 2, SIZE);
+                rpass.set_pipeline(&render_pipeline);
+                rpass.set_stencil_reference(REFERENCE);
+                rpass.draw(0..3, 0..1);
+            }
+            {
+                let mut cpass =
+                    encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+                cpass.set_pipeline(&compute_pipeline);
+                cpass.set_bind_group(0, &bg, &[]);
+                cpass.dispatch(1, 1, 1);
+            }
+
+            ctx.queue.submit(Some(encoder.finish()));
+            let slice = output.slice(..);
+            let _ = slice.map_async(wgpu::MapMode::Read);
+            ctx.device.poll(wgpu::Maintain::Wait);
+            let data: Vec<u32> = bytemuck::cast_slice(&*slice.get_mapped_range()).to_vec();
+
+            let expected = (0..SIZE * SIZE)
+                .map(|i| {
+                    if i % SIZE < SIZE / 2 {
+                        REFERENCE
+                    } else {
+                        CLEAR_VALUE
+                    }
+                })
+                .collect::<Vec<u32>>();
+            assert_eq!(data, expected);
+        },
+    )
+}
@@ -0,0 +1,23 @@
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] index: u32) -> [[builtin(position)]] vec4<f32> {
+    // A triangle covering the whole target.
+    let x = f32(i32(index & 1u) * 4 - 1);
+    let y = f32(i32(index >> 1u) * 4 - 1);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+[[group(0), binding(0)]]
+var stencil: texture_2d<u32>;
+
+[[block]]
+struct Output {
+    arr: array<u32>;
+};
+
+[[group(0), binding(1)]]
+var<storage, read_write> output: Output;
+
+[[stage(compute), workgroup_size(4, 4)]]
+fn cs_main([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    output.arr[id.y * 4u + id.x] = textureLoad(stencil, vec2<i32>(id.xy), 0).r;
+}
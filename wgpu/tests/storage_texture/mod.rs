@@ -0,0 +1,231 @@
+use std::num::NonZeroU32;
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+const SIZE: u32 = 4;
+
+/// Submits `encoder` after copying `texture` into a buffer, and returns the texels.
+fn read_back(
+    ctx: &TestingContext,
+    mut encoder: wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    texel_size: u32,
+) -> Vec<u8> {
+    let bytes_per_row = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (bytes_per_row * SIZE) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        wgpu::Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+    );
+
+    ctx.queue.submit(Some(encoder.finish()));
+    let slice = buffer.slice(..);
+    let _ = slice.map_async(wgpu::MapMode::Read);
+    ctx.device.poll(wgpu::Maintain::Wait);
+    let data = slice.get_mapped_range();
+
+    data.chunks(bytes_per_row as usize)
+        .flat_map(|row| row[..(SIZE * texel_size) as usize].iter().copied())
+        .collect()
+}
+
+#[test]
+fn compute_write_r8unorm() {
+    initialize_test(
+        TestParameters::default()
+            .test_features()
+            .features(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+            .downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS),
+        |ctx| {
+            let format_features = ctx
+                .adapter
+                .get_texture_format_features(wgpu::TextureFormat::R8Unorm);
+            if !format_features
+                .allowed_usages
+                .contains(wgpu::TextureUsages::STORAGE_BINDING)
+            {
+                println!("TEST SKIPPED: R8Unorm STORAGE NOT SUPPORTED");
+                return;
+            }
+
+            let shader = ctx
+                .device
+                .create_shader_module(&wgpu::include_wgsl!("write.wgsl"));
+
+            let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width: SIZE,
+                    height: SIZE,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::R8Unorm,
+                usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+            let pipeline = ctx
+                .device
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: None,
+                    layout: None,
+                    module: &shader,
+                    entry_point: "cs_main",
+                });
+
+            let bg = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                }],
+            });
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            {
+                let mut cpass =
+                    encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+                cpass.set_pipeline(&pipeline);
+                cpass.set_bind_group(0, &bg, &[]);
+                cpass.dispatch(1, 1, 1);
+            }
+            let texels = read_back(&ctx, encoder, &texture, 1);
+            let expected = (0..(SIZE * SIZE) as u8).collect::<Vec<u8>>();
+            assert_eq!(texels, expected);
+        },
+    )
+}
+
+#[test]
+fn compute_write_then_sample() {
+    initialize_test(
+        TestParameters::default()
+            .test_features()
+            .downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS),
+        |ctx| {
+            let shader = ctx
+                .device
+                .create_shader_module(&wgpu::include_wgsl!("sample.wgsl"));
+
+            let texture_desc = wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width: SIZE,
+                    height: SIZE,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+            };
+            let storage = ctx.device.create_texture(&texture_desc);
+            let storage_view = storage.create_view(&wgpu::TextureViewDescriptor::default());
+            let target = ctx.device.create_texture(&wgpu::TextureDescriptor {
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                ..texture_desc
+            });
+            let target_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+            let compute_pipeline =
+                ctx.device
+                    .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                        label: None,
+                        layout: None,
+                        module: &shader,
+                        entry_point: "cs_main",
+                    });
+            let render_pipeline =
+                ctx.device
+                    .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: None,
+                        layout: None,
+                        vertex: wgpu::VertexState {
+                            buffers: &[],
+                            entry_point: "vs_main",
+                            module: &shader,
+                        },
+                        primitive: wgpu::PrimitiveState::default(),
+                        depth_stencil: None,
+                        multisample: wgpu::MultisampleState::default(),
+                        fragment: Some(wgpu::FragmentState {
+                            entry_point: "fs_main",
+                            module: &shader,
+                            targets: &[wgpu::TextureFormat::Rgba8Unorm.into()],
+                        }),
+                    });
+
+            let compute_bg = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &compute_pipeline.get_bind_group_layout(0),
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&storage_view),
+                }],
+            });
+            let render_bg = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &render_pipeline.get_bind_group_layout(0),
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&storage_view),
+                }],
+            });
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            {
+                let mut cpass =
+                    encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+                cpass.set_pipeline(&compute_pipeline);
+                cpass.set_bind_group(0, &compute_bg, &[]);
+                cpass.dispatch(1, 1, 1);
+            }
+            {
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[wgpu::RenderPassColorAttachment {
+                        view: &target_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations::default(),
+                    }],
+                    depth_stencil_attachment: None,
+                });
+                rpass.set_pipeline(&render_pipeline);
+                rpass.set_bind_group(0, &render_bg, &[]);
+                rpass.draw(0..3, 0..1);
+            }
+
+            let texels = read_back(&ctx, encoder, &target, 4);
+            let expected = (0..SIZE)
+                .flat_map(|y| (0..SIZE).flat_map(move |x| [x as u8, y as u8, 0, 255]))
+                .collect::<Vec<u8>>();
+            assert_eq!(texels, expected);
+        },
+    )
+}
@@ -0,0 +1,24 @@
+[[group(0), binding(0)]]
+var output: texture_storage_2d<rgba8unorm, write>;
+
+[[stage(compute), workgroup_size(4, 4)]]
+fn cs_main([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    let value = vec4<f32>(f32(id.x) / 255.0, f32(id.y) / 255.0, 0.0, 1.0);
+    textureStore(output, vec2<i32>(id.xy), value);
+}
+
+[[group(0), binding(1)]]
+var input: texture_2d<f32>;
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] index: u32) -> [[builtin(position)]] vec4<f32> {
+    // a single triangle covering the whole target
+    let x = f32(i32(index & 1u) * 4 - 1);
+    let y = f32(i32(index & 2u) * 2 - 1);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] pos: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    return textureLoad(input, vec2<i32>(pos.xy), 0);
+}
@@ -0,0 +1,8 @@
+[[group(0), binding(0)]]
+var output: texture_storage_2d<r8unorm, write>;
+
+[[stage(compute), workgroup_size(4, 4)]]
+fn cs_main([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    let value = f32(id.y * 4u + id.x) / 255.0;
+    textureStore(output, vec2<i32>(id.xy), vec4<f32>(value, 0.0, 0.0, 1.0));
+}
@@ -0,0 +1,19 @@
+[[block]]
+struct Output {
+    value: u32;
+};
+
+[[group(0), binding(0)]]
+var<storage, read_write> output: Output;
+
+[[stage(compute), workgroup_size(1)]]
+fn cs_main() {
+    var value: u32 = 0u;
+    loop {
+        if (value >= 1048576u) {
+            break;
+        }
+        value = value + 1u;
+        output.value = value;
+    }
+}
@@ -0,0 +1,83 @@
+use std::time::Instant;
+
+use crate::common::{initialize_test, TestParameters};
+
+#[test]
+fn dispatch_duration() {
+    initialize_test(
+        TestParameters::default()
+            .test_features()
+            .features(wgpu::Features::TIMESTAMP_QUERY)
+            .downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS),
+        |ctx| {
+            let shader = ctx
+                .device
+                .create_shader_module(&wgpu::include_wgsl!("busy.wgsl"));
+
+            let output = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: 4,
+                usage: wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            });
+            let query_set = ctx.device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: None,
+                count: 2,
+                ty: wgpu::QueryType::Timestamp,
+            });
+            let resolved = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: 2 * wgpu::QUERY_SIZE as u64,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+
+            let pipeline = ctx
+                .device
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: None,
+                    layout: None,
+                    module: &shader,
+                    entry_point: "cs_main",
+                });
+            let bg = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: output.as_entire_binding(),
+                }],
+            });
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            encoder.write_timestamp(&query_set, 0);
+            {
+                let mut cpass =
+                    encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+                cpass.set_pipeline(&pipeline);
+                cpass.set_bind_group(0, &bg, &[]);
+                cpass.dispatch(1, 1, 1);
+            }
+            encoder.write_timestamp(&query_set, 1);
+            encoder.resolve_query_set(&query_set, 0..2, &resolved, 0);
+
+            let start = Instant::now();
+            ctx.queue.submit(Some(encoder.finish()));
+            let slice = resolved.slice(..);
+            let _ = slice.map_async(wgpu::MapMode::Read);
+            ctx.device.poll(wgpu::Maintain::Wait);
+            let cpu_elapsed = start.elapsed();
+
+            let timestamps: Vec<u64> = bytemuck::cast_slice(&*slice.get_mapped_range()).to_vec();
+            let period = ctx.queue.get_timestamp_period() as f64;
+            let gpu_nanos = (timestamps[1] - timestamps[0]) as f64 * period;
+
+            // The dispatch takes time on the GPU, but can't take longer
+            // than it took the CPU to see it complete.
+            assert!(gpu_nanos > 0.0);
+            assert!(gpu_nanos <= cpu_elapsed.as_nanos() as f64);
+        },
+    )
+}
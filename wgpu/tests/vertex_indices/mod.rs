@@ -176,3 +176,106 @@ fn draw_instanced_offset() {
         },
     )
 }
+
+#[test]
+fn draw_indirect_first_instance() {
+    initialize_test(
+        TestParameters::default()
+            .test_features()
+            .features(wgpu::Features::INDIRECT_FIRST_INSTANCE)
+            .downlevel_flags(wgpu::DownlevelFlags::INDIRECT_EXECUTION),
+        |ctx| {
+            let shader = ctx
+                .device
+                .create_shader_module(&wgpu::include_wgsl!("draw.vert.wgsl"));
+            let pipeline = ctx
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: None,
+                    layout: None,
+                    vertex: wgpu::VertexState {
+                        buffers: &[],
+                        entry_point: "vs_main",
+                        module: &shader,
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::PointList,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    fragment: Some(wgpu::FragmentState {
+                        entry_point: "fs_main",
+                        module: &shader,
+                        targets: &[wgpu::TextureFormat::Rgba8Unorm.into()],
+                    }),
+                });
+
+            let output = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: 4 * 6,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+            let bg = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: output.as_entire_binding(),
+                }],
+            });
+            // `vertex_count`, `instance_count`, `first_vertex` and `first_instance`
+            let indirect_buffer =
+                ctx.device
+                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: None,
+                        contents: bytemuck::cast_slice(&[3u32, 1, 0, 1]),
+                        usage: wgpu::BufferUsages::INDIRECT,
+                    });
+            let target = ctx
+                .device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: None,
+                    size: wgpu::Extent3d {
+                        width: 1,
+                        height: 1,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default());
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            {
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[wgpu::RenderPassColorAttachment {
+                        view: &target,
+                        resolve_target: None,
+                        ops: wgpu::Operations::default(),
+                    }],
+                    depth_stencil_attachment: None,
+                });
+                rpass.set_pipeline(&pipeline);
+                rpass.set_bind_group(0, &bg, &[]);
+                rpass.draw_indirect(&indirect_buffer, 0);
+            }
+
+            ctx.queue.submit(Some(encoder.finish()));
+            let slice = output.slice(..);
+            let _ = slice.map_async(wgpu::MapMode::Read);
+            ctx.device.poll(wgpu::Maintain::Wait);
+            let data: Vec<u32> = bytemuck::cast_slice(&*slice.get_mapped_range()).to_vec();
+
+            // Only the second instance is drawn, writing its vertices past the first ones.
+            assert_eq!(data, [0, 0, 0, 3, 4, 5]);
+        },
+    )
+}
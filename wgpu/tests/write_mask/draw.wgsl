@@ -0,0 +1,17 @@
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] index: u32) -> [[builtin(position)]] vec4<f32> {
+    // a single triangle covering the whole target
+    let x = f32(i32(index & 1u) * 4 - 1);
+    let y = f32(i32(index & 2u) * 2 - 1);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+struct FragmentOutput {
+    [[location(0)]] first: vec4<f32>;
+    [[location(1)]] second: vec4<f32>;
+};
+
+[[stage(fragment)]]
+fn fs_main() -> FragmentOutput {
+    return FragmentOutput(vec4<f32>(1.0), vec4<f32>(1.0));
+}
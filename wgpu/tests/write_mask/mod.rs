@@ -0,0 +1,140 @@
+use std::num::NonZeroU32;
+
+use crate::common::{initialize_test, TestParameters};
+
+const SIZE: u32 = 4;
+
+#[test]
+fn per_target_write_mask() {
+    initialize_test(
+        TestParameters::default().downlevel_flags(wgpu::DownlevelFlags::INDEPENDENT_BLENDING),
+        |ctx| {
+            let shader = ctx
+                .device
+                .create_shader_module(&wgpu::include_wgsl!("draw.wgsl"));
+
+            let pipeline = ctx
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: None,
+                    layout: None,
+                    vertex: wgpu::VertexState {
+                        buffers: &[],
+                        entry_point: "vs_main",
+                        module: &shader,
+                    },
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    fragment: Some(wgpu::FragmentState {
+                        entry_point: "fs_main",
+                        module: &shader,
+                        targets: &[
+                            wgpu::ColorTargetState {
+                                format: wgpu::TextureFormat::Rgba8Unorm,
+                                blend: None,
+                                write_mask: wgpu::ColorWrites::ALL,
+                            },
+                            wgpu::ColorTargetState {
+                                format: wgpu::TextureFormat::Rgba8Unorm,
+                                blend: None,
+                                write_mask: wgpu::ColorWrites::COLOR,
+                            },
+                        ],
+                    }),
+                });
+
+            let targets = (0..2)
+                .map(|_| {
+                    ctx.device.create_texture(&wgpu::TextureDescriptor {
+                        label: None,
+                        size: wgpu::Extent3d {
+                            width: SIZE,
+                            height: SIZE,
+                            depth_or_array_layers: 1,
+                        },
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: wgpu::TextureFormat::Rgba8Unorm,
+                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                            | wgpu::TextureUsages::COPY_SRC,
+                    })
+                })
+                .collect::<Vec<_>>();
+            let views = targets
+                .iter()
+                .map(|target| target.create_view(&wgpu::TextureViewDescriptor::default()))
+                .collect::<Vec<_>>();
+
+            let bytes_per_row = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+            let buffers = (0..2)
+                .map(|_| {
+                    ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                        label: None,
+                        size: (bytes_per_row * SIZE) as u64,
+                        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                        mapped_at_creation: false,
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            {
+                let color_attachments = views
+                    .iter()
+                    .map(|view| wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: true,
+                        },
+                    })
+                    .collect::<Vec<_>>();
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &color_attachments,
+                    depth_stencil_attachment: None,
+                });
+                rpass.set_pipeline(&pipeline);
+                rpass.draw(0..3, 0..1);
+            }
+            for (target, buffer) in targets.iter().zip(buffers.iter()) {
+                encoder.copy_texture_to_buffer(
+                    target.as_image_copy(),
+                    wgpu::ImageCopyBuffer {
+                        buffer,
+                        layout: wgpu::ImageDataLayout {
+                            offset: 0,
+                            bytes_per_row: NonZeroU32::new(bytes_per_row),
+                            rows_per_image: None,
+                        },
+                    },
+                    wgpu::Extent3d {
+                        width: SIZE,
+                        height: SIZE,
+                        depth_or_array_layers: 1,
+                    },
+                );
+            }
+            ctx.queue.submit(Some(encoder.finish()));
+
+            // The second target doesn't get its alpha written.
+            let expected_texels = [[255u8, 255, 255, 255], [255, 255, 255, 0]];
+            for (buffer, expected) in buffers.iter().zip(expected_texels.iter()) {
+                let slice = buffer.slice(..);
+                let _ = slice.map_async(wgpu::MapMode::Read);
+                ctx.device.poll(wgpu::Maintain::Wait);
+                let data = slice.get_mapped_range();
+                for row in data.chunks(bytes_per_row as usize) {
+                    for texel in row[..(SIZE * 4) as usize].chunks(4) {
+                        assert_eq!(texel, expected);
+                    }
+                }
+            }
+        },
+    )
+}